@@ -0,0 +1,112 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::anyhow;
+use arrow::{
+    array::{AsArray, RecordBatch, StringArray},
+    datatypes::{DataType, Field, Schema},
+};
+use bytesize::ByteSize;
+use datafusion::datasource::TableProvider;
+
+/// A dataset loaded in memory, the source is registered again when it is uncached.
+#[derive(Clone)]
+pub struct CachedTable {
+    pub source: Arc<dyn TableProvider>,
+    pub memory: usize,
+}
+
+/// Add the memory used by the cached datasets to the batch of the `list` command,
+/// the name of the dataset is the first column.
+pub fn with_memory(
+    batch: RecordBatch,
+    cached: &HashMap<String, CachedTable>,
+) -> anyhow::Result<RecordBatch> {
+    let names = batch
+        .column(0)
+        .as_string_opt::<i32>()
+        .ok_or_else(|| anyhow!("Expect the names of the datasets"))?;
+    let memory = StringArray::from_iter(names.iter().map(|name| {
+        let cached = cached.get(name?)?;
+        Some(ByteSize(cached.memory as u64).to_string())
+    }));
+
+    let mut fields = batch.schema().fields().to_vec();
+    fields.push(Arc::new(Field::new("memory", DataType::Utf8, true)));
+    let mut columns = batch.columns().to_vec();
+    columns.push(Arc::new(memory));
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns,
+    )?)
+}
+
+/// Split the batches into `n` partitions of about the same number of rows, the
+/// batches are sliced without copying their data.
+pub fn split_partitions(
+    batches: impl Iterator<Item = RecordBatch>,
+    rows: usize,
+    n: usize,
+) -> Vec<Vec<RecordBatch>> {
+    let n = n.max(1);
+    let size = rows.div_ceil(n).max(1);
+    let mut partitions = vec![vec![]; n];
+    let mut placed = 0;
+    for batch in batches {
+        let mut offset = 0;
+        while offset < batch.num_rows() {
+            let current = (placed / size).min(n - 1);
+            let left = batch.num_rows() - offset;
+            let len = if current == n - 1 {
+                left
+            } else {
+                (size - placed % size).min(left)
+            };
+            partitions[current].push(batch.slice(offset, len));
+            offset += len;
+            placed += len;
+        }
+    }
+    partitions
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{array::Int32Array, datatypes::Int32Type};
+
+    use super::*;
+
+    fn batch(values: std::ops::Range<i32>) -> RecordBatch {
+        let schema = Schema::new(vec![Field::new("n", DataType::Int32, false)]);
+        let column = Int32Array::from_iter_values(values);
+        RecordBatch::try_new(Arc::new(schema), vec![Arc::new(column)]).unwrap()
+    }
+
+    fn values(partition: &[RecordBatch]) -> Vec<i32> {
+        partition
+            .iter()
+            .flat_map(|b| b.column(0).as_primitive::<Int32Type>().values().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn split_partitions_should_keep_the_rows_in_order() {
+        let batches = || [batch(0..4), batch(4..8), batch(8..10)].into_iter();
+
+        let partitions = split_partitions(batches(), 10, 4);
+        let sizes: Vec<_> = partitions.iter().map(|p| values(p).len()).collect();
+        assert_eq!(sizes, vec![3, 3, 3, 1]);
+        assert_eq!(values(&partitions[1]), vec![3, 4, 5]);
+
+        let partitions = split_partitions(batches(), 10, 3);
+        let sizes: Vec<_> = partitions.iter().map(|p| values(p).len()).collect();
+        assert_eq!(sizes, vec![4, 4, 2]);
+        assert_eq!(values(&partitions.concat()), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn split_partitions_should_leave_extra_partitions_empty() {
+        let partitions = split_partitions([batch(0..2)].into_iter(), 2, 4);
+        let sizes: Vec<_> = partitions.iter().map(|p| values(p).len()).collect();
+        assert_eq!(sizes, vec![1, 1, 0, 0]);
+    }
+}
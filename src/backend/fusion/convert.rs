@@ -0,0 +1,380 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail};
+use arrow::{
+    array::AsArray,
+    datatypes::{DataType, Field, Schema, UInt64Type},
+};
+use datafusion::{
+    common::config::ConfigField,
+    dataframe::DataFrameWriteOptions,
+    logical_expr::{cast, ident},
+    prelude::{CsvReadOptions, DataFrame, NdJsonReadOptions, ParquetReadOptions, SessionContext},
+};
+
+use super::memory::query_error;
+use crate::cli::{ConvertOpts, DatasetConn};
+
+/// Write the rows of a file to another one, batch by batch: the whole file is never
+/// in memory. Returns the number of rows written.
+pub async fn convert(ctx: &SessionContext, opts: &ConvertOpts) -> anyhow::Result<u64> {
+    let output = opts.output.path();
+    if Path::new(output).exists() {
+        bail!("{} already exists", output);
+    }
+    let has_parquet_options =
+        opts.compression.is_some() || opts.row_group_size.is_some() || !opts.options.is_empty();
+    if has_parquet_options && !matches!(opts.output, DatasetConn::Parquet(_)) {
+        bail!("The parquet writer options need a parquet output");
+    }
+
+    let mut df = read(ctx, &opts.input, None).await?;
+    if !opts.schema.is_empty() {
+        let schema = override_schema(ctx, df.schema().inner(), &opts.schema).await?;
+        df = match &opts.input {
+            // the text files are parsed as the new types, the parquet columns are cast
+            DatasetConn::Parquet(_) => cast_columns(df, &schema)?,
+            conn => read(ctx, conn, Some(&schema)).await?,
+        };
+    }
+    if !opts.partition_by.is_empty() {
+        df = partition_columns(df, &opts.partition_by)?;
+    }
+
+    let write_options = DataFrameWriteOptions::new().with_partition_by(opts.partition_by.clone());
+    let table_options = ctx.state().default_table_options();
+    let result = match &opts.output {
+        DatasetConn::Postgres(_) => bail!("Converting to postgres is not supported"),
+        DatasetConn::Parquet(path) => {
+            let mut options = table_options.parquet;
+            if let Some(compression) = &opts.compression {
+                options.set("compression", compression)?;
+            }
+            if let Some(size) = opts.row_group_size {
+                options.global.max_row_group_size = size;
+            }
+            for (key, value) in &opts.options {
+                options.set(key, value)?;
+            }
+            df.write_parquet(path, write_options, Some(options)).await
+        }
+        DatasetConn::Csv(file) => {
+            let mut options = table_options.csv;
+            options.has_header = Some(true);
+            options.compression = file.compression.into();
+            df.write_csv(&file.filename, write_options, Some(options))
+                .await
+        }
+        DatasetConn::NdJson(file) => {
+            let mut options = table_options.json;
+            options.compression = file.compression.into();
+            df.write_json(&file.filename, write_options, Some(options))
+                .await
+        }
+    };
+    let batches = result.map_err(|e| {
+        // the output did not exist, don't leave a part of it
+        let path = Path::new(output);
+        let _ = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        query_error(e)
+    })?;
+    let rows = batches
+        .iter()
+        .map(|batch| batch.column(0).as_primitive::<UInt64Type>().value(0))
+        .sum();
+    Ok(rows)
+}
+
+async fn read(
+    ctx: &SessionContext,
+    conn: &DatasetConn,
+    schema: Option<&Schema>,
+) -> anyhow::Result<DataFrame> {
+    let df = match conn {
+        DatasetConn::Postgres(_) => bail!("Postgres connection is not supported yet"),
+        DatasetConn::Parquet(path) => {
+            ctx.read_parquet(path.as_str(), ParquetReadOptions::default())
+                .await?
+        }
+        DatasetConn::Csv(file) => {
+            let mut options = CsvReadOptions::default()
+                .file_extension(&file.ext)
+                .file_compression_type(file.compression);
+            if let Some(schema) = schema {
+                options = options.schema(schema);
+            }
+            ctx.read_csv(file.filename.as_str(), options).await?
+        }
+        DatasetConn::NdJson(file) => {
+            let mut options = NdJsonReadOptions::default()
+                .file_extension(&file.ext)
+                .file_compression_type(file.compression);
+            if let Some(schema) = schema {
+                options = options.schema(schema);
+            }
+            ctx.read_json(file.filename.as_str(), options).await?
+        }
+    };
+    Ok(df)
+}
+
+/// The inferred schema with the types of the overrides, given in SQL.
+async fn override_schema(
+    ctx: &SessionContext,
+    schema: &Schema,
+    overrides: &[(String, String)],
+) -> anyhow::Result<Schema> {
+    let mut fields: Vec<Field> = schema.fields().iter().map(|f| f.as_ref().clone()).collect();
+    for (name, sql_type) in overrides {
+        let field = fields
+            .iter_mut()
+            .find(|f| f.name() == name)
+            .ok_or_else(|| anyhow!("No column {} in the input", name))?;
+        let df = ctx
+            .sql(&format!("SELECT CAST(NULL AS {})", sql_type))
+            .await
+            .map_err(|e| anyhow!("Invalid type {} of column {}: {}", sql_type, name, e))?;
+        let data_type = df.schema().field(0).data_type().clone();
+        *field = Field::new(name, data_type, true);
+    }
+    Ok(Schema::new(fields))
+}
+
+fn cast_columns(df: DataFrame, schema: &Schema) -> anyhow::Result<DataFrame> {
+    let exprs = schema
+        .fields()
+        .iter()
+        .map(|f| cast(ident(f.name()), f.data_type().clone()).alias(f.name()))
+        .collect();
+    Ok(df.select(exprs)?)
+}
+
+/// The directories are named after the values of the partition columns, which
+/// DataFusion only writes as strings.
+fn partition_columns(df: DataFrame, columns: &[String]) -> anyhow::Result<DataFrame> {
+    for column in columns {
+        if df.schema().field_with_unqualified_name(column).is_err() {
+            bail!("No column {} to partition by", column);
+        }
+    }
+    let exprs = df
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| {
+            if columns.contains(f.name()) && f.data_type() != &DataType::Utf8 {
+                cast(ident(f.name()), DataType::Utf8).alias(f.name())
+            } else {
+                ident(f.name())
+            }
+        })
+        .collect();
+    Ok(df.select(exprs)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use arrow::{
+        array::AsArray,
+        datatypes::{DataType, Int32Type},
+    };
+    use clap::Parser;
+    use datafusion::prelude::{ParquetReadOptions, SessionConfig, SessionContext};
+    use parquet::{
+        basic::Compression,
+        file::reader::{FileReader, SerializedFileReader},
+    };
+
+    use super::convert;
+    use crate::cli::ConvertOpts;
+
+    /// An empty temp dir for a test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn opts(input: &Path, output: &Path, args: &[&str]) -> ConvertOpts {
+        let files = ["convert", input.to_str().unwrap(), output.to_str().unwrap()];
+        ConvertOpts::try_parse_from(files.iter().chain(args)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn convert_should_override_the_inferred_types() {
+        let dir = temp_dir("taotie_convert_schema");
+        let input = dir.join("places.csv");
+        fs::write(&input, "city,zip\nrome,00118\nturin,10121\n").unwrap();
+        let ctx = SessionContext::new();
+
+        // the zip codes are inferred as numbers, which loses the leading zero
+        let text = dir.join("text.parquet");
+        convert(&ctx, &opts(&input, &text, &["--schema", "zip=varchar"]))
+            .await
+            .unwrap();
+        let df = ctx.read_parquet(text.to_str().unwrap(), Default::default());
+        let batches = df.await.unwrap().collect().await.unwrap();
+        let zips: Vec<_> = batches[0].column(1).as_string::<i32>().iter().collect();
+        assert_eq!(zips, [Some("00118"), Some("10121")]);
+
+        // the parquet columns are cast
+        let numbers = dir.join("numbers.parquet");
+        convert(&ctx, &opts(&text, &numbers, &["-s", "zip=int"]))
+            .await
+            .unwrap();
+        let df = ctx.read_parquet(numbers.to_str().unwrap(), Default::default());
+        let batches = df.await.unwrap().collect().await.unwrap();
+        let zips = batches[0].column(1).as_primitive::<Int32Type>();
+        assert_eq!(zips.values().to_vec(), [118, 10121]);
+
+        let err = convert(&ctx, &opts(&input, &dir.join("x.csv"), &["-s", "zap=int"]))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "No column zap in the input");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn convert_should_apply_the_parquet_options() {
+        let dir = temp_dir("taotie_convert_options");
+        let input = dir.join("numbers.csv");
+        fs::write(&input, "n\n1\n2\n3\n4\n5\n").unwrap();
+        let output = dir.join("numbers.parquet");
+        let args = [
+            "--compression",
+            "zstd(3)",
+            "--row-group-size",
+            "2",
+            "--option",
+            "bloom_filter_on_write=true",
+        ];
+        let ctx = SessionContext::new();
+        assert_eq!(
+            convert(&ctx, &opts(&input, &output, &args)).await.unwrap(),
+            5
+        );
+
+        let reader = SerializedFileReader::new(fs::File::open(&output).unwrap()).unwrap();
+        let metadata = reader.metadata();
+        let rows: Vec<_> = metadata.row_groups().iter().map(|g| g.num_rows()).collect();
+        assert_eq!(rows, [2, 2, 1]);
+        let column = metadata.row_group(0).column(0);
+        // the level is not kept in the metadata
+        assert!(matches!(column.compression(), Compression::ZSTD(_)));
+        assert!(column.bloom_filter_offset().is_some());
+
+        let invalid = dir.join("invalid.parquet");
+        let err = convert(&ctx, &opts(&input, &invalid, &["-o", "no_such_option=1"]))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no_such_option"), "{}", err);
+        assert!(!invalid.exists());
+
+        let csv = dir.join("numbers.out.csv");
+        let err = convert(&ctx, &opts(&input, &csv, &["--row-group-size", "2"]))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The parquet writer options need a parquet output"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn convert_should_not_overwrite_an_existing_output() {
+        let dir = temp_dir("taotie_convert_existing");
+        let input = dir.join("numbers.csv");
+        fs::write(&input, "n\n1\n").unwrap();
+        let output = dir.join("numbers.ndjson");
+        fs::write(&output, "kept").unwrap();
+
+        let ctx = SessionContext::new();
+        let err = convert(&ctx, &opts(&input, &output, &[]))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("{} already exists", output.display())
+        );
+        assert_eq!(fs::read_to_string(&output).unwrap(), "kept");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn convert_should_remove_the_output_of_a_failed_write() {
+        let dir = temp_dir("taotie_convert_failed");
+        let input = dir.join("numbers.csv");
+        let mut content = String::from("n\n");
+        for n in 0..100 {
+            content.push_str(&format!("{}\n", n));
+        }
+        content.push_str("many\n");
+        fs::write(&input, content).unwrap();
+        let output = dir.join("numbers.ndjson");
+
+        // the first batches are written before the last row fails to parse
+        let ctx = SessionContext::new_with_config(SessionConfig::new().with_batch_size(10));
+        let err = convert(&ctx, &opts(&input, &output, &["-s", "n=int"]))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("many"), "{}", err);
+        assert!(!output.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn convert_should_write_a_partitioned_parquet_dataset() {
+        let dir = temp_dir("taotie_convert_partitioned");
+        let input = dir.join("players.csv");
+        fs::write(&input, "name,team,goals\na,x,1\nb,y,2\nc,x,3\n").unwrap();
+        let output = dir.join("players.parquet");
+        let opts = ConvertOpts::try_parse_from([
+            "convert",
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            "--partition-by",
+            "team",
+        ])
+        .unwrap();
+
+        let ctx = SessionContext::new();
+        assert_eq!(convert(&ctx, &opts).await.unwrap(), 3);
+        assert!(output.join("team=x").is_dir());
+        assert!(output.join("team=y").is_dir());
+
+        let options = ParquetReadOptions::default()
+            .table_partition_cols(vec![("team".to_string(), DataType::Utf8)]);
+        ctx.register_parquet("players", output.to_str().unwrap(), options)
+            .await
+            .unwrap();
+        let batches = ctx
+            .sql("SELECT team, sum(goals) FROM players GROUP BY team ORDER BY team")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let batch = arrow::compute::concat_batches(&batches[0].schema(), &batches).unwrap();
+        let teams = arrow::compute::cast(batch.column(0), &DataType::Utf8).unwrap();
+        let teams: Vec<_> = teams.as_string::<i32>().iter().flatten().collect();
+        assert_eq!(teams, ["x", "y"]);
+        let goals = arrow::compute::cast(batch.column(1), &DataType::Int64).unwrap();
+        let goals: Vec<_> = goals
+            .as_primitive::<arrow::datatypes::Int64Type>()
+            .values()
+            .to_vec();
+        assert_eq!(goals, [4, 2]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -49,7 +49,7 @@ impl DescribeDataFrame {
                 .collect::<Vec<_>>(),
         ))];
         // collect recordBatch
-        let batches = vec![
+        let batches = [
             self.count(),
             self.null_count(),
             self.mean(),
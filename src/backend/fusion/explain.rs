@@ -0,0 +1,121 @@
+use std::{fmt::Write, sync::Arc, time::Instant};
+
+use datafusion::{
+    physical_plan::{display::DisplayableExecutionPlan, displayable, execute_stream},
+    prelude::DataFrame,
+};
+use futures::StreamExt;
+
+use super::memory::query_error;
+
+/// Render the logical and physical plans of the query as indented trees. With
+/// `analyze` the query is executed and the metrics of each operator are shown.
+pub async fn explain(df: DataFrame, analyze: bool, verbose: bool) -> anyhow::Result<String> {
+    let mut output = String::new();
+    let logical = df.logical_plan();
+    if verbose {
+        writeln!(output, "Logical Plan:")?;
+        write_indented(&mut output, logical.display_indent_schema())?;
+        writeln!(output)?;
+    }
+
+    let optimized = df.clone().into_optimized_plan()?;
+    writeln!(output, "Optimized Logical Plan:")?;
+    if verbose {
+        write_indented(&mut output, optimized.display_indent_schema())?;
+    } else {
+        write_indented(&mut output, optimized.display_indent())?;
+    }
+    writeln!(output)?;
+
+    let task_ctx = Arc::new(df.task_ctx());
+    let plan = df.create_physical_plan().await?;
+    if !analyze {
+        writeln!(output, "Physical Plan:")?;
+        write_indented(&mut output, displayable(plan.as_ref()).indent(verbose))?;
+        return Ok(output.trim_end().to_string());
+    }
+
+    let start = Instant::now();
+    // only the rows are counted, the batches are dropped as they come
+    let mut stream = execute_stream(plan.clone(), task_ctx).map_err(query_error)?;
+    let mut rows = 0;
+    while let Some(batch) = stream.next().await {
+        rows += batch.map_err(query_error)?.num_rows();
+    }
+    let elapsed = start.elapsed();
+
+    writeln!(output, "Physical Plan (analyzed):")?;
+    // verbose shows the metrics of every partition instead of the aggregated ones
+    let displayable = if verbose {
+        DisplayableExecutionPlan::with_full_metrics(plan.as_ref())
+    } else {
+        DisplayableExecutionPlan::with_metrics(plan.as_ref())
+    };
+    write_indented(&mut output, displayable.indent(verbose))?;
+    writeln!(output)?;
+    writeln!(output, "Output rows: {}, elapsed: {:.3?}", rows, elapsed)?;
+    Ok(output.trim_end().to_string())
+}
+
+fn write_indented(output: &mut String, plan: impl std::fmt::Display) -> std::fmt::Result {
+    for line in plan.to_string().lines() {
+        writeln!(output, "  {}", line)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::{CsvReadOptions, SessionContext};
+
+    use super::explain;
+
+    async fn juve() -> SessionContext {
+        let ctx = SessionContext::new();
+        ctx.register_csv("juve", "assets/juventus.csv", CsvReadOptions::default())
+            .await
+            .unwrap();
+        ctx
+    }
+
+    #[tokio::test]
+    async fn explain_should_show_the_optimized_and_physical_plans() {
+        let ctx = juve().await;
+        let df = ctx
+            .sql("SELECT name FROM juve WHERE position = 'Goalkeeper'")
+            .await
+            .unwrap();
+        let output = explain(df, false, false).await.unwrap();
+        let (logical, physical) = output.split_once("Physical Plan:").unwrap();
+        assert!(logical.starts_with("Optimized Logical Plan:\n  Projection: juve.name"));
+        assert!(logical.contains("TableScan: juve"));
+        assert!(physical.contains("CsvExec"));
+        assert!(!output.starts_with("Logical Plan:"));
+        assert!(!output.contains("output_rows"));
+
+        let df = ctx.sql("SELECT name FROM juve").await.unwrap();
+        let output = explain(df, false, true).await.unwrap();
+        assert!(output.starts_with("Logical Plan:\n"));
+        assert!(output.contains("Optimized Logical Plan:\n"));
+    }
+
+    #[tokio::test]
+    async fn explain_analyze_should_show_the_metrics() {
+        let ctx = juve().await;
+        let df = ctx
+            .sql("SELECT name FROM juve WHERE position = 'Goalkeeper'")
+            .await
+            .unwrap();
+        let output = explain(df, true, false).await.unwrap();
+        let (_, physical) = output.split_once("Physical Plan (analyzed):").unwrap();
+        assert!(physical.contains("FilterExec"));
+        assert!(physical.contains("output_rows="));
+        assert!(physical.contains("elapsed_compute="));
+        assert!(output
+            .lines()
+            .last()
+            .unwrap()
+            .starts_with("Output rows: 4, elapsed: "));
+    }
+}
@@ -0,0 +1,303 @@
+use std::{cmp::Ordering, fmt, path::PathBuf, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, RecordBatch, StringArray},
+    compute::cast,
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    util::display::array_value_to_string,
+};
+use datafusion::{
+    common::ScalarValue,
+    datasource::physical_plan::parquet::{RequestedStatistics, StatisticsConverter},
+    parquet::{arrow::parquet_to_arrow_schema, file::metadata::ParquetMetaData},
+};
+
+use super::parquet::read_metadata;
+
+/// Describe a parquet dataset from the statistics of the footers of its files, without
+/// reading the data: the same total, null_total, min and max as [`DataFrameDescriber`],
+/// except that the min and max of the strings are the values and not their lengths.
+///
+/// [`DataFrameDescriber`]: super::describe::DataFrameDescriber
+pub struct FooterDescriber {
+    schema: SchemaRef,
+    files: Vec<PathBuf>,
+}
+
+/// A value of the footers. The writers may truncate the long min and max, they are
+/// only bounds then.
+enum FooterStat {
+    Exact(String),
+    Bound(&'static str, String),
+    Null,
+    Unavailable,
+}
+
+/// The statistics of a column over the row groups of all the files.
+struct ColumnStats {
+    nulls: Option<u64>,
+    min: Extremum,
+    max: Extremum,
+}
+
+/// The min or the max of the row groups read so far, `None` when a row group has none.
+struct Extremum {
+    value: Option<Option<ScalarValue>>,
+    exact: bool,
+    keep: Ordering,
+}
+
+impl FooterDescriber {
+    pub fn new(schema: SchemaRef, files: Vec<PathBuf>) -> Self {
+        Self { schema, files }
+    }
+
+    pub fn describe(&self) -> anyhow::Result<RecordBatch> {
+        let mut rows = 0;
+        let mut columns: Vec<ColumnStats> = self
+            .schema
+            .fields()
+            .iter()
+            .map(|_| ColumnStats::new())
+            .collect();
+        for file in &self.files {
+            let metadata = read_metadata(file)?;
+            rows += metadata.file_metadata().num_rows() as u64;
+            for (field, stats) in self.schema.fields().iter().zip(columns.iter_mut()) {
+                stats.update(field, &metadata)?;
+            }
+        }
+
+        let mut fields = vec![Field::new("describe", DataType::Utf8, false)];
+        fields.extend(
+            self.schema
+                .fields()
+                .iter()
+                .map(|f| Field::new(f.name(), DataType::Utf8, true)),
+        );
+        let mut arrays: Vec<ArrayRef> = vec![Arc::new(StringArray::from(vec![
+            "total",
+            "null_total",
+            "min",
+            "max",
+        ]))];
+        for stats in columns {
+            let (total, null_total) = match stats.nulls {
+                Some(nulls) => (
+                    FooterStat::Exact((rows - nulls).to_string()),
+                    FooterStat::Exact(nulls.to_string()),
+                ),
+                None => (FooterStat::Unavailable, FooterStat::Unavailable),
+            };
+            let values = [
+                total,
+                null_total,
+                stats.min.stat(">=")?,
+                stats.max.stat("<=")?,
+            ]
+            .map(|stat| stat.to_string_opt());
+            arrays.push(Arc::new(StringArray::from(values.to_vec())));
+        }
+        Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+    }
+}
+
+impl ColumnStats {
+    fn new() -> Self {
+        Self {
+            nulls: Some(0),
+            min: Extremum::new(Ordering::Less),
+            max: Extremum::new(Ordering::Greater),
+        }
+    }
+
+    fn update(&mut self, field: &Field, metadata: &ParquetMetaData) -> anyhow::Result<()> {
+        let file = metadata.file_metadata();
+        let row_groups = metadata.row_groups();
+        // the nested columns have the null counts of their leaves, the partition
+        // columns are not in the files
+        let column = file
+            .schema_descr()
+            .columns()
+            .iter()
+            .position(|c| c.path().parts() == [field.name().as_str()]);
+        let Some(column) = column.filter(|_| !field.data_type().is_nested()) else {
+            if !row_groups.is_empty() {
+                self.unavailable();
+            }
+            return Ok(());
+        };
+
+        let file_schema = parquet_to_arrow_schema(file.schema_descr(), file.key_value_metadata())?;
+        let extract = |requested| -> anyhow::Result<ArrayRef> {
+            let array = StatisticsConverter::try_new(field.name(), requested, &file_schema)?
+                .extract(metadata)?;
+            // the files of a dataset may have other types than the merged schema
+            Ok(cast(&array, field.data_type())?)
+        };
+        let (mins, maxs) = (
+            extract(RequestedStatistics::Min)?,
+            extract(RequestedStatistics::Max)?,
+        );
+
+        for (idx, rg) in row_groups.iter().enumerate() {
+            let Some(stats) = rg.column(column).statistics() else {
+                if rg.num_rows() > 0 {
+                    self.unavailable();
+                }
+                continue;
+            };
+            self.nulls = self.nulls.map(|nulls| nulls + stats.null_count());
+            // a row group of nulls has no min and max
+            if stats.null_count() as i64 >= rg.num_rows() {
+                continue;
+            }
+            self.min.update(&mins, idx, stats.min_is_exact())?;
+            self.max.update(&maxs, idx, stats.max_is_exact())?;
+        }
+        Ok(())
+    }
+
+    fn unavailable(&mut self) {
+        self.nulls = None;
+        self.min.value = None;
+        self.max.value = None;
+    }
+}
+
+impl Extremum {
+    fn new(keep: Ordering) -> Self {
+        Self {
+            value: Some(None),
+            exact: true,
+            keep,
+        }
+    }
+
+    fn update(&mut self, array: &ArrayRef, idx: usize, exact: bool) -> anyhow::Result<()> {
+        let Some(current) = &mut self.value else {
+            return Ok(());
+        };
+        if array.is_null(idx) {
+            self.value = None;
+            return Ok(());
+        }
+        let value = ScalarValue::try_from_array(array, idx)?;
+        match current {
+            Some(current) => match value.partial_cmp(current) {
+                Some(ord) if ord == self.keep => {
+                    *current = value;
+                    self.exact = exact;
+                }
+                // the value is reached if one of the row groups has it exactly
+                Some(Ordering::Equal) => self.exact |= exact,
+                _ => {}
+            },
+            None => {
+                *current = Some(value);
+                self.exact = exact;
+            }
+        }
+        Ok(())
+    }
+
+    /// The statistic, `op` tells how the real value compares to a bound.
+    fn stat(&self, op: &'static str) -> anyhow::Result<FooterStat> {
+        let stat = match &self.value {
+            None => FooterStat::Unavailable,
+            Some(None) => FooterStat::Null,
+            Some(Some(value)) => {
+                let value = array_value_to_string(&value.to_array()?, 0)?;
+                if self.exact {
+                    FooterStat::Exact(value)
+                } else {
+                    FooterStat::Bound(op, value)
+                }
+            }
+        };
+        Ok(stat)
+    }
+}
+
+impl FooterStat {
+    fn to_string_opt(&self) -> Option<String> {
+        match self {
+            FooterStat::Null => None,
+            stat => Some(stat.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for FooterStat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FooterStat::Exact(value) => write!(f, "{}", value),
+            FooterStat::Bound(op, value) => write!(f, "{} {}", op, value),
+            FooterStat::Null => write!(f, "NULL"),
+            FooterStat::Unavailable => write!(f, "unavailable"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::compute::concat_batches;
+    use datafusion::prelude::{ParquetReadOptions, SessionContext};
+
+    use super::*;
+    use crate::backend::fusion::describe::DataFrameDescriber;
+
+    /// The row of `describe` of a statistic, with the rendered values of each column.
+    /// The full describe casts the counts of the temporal columns back to their types,
+    /// they are shown as numbers again.
+    fn rows(batch: &RecordBatch, stat: &str) -> Vec<Option<String>> {
+        let stats = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        let row = (0..stats.len()).find(|&i| stats.value(i) == stat).unwrap();
+        batch.columns()[1..]
+            .iter()
+            .map(|array| match array.data_type() {
+                dt if dt.is_temporal() => cast(array, &DataType::Int64).unwrap(),
+                _ => array.clone(),
+            })
+            .map(|array| (!array.is_null(row)).then(|| array_value_to_string(&array, row).unwrap()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn describe_should_count_as_the_full_describe() {
+        let ctx = SessionContext::new();
+        ctx.register_parquet(
+            "sample",
+            "assets/sample.parquet",
+            ParquetReadOptions::default(),
+        )
+        .await
+        .unwrap();
+        let df = ctx.table("sample").await.unwrap();
+        let schema = df.schema().inner().clone();
+        let full = DataFrameDescriber::try_new(df).unwrap().describe().unwrap();
+        let full_schema = full.schema().inner().clone();
+        let full = concat_batches(&full_schema, &full.collect().await.unwrap()).unwrap();
+        let fast = FooterDescriber::new(schema.clone(), vec!["assets/sample.parquet".into()])
+            .describe()
+            .unwrap();
+
+        for stat in ["total", "null_total"] {
+            let (full, fast) = (rows(&full, stat), rows(&fast, stat));
+            for (i, field) in schema.fields().iter().enumerate() {
+                if field.data_type().is_nested() {
+                    // the footers only have the statistics of the list elements
+                    assert_eq!(fast[i].as_deref(), Some("unavailable"), "{}", field.name());
+                } else {
+                    let full = full[i].as_deref().map(|v| v.trim_end_matches(".0"));
+                    assert_eq!(fast[i].as_deref(), full, "{} of {}", stat, field.name());
+                }
+            }
+        }
+        assert!(schema.fields().iter().any(|f| f.data_type().is_nested()));
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{cli::FreqOpts, quote_ident};
+
+/// The counts of the values, the most frequent first. The nulls are a group of their
+/// own, always shown last and left out of the ranking and the cumulative share.
+pub fn query(name: &str, opts: &FreqOpts) -> String {
+    // the values are named after the column
+    let value = quote_ident(&opts.column);
+    let expr = if opts.explode {
+        format!("unnest({})", value)
+    } else {
+        value.clone()
+    };
+    let (scale, digits, share, cumulative) = if opts.normalize {
+        ("1.0", 4, "fraction", "cumulative_fraction")
+    } else {
+        ("100.0", 2, "pct", "cumulative_pct")
+    };
+    let order = format!("ORDER BY count DESC, {}", value);
+    format!(
+        "SELECT {value}, count,
+                round({scale} * count / total, {digits}) AS {share},
+                CASE WHEN {value} IS NOT NULL THEN round({scale} * sum(count) OVER (
+                    PARTITION BY {value} IS NULL {order} ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW
+                ) / total, {digits}) END AS {cumulative}
+         FROM (
+            SELECT {value}, count, sum(count) OVER () AS total,
+                   row_number() OVER (PARTITION BY {value} IS NULL {order}) AS __rank
+            FROM (
+                SELECT {value}, count(*) AS count
+                FROM (SELECT {expr} AS {value} FROM {name}) GROUP BY {value}
+            )
+         )
+         WHERE __rank <= {top} OR {value} IS NULL
+         ORDER BY {value} IS NULL, count DESC, {value}",
+        top = opts.top,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{Array, AsArray},
+        datatypes::{Float64Type, Int64Type},
+    };
+    use clap::Parser;
+
+    use super::query;
+    use crate::{
+        backend::fusion::udf::test_utils::{self, assets_ctx},
+        cli::FreqOpts,
+    };
+
+    #[tokio::test]
+    async fn freq_should_count_the_values_with_the_nulls_last() {
+        let ctx = assets_ctx().await;
+        ctx.sql(
+            "CREATE VIEW people AS SELECT nullif(gender, 'unknown') AS \"the gender\" FROM users",
+        )
+        .await
+        .unwrap();
+        let opts = FreqOpts::try_parse_from(["freq", "people", "the gender"]).unwrap();
+        let batch = test_utils::query(&ctx, &query("people", &opts)).await;
+        assert_eq!(batch.schema().field(0).name(), "the gender");
+        let values = batch.column(0).as_string::<i32>();
+        assert_eq!(
+            values.iter().collect::<Vec<_>>(),
+            [Some("female"), Some("male"), None]
+        );
+        let counts = batch.column(1).as_primitive::<Int64Type>();
+        assert_eq!(counts.values().to_vec(), [36, 31, 33]);
+        let pct = batch.column(2).as_primitive::<Float64Type>();
+        assert_eq!(pct.values().to_vec(), [36.0, 31.0, 33.0]);
+        let cumulative = batch.column(3).as_primitive::<Float64Type>();
+        assert_eq!(cumulative.value(1), 67.0);
+        assert!(cumulative.is_null(2));
+    }
+
+    #[tokio::test]
+    async fn freq_should_normalize_the_shares() {
+        let ctx = assets_ctx().await;
+        let opts = FreqOpts::try_parse_from(["freq", "users", "gender", "--normalize"]).unwrap();
+        let batch = test_utils::query(&ctx, &query("users", &opts)).await;
+        assert_eq!(batch.schema().field(2).name(), "fraction");
+        let fractions = batch.column(2).as_primitive::<Float64Type>();
+        assert_eq!(fractions.values().to_vec(), [0.36, 0.33, 0.31]);
+        let cumulative = batch.column(3).as_primitive::<Float64Type>();
+        assert_eq!(cumulative.values().to_vec(), [0.36, 0.69, 1.0]);
+    }
+
+    #[tokio::test]
+    async fn freq_should_count_the_elements_of_the_lists() {
+        let ctx = assets_ctx().await;
+        let opts =
+            FreqOpts::try_parse_from(["freq", "users", "finished", "--explode", "--top", "3"])
+                .unwrap();
+        let batch = test_utils::query(&ctx, &query("users", &opts)).await;
+        assert_eq!(batch.num_rows(), 3);
+        let counts = batch.column(1).as_primitive::<Int64Type>();
+        assert_eq!(counts.values().to_vec(), [2, 2, 2]);
+        // the shares are those of all the elements
+        let pct = batch.column(2).as_primitive::<Float64Type>();
+        assert_eq!(pct.value(0), 0.09);
+    }
+}
@@ -0,0 +1,474 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, bail};
+use arrow::{
+    array::{RecordBatch, StringArray},
+    datatypes::{DataType, Field, Schema},
+};
+use datafusion::{
+    common::{
+        config::ConfigOptions,
+        exec_err, plan_err,
+        tree_node::{Transformed, TreeNode},
+        DFSchema, TableReference,
+    },
+    error::Result,
+    execution::{context::SessionState, FunctionRegistry},
+    logical_expr::{
+        simplify::{ExprSimplifyResult, SimplifyInfo},
+        AggregateUDF, ColumnarValue, EmptyRelation, Expr, ExprSchemable, LogicalPlan, Projection,
+        ScalarUDF, ScalarUDFImpl, Signature, TableSource, Volatility, WindowUDF,
+    },
+    optimizer::{analyzer::type_coercion::TypeCoercion, AnalyzerRule},
+    prelude::SessionContext,
+    sql::{
+        planner::{ContextProvider, PlannerContext, SqlToRel},
+        sqlparser::{ast::Expr as SQLExpr, dialect::GenericDialect, parser::Parser},
+    },
+};
+
+/// A function defined in SQL as `name(a, b) AS <expression of a and b>`.
+#[derive(Debug, Clone)]
+pub struct SqlFunction {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: String,
+}
+
+/// The functions created in the session, saved to `path` once they are loaded from it.
+/// They are kept in the order of creation since a function may call the previous ones.
+#[derive(Debug, Clone, Default)]
+pub struct SqlFunctions {
+    functions: Vec<SqlFunction>,
+    pub path: Option<PathBuf>,
+}
+
+impl SqlFunction {
+    pub fn parse(definition: &str) -> anyhow::Result<Self> {
+        let invalid = || {
+            anyhow!(
+                "Invalid function {}, expect name(arg, ...) AS <expression>",
+                definition
+            )
+        };
+        let (name, rest) = definition.trim().split_once('(').ok_or_else(invalid)?;
+        let (params, rest) = rest.split_once(')').ok_or_else(invalid)?;
+        let (keyword, body) = rest.trim_start().split_at(rest.trim_start().len().min(2));
+        if !keyword.eq_ignore_ascii_case("as") || !body.starts_with(char::is_whitespace) {
+            return Err(invalid());
+        }
+
+        // unquoted identifiers are lowercase in DataFusion
+        let name = name.trim().to_lowercase();
+        let params: Vec<String> = params
+            .split(',')
+            .map(|p| p.trim().to_lowercase())
+            .filter(|p| !p.is_empty())
+            .collect();
+        for ident in params.iter().chain([&name]) {
+            if !is_identifier(ident) {
+                bail!("Invalid identifier {} in function {}", ident, definition);
+            }
+        }
+        if let Some(param) = params
+            .iter()
+            .find(|p| params.iter().filter(|q| q == p).count() > 1)
+        {
+            bail!("Duplicate argument {} in function {}", param, name);
+        }
+        let function = Self {
+            name,
+            params,
+            body: body.trim().to_string(),
+        };
+        function.parse_body()?;
+        Ok(function)
+    }
+
+    fn parse_body(&self) -> anyhow::Result<SQLExpr> {
+        let mut parser = Parser::new(&GenericDialect {}).try_with_sql(&self.body)?;
+        let expr = parser
+            .parse_expr()
+            .map_err(|e| anyhow!("Invalid expression of function {}: {}", self.name, e))?;
+        if parser.peek_token() != datafusion::sql::sqlparser::tokenizer::Token::EOF {
+            bail!(
+                "Invalid expression of function {}: {}",
+                self.name,
+                self.body
+            );
+        }
+        Ok(expr)
+    }
+
+    /// Register the function as a macro, its calls are replaced by its expression when
+    /// the queries are optimized.
+    pub fn register(&self, ctx: &SessionContext) -> anyhow::Result<()> {
+        if let Ok(udf) = ctx.udf(&self.name) {
+            if !udf.inner().as_any().is::<SqlMacro>() {
+                bail!("Function {} already exists", self.name);
+            }
+        }
+        let signature = match self.params.len() {
+            0 => Signature::exact(vec![], Volatility::Volatile),
+            // the body is planned for the types of the arguments of each call
+            n => Signature::any(n, Volatility::Volatile),
+        };
+        let udf = SqlMacro {
+            function: self.clone(),
+            body: self.parse_body()?,
+            state: ctx.state(),
+            signature,
+        };
+        // the columns and the functions of the body must exist, whatever the types of the args
+        let schema = udf.params_schema(&vec![DataType::Null; self.params.len()])?;
+        udf.to_expr(&schema)
+            .map_err(|e| anyhow!("Invalid function {}: {}", self.name, e))?;
+        ctx.register_udf(ScalarUDF::from(udf));
+        Ok(())
+    }
+}
+
+impl fmt::Display for SqlFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}({}) AS {}",
+            self.name,
+            self.params.join(", "),
+            self.body
+        )
+    }
+}
+
+impl SqlFunctions {
+    pub fn insert(&mut self, function: SqlFunction) {
+        match self.functions.iter_mut().find(|f| f.name == function.name) {
+            Some(f) => *f = function,
+            None => self.functions.push(function),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<SqlFunction> {
+        let idx = self.functions.iter().position(|f| f.name == name)?;
+        Some(self.functions.remove(idx))
+    }
+
+    /// Read the definitions of a functions file, a missing file has none.
+    pub fn read(path: &Path) -> anyhow::Result<Vec<SqlFunction>> {
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read functions from {}: {}", path.display(), e))?;
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(SqlFunction::parse)
+            .collect()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut content =
+            String::from("# taotie functions, one per line as name(arg, ...) AS <expression>\n");
+        for function in &self.functions {
+            content.push_str(&format!("{}\n", function));
+        }
+        fs::write(path, content)
+            .map_err(|e| anyhow!("Failed to write functions to {}: {}", path.display(), e))
+    }
+
+    pub fn batch(&self) -> anyhow::Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("name", DataType::Utf8, false),
+            Field::new("arguments", DataType::Utf8, false),
+            Field::new("expression", DataType::Utf8, false),
+        ]));
+        let functions = self.functions.iter();
+        let names = StringArray::from_iter_values(functions.clone().map(|f| &f.name));
+        let params = StringArray::from_iter_values(functions.clone().map(|f| f.params.join(", ")));
+        let bodies = StringArray::from_iter_values(functions.map(|f| &f.body));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(names), Arc::new(params), Arc::new(bodies)],
+        )?;
+        Ok(batch)
+    }
+}
+
+/// The UDF of a [`SqlFunction`]. It is never executed: the optimizer replaces its
+/// calls by the body planned with the arguments.
+struct SqlMacro {
+    function: SqlFunction,
+    body: SQLExpr,
+    /// the session when the function was created, to plan the functions of the body
+    state: SessionState,
+    signature: Signature,
+}
+
+impl fmt::Debug for SqlMacro {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqlMacro")
+            .field("function", &self.function)
+            .finish()
+    }
+}
+
+impl SqlMacro {
+    /// The schema of the arguments, named after the parameters.
+    fn params_schema(&self, arg_types: &[DataType]) -> Result<DFSchema> {
+        let fields: Vec<_> = self
+            .function
+            .params
+            .iter()
+            .zip(arg_types)
+            .map(|(name, data_type)| Field::new(name, data_type.clone(), true))
+            .collect();
+        DFSchema::try_from(Schema::new(fields))
+    }
+
+    /// The body for the arguments of `schema`, without the casts.
+    fn to_expr(&self, schema: &DFSchema) -> Result<Expr> {
+        let context = MacroContext(&self.state);
+        let planner = SqlToRel::new(&context);
+        planner.sql_to_expr(self.body.clone(), schema, &mut PlannerContext::new())
+    }
+
+    /// The body for the arguments of `schema`, with the casts needed by their types.
+    fn plan(&self, schema: &DFSchema) -> Result<Expr> {
+        let expr = self.to_expr(schema)?;
+        // the calls are expanded after the type coercion of the query, coerce the body now
+        let relation = LogicalPlan::EmptyRelation(EmptyRelation {
+            produce_one_row: false,
+            schema: Arc::new(schema.clone()),
+        });
+        let plan = Projection::try_new(vec![expr], Arc::new(relation))?;
+        let plan = TypeCoercion::new()
+            .analyze(LogicalPlan::Projection(plan), self.state.config_options())?;
+        match plan.expressions().into_iter().next() {
+            Some(expr) => Ok(expr),
+            None => plan_err!("The function {} has no expression", self.function.name),
+        }
+    }
+}
+
+impl ScalarUDFImpl for SqlMacro {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.function.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        let schema = self.params_schema(arg_types)?;
+        self.plan(&schema)?.get_type(&schema)
+    }
+
+    fn invoke(&self, _args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        exec_err!(
+            "The function {} must be expanded by the optimizer",
+            self.function.name
+        )
+    }
+
+    fn simplify(&self, args: Vec<Expr>, info: &dyn SimplifyInfo) -> Result<ExprSimplifyResult> {
+        let arg_types = args
+            .iter()
+            .map(|arg| info.get_data_type(arg))
+            .collect::<Result<Vec<_>>>()?;
+        let schema = self.params_schema(&arg_types)?;
+        let body = self.plan(&schema)?;
+        let args: HashMap<&str, &Expr> = self
+            .function
+            .params
+            .iter()
+            .map(String::as_str)
+            .zip(args.iter())
+            .collect();
+        let expr = body
+            .transform_up(|expr| match &expr {
+                Expr::Column(col) if col.relation.is_none() => match args.get(col.name.as_str()) {
+                    Some(arg) => Ok(Transformed::yes((*arg).clone())),
+                    None => Ok(Transformed::no(expr)),
+                },
+                _ => Ok(Transformed::no(expr)),
+            })?
+            .data;
+        Ok(ExprSimplifyResult::Simplified(expr))
+    }
+}
+
+/// Resolves the functions of a macro body, which can't read tables.
+struct MacroContext<'a>(&'a SessionState);
+
+impl ContextProvider for MacroContext<'_> {
+    fn get_table_source(&self, name: TableReference) -> Result<Arc<dyn TableSource>> {
+        plan_err!("A function can't read the table {}", name)
+    }
+
+    fn get_function_meta(&self, name: &str) -> Option<Arc<ScalarUDF>> {
+        self.0.scalar_functions().get(name).cloned()
+    }
+
+    fn get_aggregate_meta(&self, name: &str) -> Option<Arc<AggregateUDF>> {
+        self.0.aggregate_functions().get(name).cloned()
+    }
+
+    fn get_window_meta(&self, name: &str) -> Option<Arc<WindowUDF>> {
+        self.0.window_functions().get(name).cloned()
+    }
+
+    fn get_variable_type(&self, _variable_names: &[String]) -> Option<DataType> {
+        None
+    }
+
+    fn options(&self) -> &ConfigOptions {
+        self.0.config_options()
+    }
+
+    fn udf_names(&self) -> Vec<String> {
+        self.0.scalar_functions().keys().cloned().collect()
+    }
+
+    fn udaf_names(&self) -> Vec<String> {
+        self.0.aggregate_functions().keys().cloned().collect()
+    }
+
+    fn udwf_names(&self) -> Vec<String> {
+        self.0.window_functions().keys().cloned().collect()
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Array, AsArray};
+    use arrow::datatypes::{Float64Type, Int64Type};
+
+    use super::*;
+
+    async fn query(ctx: &SessionContext, sql: &str) -> RecordBatch {
+        let batches = ctx.sql(sql).await.unwrap().collect().await.unwrap();
+        batches.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn parse_should_lowercase_the_identifiers() {
+        let function =
+            SqlFunction::parse("Full_Name(First, last) as concat(first, ' ', last)").unwrap();
+        assert_eq!(function.name, "full_name");
+        assert_eq!(function.params, vec!["first", "last"]);
+        assert_eq!(function.body, "concat(first, ' ', last)");
+        assert_eq!(
+            function.to_string(),
+            "full_name(first, last) AS concat(first, ' ', last)"
+        );
+        assert!(SqlFunction::parse("now_utc() AS now()")
+            .unwrap()
+            .params
+            .is_empty());
+    }
+
+    #[test]
+    fn parse_should_reject_invalid_definitions() {
+        for definition in [
+            "f(a) a + 1",
+            "f(a) ASa + 1",
+            "f(a AS a + 1",
+            "f(a, a) AS a + 1",
+            "f(1a) AS 1",
+            "my-f(a) AS a",
+            "f(a) AS a +",
+            "f(a) AS a b",
+        ] {
+            assert!(SqlFunction::parse(definition).is_err(), "{}", definition);
+        }
+    }
+
+    #[test]
+    fn functions_should_be_saved_and_read_back() {
+        let path = std::env::temp_dir().join("taotie_functions_saved");
+        let mut functions = SqlFunctions {
+            path: Some(path.clone()),
+            ..Default::default()
+        };
+        functions.insert(SqlFunction::parse("add1(x) AS x + 1").unwrap());
+        functions.insert(SqlFunction::parse("add2(x) AS add1(add1(x))").unwrap());
+        functions.insert(SqlFunction::parse("add1(y) AS y + 1").unwrap());
+        functions.save().unwrap();
+
+        let read: Vec<_> = SqlFunctions::read(&path)
+            .unwrap()
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        // the replaced function keeps its place
+        assert_eq!(read, vec!["add1(y) AS y + 1", "add2(x) AS add1(add1(x))"]);
+        assert!(SqlFunctions::read(Path::new("/no/such/functions"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn macros_should_expand_nested_calls() {
+        let ctx = SessionContext::new();
+        for definition in ["add1(x) AS x + 1", "add2(x) AS add1(add1(x))"] {
+            SqlFunction::parse(definition)
+                .unwrap()
+                .register(&ctx)
+                .unwrap();
+        }
+        let batch = query(&ctx, "SELECT add2(a) FROM (VALUES (1), (2)) AS t(a)").await;
+        assert_eq!(
+            batch.column(0).as_primitive::<Int64Type>().values(),
+            &[3, 4]
+        );
+    }
+
+    #[tokio::test]
+    async fn macros_should_follow_the_argument_types() {
+        let ctx = SessionContext::new();
+        for definition in ["add1(x) AS x + 1", "greet(n) AS concat('hi ', n)"] {
+            SqlFunction::parse(definition)
+                .unwrap()
+                .register(&ctx)
+                .unwrap();
+        }
+        let batch = query(
+            &ctx,
+            "SELECT add1(1.5) AS f, greet('bob') AS s, add1(NULL) AS n",
+        )
+        .await;
+        assert_eq!(batch.column(0).as_primitive::<Float64Type>().value(0), 2.5);
+        assert_eq!(batch.column(1).as_string::<i32>().value(0), "hi bob");
+        assert!(batch.column(2).is_null(0));
+
+        // the body must make sense whatever the types
+        let err = SqlFunction::parse("f(x) AS no_such_function(x)")
+            .unwrap()
+            .register(&ctx);
+        assert!(err.is_err());
+    }
+}
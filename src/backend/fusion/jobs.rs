@@ -0,0 +1,44 @@
+use std::{sync::Arc, time::Duration};
+
+use arrow::{
+    array::{RecordBatch, StringArray, UInt64Array},
+    datatypes::{DataType, Field, Schema},
+};
+
+use crate::backend::{job_table, Job, JobState};
+
+/// Render the jobs as a table of id, command, state, elapsed time and the table
+/// of the result for the finished background jobs. Foreground commands have no id.
+pub fn jobs_batch(jobs: &[Job]) -> anyhow::Result<RecordBatch> {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("id", DataType::UInt64, true),
+        Field::new("command", DataType::Utf8, false),
+        Field::new("state", DataType::Utf8, false),
+        Field::new("elapsed", DataType::Utf8, false),
+        Field::new("table", DataType::Utf8, true),
+    ]));
+    let ids = UInt64Array::from_iter(jobs.iter().map(|job| job.id.map(|id| id as u64)));
+    let commands = StringArray::from_iter_values(jobs.iter().map(|job| &job.command));
+    let states = StringArray::from_iter_values(jobs.iter().map(|job| job.state.to_string()));
+    let elapsed = StringArray::from_iter_values(jobs.iter().map(|job| format_elapsed(job.elapsed)));
+    let tables = StringArray::from_iter(jobs.iter().map(|job| match (job.id, &job.state) {
+        (Some(id), JobState::Finished { .. }) => Some(job_table(id)),
+        _ => None,
+    }));
+    let batch = RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(ids),
+            Arc::new(commands),
+            Arc::new(states),
+            Arc::new(elapsed),
+            Arc::new(tables),
+        ],
+    )?;
+    Ok(batch)
+}
+
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let elapsed = Duration::from_millis(elapsed.as_millis() as u64);
+    humantime::format_duration(elapsed).to_string()
+}
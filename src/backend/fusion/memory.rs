@@ -0,0 +1,215 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{anyhow, bail};
+use bytesize::ByteSize;
+use datafusion::{
+    error::DataFusionError,
+    execution::{
+        context::SessionState,
+        disk_manager::DiskManagerConfig,
+        memory_pool::{FairSpillPool, GreedyMemoryPool, MemoryPool, UnboundedMemoryPool},
+        runtime_env::{RuntimeConfig, RuntimeEnv},
+        FunctionRegistry,
+    },
+    prelude::SessionContext,
+};
+
+/// How the memory is shared by the operators of the queries once it is limited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PoolKind {
+    /// Every spilling operator (sort, aggregate, join) gets an equal share of the limit.
+    #[default]
+    Fair,
+    /// First come first served, the operators may starve each other.
+    Greedy,
+}
+
+/// Memory settings of the runtime. DataFusion can't change them in place, so the
+/// session is rebuilt around the same catalog when they change.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySettings {
+    pub limit: Option<ByteSize>,
+    pub pool: PoolKind,
+    /// None spills to the temp dir of the OS
+    pub spill_dir: Option<SpillDir>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpillDir {
+    Disabled,
+    Path(PathBuf),
+}
+
+impl MemorySettings {
+    /// Apply a memory setting, returns false if the key is unknown.
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<bool> {
+        match key {
+            "memory_limit" => self.limit = parse_limit(value)?,
+            "memory_pool" => self.pool = value.parse()?,
+            "spill_dir" => self.spill_dir = parse_spill_dir(value)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// The memory settings, as key, value and description.
+    pub fn entries(&self) -> Vec<(&'static str, String, &'static str)> {
+        let limit = self
+            .limit
+            .map_or("off".to_string(), |l| l.to_string_as(false));
+        let pool = match self.pool {
+            PoolKind::Fair => "fair",
+            PoolKind::Greedy => "greedy",
+        };
+        let spill_dir = match &self.spill_dir {
+            None => "default".to_string(),
+            Some(SpillDir::Disabled) => "off".to_string(),
+            Some(SpillDir::Path(dir)) => dir.display().to_string(),
+        };
+        vec![
+            (
+                "memory_limit",
+                limit,
+                "The memory the queries may use, off for no limit",
+            ),
+            (
+                "memory_pool",
+                pool.to_string(),
+                "How the memory limit is shared: fair or greedy",
+            ),
+            (
+                "spill_dir",
+                spill_dir,
+                "Where the queries spill, default is the temp dir, off disables it",
+            ),
+        ]
+    }
+
+    pub fn runtime_env(&self) -> anyhow::Result<Arc<RuntimeEnv>> {
+        let pool: Arc<dyn MemoryPool> = match (self.limit, self.pool) {
+            (None, _) => Arc::new(UnboundedMemoryPool::default()),
+            (Some(limit), PoolKind::Fair) => Arc::new(FairSpillPool::new(limit.as_u64() as usize)),
+            (Some(limit), PoolKind::Greedy) => {
+                Arc::new(GreedyMemoryPool::new(limit.as_u64() as usize))
+            }
+        };
+        let disk_manager = match &self.spill_dir {
+            None => DiskManagerConfig::NewOs,
+            Some(SpillDir::Disabled) => DiskManagerConfig::Disabled,
+            Some(SpillDir::Path(dir)) => DiskManagerConfig::NewSpecified(vec![dir.clone()]),
+        };
+        let config = RuntimeConfig::new()
+            .with_memory_pool(pool)
+            .with_disk_manager(disk_manager);
+        Ok(Arc::new(RuntimeEnv::new(config)?))
+    }
+}
+
+/// A new session sharing the catalog, the config and the functions of `ctx`, but
+/// running its queries with `runtime`.
+pub fn with_runtime(
+    ctx: &SessionContext,
+    runtime: Arc<RuntimeEnv>,
+) -> anyhow::Result<SessionContext> {
+    let state = ctx.state();
+    // the default catalog already exists in the catalog list, don't replace it by an empty one
+    let create_default = state.config().create_default_catalog_and_schema();
+    let config = state
+        .config()
+        .clone()
+        .with_create_default_catalog_and_schema(false);
+    let mut new_state =
+        SessionState::new_with_config_rt_and_catalog_list(config, runtime, state.catalog_list());
+    new_state
+        .config_mut()
+        .options_mut()
+        .catalog
+        .create_default_catalog_and_schema = create_default;
+    for udf in state.scalar_functions().values() {
+        new_state.register_udf(udf.clone())?;
+    }
+    for udaf in state.aggregate_functions().values() {
+        new_state.register_udaf(udaf.clone())?;
+    }
+    for udwf in state.window_functions().values() {
+        new_state.register_udwf(udwf.clone())?;
+    }
+    Ok(SessionContext::new_with_state(new_state))
+}
+
+/// The error of a query, explaining how to get more memory if it ran out of it.
+pub fn query_error(err: DataFusionError) -> anyhow::Error {
+    if let DataFusionError::ResourcesExhausted(msg) = err.find_root() {
+        return anyhow!(
+            "Query exceeded the memory limit: {}. Raise it with `set memory_limit <size>`",
+            msg
+        );
+    }
+    err.into()
+}
+
+impl std::str::FromStr for PoolKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fair" => Ok(Self::Fair),
+            "greedy" => Ok(Self::Greedy),
+            v => bail!("Invalid memory pool {}, expect fair or greedy", v),
+        }
+    }
+}
+
+fn parse_limit(value: &str) -> anyhow::Result<Option<ByteSize>> {
+    match value {
+        "off" | "none" | "0" => Ok(None),
+        v => {
+            let limit = v
+                .parse::<ByteSize>()
+                .map_err(|e| anyhow!("Invalid memory limit {}: {}", v, e))?;
+            Ok(Some(limit))
+        }
+    }
+}
+
+fn parse_spill_dir(value: &str) -> anyhow::Result<Option<SpillDir>> {
+    match value {
+        "default" => Ok(None),
+        "off" | "none" => Ok(Some(SpillDir::Disabled)),
+        v => {
+            let dir = PathBuf::from(v);
+            if !dir.is_dir() {
+                bail!("Spill directory {} does not exist", v);
+            }
+            Ok(Some(SpillDir::Path(dir)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_limit_should_accept_sizes_and_off() {
+        assert_eq!(parse_limit("2GB").unwrap(), Some(ByteSize::gb(2)));
+        assert_eq!(parse_limit("512MiB").unwrap(), Some(ByteSize::mib(512)));
+        for value in ["off", "none", "0"] {
+            assert_eq!(parse_limit(value).unwrap(), None);
+        }
+        assert!(parse_limit("lots").is_err());
+    }
+
+    #[test]
+    fn parse_spill_dir_should_check_the_directory() {
+        let tmp = std::env::temp_dir();
+        assert_eq!(
+            parse_spill_dir(tmp.to_str().unwrap()).unwrap(),
+            Some(SpillDir::Path(tmp))
+        );
+        assert_eq!(parse_spill_dir("default").unwrap(), None);
+        assert_eq!(parse_spill_dir("off").unwrap(), Some(SpillDir::Disabled));
+        assert!(parse_spill_dir("/no/such/dir").is_err());
+        assert!(parse_spill_dir("Cargo.toml").is_err());
+    }
+}
@@ -1,23 +1,75 @@
+mod cache;
+mod convert;
 mod describe;
 mod df_describe;
+mod explain;
+mod fast_describe;
+mod freq;
+mod functions;
+mod jobs;
+mod memory;
+mod parquet;
+mod sample;
+mod settings;
+mod tail;
+mod udf;
+mod variables;
 
-use std::ops::Deref;
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
 
-use arrow::{array::RecordBatch, util::pretty::pretty_format_batches};
-use datafusion::prelude::{
-    CsvReadOptions, DataFrame, NdJsonReadOptions, SessionConfig, SessionContext,
+use anyhow::anyhow;
+use arrow::{
+    array::{AsArray, RecordBatch},
+    compute::concat_batches,
+    datatypes::{Int64Type, SchemaRef},
+    util::pretty::pretty_format_batches,
+};
+use bytesize::ByteSize;
+use cache::CachedTable;
+use datafusion::{
+    common::{DFSchema, ScalarValue},
+    datasource::{MemTable, TableProvider},
+    error::DataFusionError,
+    logical_expr::{LogicalPlan, SetVariable, Statement},
+    physical_plan::{execute_stream, ExecutionPlan, SendableRecordBatchStream},
+    prelude::{CsvReadOptions, DataFrame, NdJsonReadOptions, SessionConfig, SessionContext},
+    sql::TableReference,
 };
 use describe::DataFrameDescriber;
+use fast_describe::FooterDescriber;
+use functions::{SqlFunction, SqlFunctions};
+use futures::StreamExt;
+use memory::{query_error, MemorySettings};
+use settings::Setting;
+use variables::Variables;
 
 use crate::{
-    cli::{ConnectOpts, DatasetConn},
-    Backend, ReplDisplay,
+    backend::{job_table, JobRegistry, JobState},
+    cli::{self, ConnectOpts, ConvertOpts, DatasetConn, FreqOpts, ReplSettings, SampleOpts},
+    Backend, Error, QueryStats, ReplDisplay, Result, MORE_ROWS,
 };
 
-pub struct DataFusionBackend(SessionContext);
+#[derive(Clone)]
+pub struct DataFusionBackend {
+    ctx: SessionContext,
+    settings: ReplSettings,
+    memory: MemorySettings,
+    cached: HashMap<String, CachedTable>,
+    variables: Variables,
+    functions: SqlFunctions,
+    jobs: JobRegistry,
+}
 
+// the concrete data types are exposed so that the futures of the commands are known to be Send
+#[allow(refining_impl_trait)]
 impl Backend for DataFusionBackend {
-    async fn connect(&mut self, opts: &ConnectOpts) -> anyhow::Result<()> {
+    async fn connect(&mut self, opts: &ConnectOpts) -> Result<()> {
         match &opts.conn {
             DatasetConn::Postgres(_) => {
                 println!("Postgres connection is not supported yet")
@@ -27,67 +79,566 @@ impl Backend for DataFusionBackend {
                     .file_extension(&file_opt.ext)
                     .file_compression_type(file_opt.compression);
                 self.register_csv(&opts.name, &file_opt.filename, options)
-                    .await?;
+                    .await
+                    .map_err(backend_error)?;
             }
             DatasetConn::Parquet(filename) => {
                 self.register_parquet(&opts.name, filename, Default::default())
-                    .await?;
+                    .await
+                    .map_err(backend_error)?;
             }
             DatasetConn::NdJson(file_opt) => {
                 let options = NdJsonReadOptions::default()
                     .file_extension(&file_opt.ext)
                     .file_compression_type(file_opt.compression);
                 self.register_json(&opts.name, &file_opt.filename, options)
-                    .await?;
+                    .await
+                    .map_err(backend_error)?;
             }
         }
+        // the dataset replaced a cached one, which must not come back on uncache
+        let table = TableReference::from(opts.name.as_str()).table().to_string();
+        self.cached.remove(&table);
 
         Ok(())
     }
 
-    async fn list(&self) -> anyhow::Result<impl ReplDisplay> {
+    async fn list(&self) -> Result<RecordBatch> {
         let df = self
-            .0
+            .ctx
             .sql("SELECT t.table_name, t.table_type FROM information_schema.tables t WHERE t.table_schema = 'public'")
-            .await?;
-        Ok(df)
+            .await
+            .map_err(backend_error)?;
+        let schema = df.schema().inner().clone();
+        let batch = concat_batches(&schema, &df.collect().await.map_err(backend_error)?)
+            .map_err(Error::backend)?;
+        cache::with_memory(batch, &self.cached).map_err(Error::backend)
     }
 
-    async fn schema(&self, name: &str) -> anyhow::Result<impl ReplDisplay> {
-        let df = self.0.sql(&format!("DESCRIBE {}", name)).await?;
+    async fn cache(&mut self, name: &str, partitions: Option<usize>) -> Result<String> {
+        let table = TableReference::from(name).table().to_string();
+        if self.cached.contains_key(&table) {
+            return Err(Error::backend(anyhow!(
+                "Dataset {} is already cached",
+                name
+            )));
+        }
+
+        let df = self.ctx.table(name).await.map_err(backend_error)?;
+        let schema = df.schema().inner().clone();
+        let data = df.collect_partitioned().await.map_err(backend_error)?;
+        let batches = data.iter().flatten();
+        let rows: usize = batches.clone().map(|batch| batch.num_rows()).sum();
+        let memory = batches.clone().map(|b| b.get_array_memory_size()).sum();
+        let schema = batches
+            .clone()
+            .next()
+            .map_or(schema, |batch| batch.schema());
+        let data = match partitions {
+            Some(n) => cache::split_partitions(data.into_iter().flatten(), rows, n),
+            None => data,
+        };
+        let partitions = data.len();
+        let table_provider = MemTable::try_new(schema, data).map_err(backend_error)?;
+
+        let source = self
+            .ctx
+            .deregister_table(name)
+            .map_err(backend_error)?
+            .ok_or_else(|| Error::backend(anyhow!("No such dataset: {}", name)))?;
+        self.ctx
+            .register_table(name, Arc::new(table_provider))
+            .map_err(backend_error)?;
+        self.cached.insert(table, CachedTable { source, memory });
+        Ok(format!(
+            "Cached {}: {} rows, {} in {} partitions",
+            name,
+            rows,
+            ByteSize(memory as u64),
+            partitions
+        ))
+    }
+
+    async fn uncache(&mut self, name: &str) -> Result<()> {
+        let table = TableReference::from(name).table().to_string();
+        let cached = self
+            .cached
+            .remove(&table)
+            .ok_or_else(|| Error::backend(anyhow!("Dataset {} is not cached", name)))?;
+        self.ctx.deregister_table(name).map_err(backend_error)?;
+        self.ctx
+            .register_table(name, cached.source)
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn schema(&self, name: &str) -> Result<DataFrame> {
+        let df = self
+            .ctx
+            .sql(&format!("DESCRIBE {}", name))
+            .await
+            .map_err(backend_error)?;
         Ok(df)
     }
 
-    async fn describe(&self, name: &str) -> anyhow::Result<impl ReplDisplay> {
-        let df = self.0.sql(&format!("SELECT * FROM {}", name)).await?;
+    async fn describe(&self, name: &str) -> Result<DataFrame> {
+        let name = variables::resolve_name(name, &self.variables).map_err(Error::backend)?;
+        let df = self
+            .ctx
+            .sql(&format!("SELECT * FROM {}", name))
+            .await
+            .map_err(backend_error)?;
         // let ddf = DescribeDataFrame::new(df);
         // let batch = ddf.to_record_batch().await?;
 
-        let ddf = DataFrameDescriber::try_new(df)?;
-        ddf.describe()
+        let ddf = DataFrameDescriber::try_new(df).map_err(Error::backend)?;
+        ddf.describe().map_err(Error::backend)
+    }
+
+    async fn describe_fast(&self, name: &str) -> Result<RecordBatch> {
+        let name = variables::resolve_name(name, &self.variables).map_err(Error::backend)?;
+        let source = self.source(name).await.map_err(Error::backend)?;
+        let files = parquet::dataset_files(&self.ctx.state(), &source, name)
+            .await
+            .map_err(Error::backend)?;
+        FooterDescriber::new(source.schema(), files)
+            .describe()
+            .map_err(Error::backend)
     }
 
-    async fn head(&self, name: &str, size: usize) -> anyhow::Result<impl ReplDisplay> {
+    async fn head(&self, name: &str, size: usize) -> Result<DataFrame> {
+        let name = variables::resolve_name(name, &self.variables).map_err(Error::backend)?;
         let df = self
-            .0
+            .ctx
             .sql(&format!("SELECT * FROM {} LIMIT {}", name, size))
-            .await?;
+            .await
+            .map_err(backend_error)?;
         Ok(df)
     }
 
-    async fn sql(&self, sql: &str) -> anyhow::Result<impl ReplDisplay> {
-        let df = self.0.sql(sql).await?;
+    async fn tail(&self, name: &str, size: usize, order_by: Option<&str>) -> Result<RecordBatch> {
+        let name = variables::resolve_name(name, &self.variables).map_err(Error::backend)?;
+        let Some(column) = order_by else {
+            return tail::last_rows(&self.ctx, name, size)
+                .await
+                .map_err(Error::backend);
+        };
+        let df = self
+            .ctx
+            .sql(&cli::tail_query(name, column, size))
+            .await
+            .map_err(backend_error)?;
+        let schema = df.schema().inner().clone();
+        let batches = df.collect().await.map_err(backend_error)?;
+        concat_batches(&schema, &batches).map_err(Error::backend)
+    }
+
+    async fn top(&self, name: &str, by: &str, size: usize) -> Result<DataFrame> {
+        let name = variables::resolve_name(name, &self.variables).map_err(Error::backend)?;
+        let df = self
+            .ctx
+            .sql(&cli::top_query(name, by, size))
+            .await
+            .map_err(backend_error)?;
         Ok(df)
     }
+
+    async fn count(&self, name: &str, filter: Option<&str>) -> Result<u64> {
+        let name = variables::resolve_name(name, &self.variables).map_err(Error::backend)?;
+        // the condition may use the variables
+        let sql = cli::count_query(name, filter);
+        let df = variables::plan(&self.ctx, &sql, &[], &self.variables)
+            .await
+            .map_err(Error::backend)?;
+        let batches = df.collect().await.map_err(backend_error)?;
+        let count = batches
+            .first()
+            .map_or(0, |b| b.column(0).as_primitive::<Int64Type>().value(0));
+        Ok(count as u64)
+    }
+
+    async fn freq(&self, opts: &FreqOpts) -> Result<DataFrame> {
+        let name = variables::resolve_name(&opts.name, &self.variables).map_err(Error::backend)?;
+        let df = self
+            .ctx
+            .sql(&freq::query(name, opts))
+            .await
+            .map_err(backend_error)?;
+        Ok(df)
+    }
+
+    async fn sample(&mut self, opts: &SampleOpts) -> Result<DataFrame> {
+        let name = variables::resolve_name(&opts.name, &self.variables).map_err(Error::backend)?;
+        let columns: Vec<String> = self
+            .ctx
+            .table(name)
+            .await
+            .map_err(backend_error)?
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().to_string())
+            .collect();
+        let seed = opts.seed.unwrap_or_else(|| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            now.as_nanos() as u64
+        });
+        let df = self
+            .ctx
+            .sql(&sample::query(name, &columns, opts, seed).map_err(Error::backend)?)
+            .await
+            .map_err(backend_error)?;
+        let Some(save_as) = &opts.save_as else {
+            return Ok(df);
+        };
+        if self
+            .ctx
+            .table_exist(save_as.as_str())
+            .map_err(backend_error)?
+        {
+            return Err(Error::backend(anyhow!(
+                "Dataset {} already exists",
+                save_as
+            )));
+        }
+        // keep the rows, a view would draw other ones on each query without a seed
+        let schema = df.schema().inner().clone();
+        let data = df.collect_partitioned().await.map_err(backend_error)?;
+        let table = MemTable::try_new(schema, data).map_err(backend_error)?;
+        self.ctx
+            .register_table(save_as.as_str(), Arc::new(table))
+            .map_err(backend_error)?;
+        self.ctx
+            .table(save_as.as_str())
+            .await
+            .map_err(backend_error)
+    }
+
+    async fn sql(&self, sql: &str) -> Result<DataFrame> {
+        variables::plan(&self.ctx, sql, &[], &self.variables)
+            .await
+            .map_err(Error::backend)
+    }
+
+    async fn sql_with_params(&self, sql: &str, params: &[(String, String)]) -> Result<DataFrame> {
+        variables::plan(&self.ctx, sql, params, &self.variables)
+            .await
+            .map_err(Error::backend)
+    }
+
+    async fn define(&mut self, name: &str, value: &str) -> Result<String> {
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(Error::backend(anyhow!("Invalid variable name {}", name)));
+        }
+        // the value is a SQL expression, e.g. '2024-05-01' or now() - interval '7 days'
+        let df = self
+            .ctx
+            .sql(&format!("SELECT {}", value))
+            .await
+            .map_err(backend_error)?;
+        let batches = df.collect().await.map_err(backend_error)?;
+        let value = match batches.iter().find(|batch| batch.num_rows() > 0) {
+            Some(batch) if batch.num_columns() == 1 => {
+                ScalarValue::try_from_array(batch.column(0), 0).map_err(backend_error)?
+            }
+            _ => {
+                return Err(Error::backend(anyhow!(
+                    "The value of {} must be a single expression",
+                    name
+                )))
+            }
+        };
+        let desc = format!(
+            "{} ({})",
+            variables::format_value(&value),
+            value.data_type()
+        );
+        self.variables.insert(name.to_string(), value);
+        Ok(desc)
+    }
+
+    async fn variables(&self) -> Result<RecordBatch> {
+        variables::variables_batch(&self.variables).map_err(Error::backend)
+    }
+
+    async fn create_view(&mut self, name: &str, sql: &str) -> Result<()> {
+        self.ctx
+            .sql(&format!("CREATE VIEW {} AS {}", name, sql))
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn drop_view(&mut self, name: &str) -> Result<()> {
+        self.ctx
+            .sql(&format!("DROP VIEW {}", name))
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn create_function(&mut self, definition: &str) -> Result<String> {
+        let function = SqlFunction::parse(definition).map_err(Error::backend)?;
+        function.register(&self.ctx).map_err(Error::backend)?;
+        let name = function.name.clone();
+        self.functions.insert(function);
+        self.functions.save().map_err(Error::backend)?;
+        Ok(name)
+    }
+
+    async fn drop_function(&mut self, name: &str) -> Result<()> {
+        let name = name.to_lowercase();
+        if self.functions.remove(&name).is_none() {
+            return Err(Error::backend(anyhow!("No such function: {}", name)));
+        }
+        self.ctx.deregister_udf(&name);
+        self.functions.save().map_err(Error::backend)
+    }
+
+    async fn functions(&self) -> Result<RecordBatch> {
+        self.functions.batch().map_err(Error::backend)
+    }
+
+    async fn load_functions(&mut self, path: &Path) -> Result<usize> {
+        let functions = SqlFunctions::read(path).map_err(Error::backend)?;
+        let count = functions.len();
+        for function in functions {
+            function.register(&self.ctx).map_err(Error::backend)?;
+            self.functions.insert(function);
+        }
+        self.functions.path = Some(path.to_path_buf());
+        Ok(count)
+    }
+
+    async fn parquet_meta(&self, target: &str) -> Result<String> {
+        let files = self.parquet_files(target).await.map_err(Error::backend)?;
+        let rendered = files
+            .iter()
+            .map(|file| parquet::render(file, &parquet::read_metadata(file)?))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map_err(Error::backend)?;
+        Ok(rendered.join("\n\n"))
+    }
+
+    async fn convert(&self, opts: &ConvertOpts) -> Result<u64> {
+        convert::convert(&self.ctx, opts)
+            .await
+            .map_err(Error::backend)
+    }
+
+    async fn explain(&self, sql: &str, analyze: bool, verbose: bool) -> Result<String> {
+        let df = variables::plan(&self.ctx, sql, &[], &self.variables)
+            .await
+            .map_err(Error::backend)?;
+        explain::explain(df, analyze, verbose)
+            .await
+            .map_err(Error::backend)
+    }
+
+    async fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        if self.settings.set(key, value).map_err(Error::backend)? {
+            return Ok(());
+        }
+        let mut memory = self.memory.clone();
+        if memory.set(key, value).map_err(Error::backend)? {
+            self.ctx =
+                memory::with_runtime(&self.ctx, memory.runtime_env().map_err(Error::backend)?)
+                    .map_err(Error::backend)?;
+            self.memory = memory;
+            return Ok(());
+        }
+
+        // the DataFusion options are validated by the options registry of the session
+        let variable = settings::find(&self.setting_list(), key)
+            .map_err(Error::backend)?
+            .key
+            .clone();
+        let plan = LogicalPlan::Statement(Statement::SetVariable(SetVariable {
+            variable,
+            value: value.to_string(),
+            schema: Arc::new(DFSchema::empty()),
+        }));
+        self.ctx
+            .execute_logical_plan(plan)
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn show(&self, key: &str) -> Result<RecordBatch> {
+        let settings = self.setting_list();
+        if key == "all" {
+            return settings::settings_batch(&settings).map_err(Error::backend);
+        }
+        let setting = settings::find(&settings, key).map_err(Error::backend)?;
+        settings::settings_batch(std::slice::from_ref(setting)).map_err(Error::backend)
+    }
+
+    async fn save_settings(&self, path: &Path) -> Result<usize> {
+        let defaults = settings::list(
+            &ReplSettings::default(),
+            &MemorySettings::default(),
+            &session_config(),
+        );
+        settings::save(path, &self.setting_list(), &defaults).map_err(Error::backend)
+    }
+
+    async fn load_settings(&mut self, path: &Path) -> Result<usize> {
+        let settings = settings::load(path).map_err(Error::backend)?;
+        self.check_settings(&settings)
+            .map_err(|e| anyhow!("Invalid settings in {}: {}", path.display(), e))
+            .map_err(Error::backend)?;
+        for (key, value) in &settings {
+            self.set(key, value).await?;
+        }
+        Ok(settings.len())
+    }
+
+    async fn jobs(&self) -> Result<RecordBatch> {
+        jobs::jobs_batch(&self.jobs.list()).map_err(Error::backend)
+    }
+
+    async fn sql_background(&self, sql: &str) -> Result<usize> {
+        let df = variables::plan(&self.ctx, sql, &[], &self.variables)
+            .await
+            .map_err(Error::backend)?;
+        let ctx = self.ctx.clone();
+        let id = self
+            .jobs
+            .spawn(format!("sql {}", sql), move |id| async move {
+                let schema = df.schema().inner().clone();
+                let batches = df.collect().await.map_err(query_error)?;
+                let schema = batches.first().map_or(schema, |batch| batch.schema());
+                let rows = batches.iter().map(|batch| batch.num_rows()).sum();
+                let table = MemTable::try_new(schema, vec![batches])?;
+                ctx.register_table(job_table(id).as_str(), Arc::new(table))?;
+                Ok(rows)
+            });
+        Ok(id)
+    }
+
+    async fn wait(&self, id: usize) -> Result<String> {
+        let job = self.jobs.wait(id).await.map_err(Error::backend)?;
+        let elapsed = jobs::format_elapsed(job.elapsed);
+        let msg = match job.state {
+            JobState::Finished { rows } => format!(
+                "Job {} finished in {}, {} rows in table {}",
+                id,
+                elapsed,
+                rows,
+                job_table(id)
+            ),
+            state => format!("Job {} {} after {}", id, state, elapsed),
+        };
+        Ok(msg)
+    }
+
+    async fn cancel(&self, id: usize) -> Result<()> {
+        self.jobs.cancel(id).map_err(Error::backend)
+    }
+
+    async fn result(&self, id: usize) -> Result<DataFrame> {
+        let job = self
+            .jobs
+            .get(id)
+            .ok_or_else(|| Error::backend(anyhow!("No such job: {}", id)))?;
+        let df = match job.state {
+            JobState::Finished { .. } => self.ctx.table(job_table(id)).await.map_err(query_error),
+            JobState::Running => Err(anyhow!("Job {} is still running, use `wait {}`", id, id)),
+            state => Err(anyhow!("Job {} {}", id, state)),
+        };
+        df.map_err(Error::backend)
+    }
+
+    async fn clear_jobs(&self) -> Result<usize> {
+        let cleared = self.jobs.clear();
+        for (id, state) in &cleared {
+            if let JobState::Finished { .. } = state {
+                self.ctx
+                    .deregister_table(job_table(*id).as_str())
+                    .map_err(backend_error)?;
+            }
+        }
+        Ok(cleared.len())
+    }
+
+    fn settings(&self) -> &ReplSettings {
+        &self.settings
+    }
 }
 
 impl DataFusionBackend {
     pub fn new() -> Self {
-        let mut config = SessionConfig::new();
-        config.options_mut().catalog.information_schema = true;
-        let ctx = SessionContext::new_with_config(config);
-        Self(ctx)
+        let ctx = SessionContext::new_with_config(session_config());
+        udf::register_all(&ctx);
+        Self {
+            ctx,
+            settings: ReplSettings::default(),
+            memory: MemorySettings::default(),
+            cached: HashMap::new(),
+            variables: Variables::new(),
+            functions: SqlFunctions::default(),
+            jobs: JobRegistry::default(),
+        }
+    }
+
+    /// The table read by a dataset, the source of the cached ones.
+    async fn source(&self, name: &str) -> anyhow::Result<Arc<dyn TableProvider>> {
+        let table = TableReference::from(name).table().to_string();
+        match self.cached.get(&table) {
+            Some(cached) => Ok(cached.source.clone()),
+            None => Ok(self.ctx.table_provider(name).await?),
+        }
     }
+
+    /// The parquet files of a path, or else of a dataset.
+    async fn parquet_files(&self, target: &str) -> anyhow::Result<Vec<PathBuf>> {
+        let path = Path::new(target);
+        if path.exists() {
+            return parquet::path_files(path);
+        }
+        let name = variables::resolve_name(target, &self.variables)?;
+        let source = self
+            .source(name)
+            .await
+            .map_err(|_| anyhow!("No such file or dataset: {}", target))?;
+        parquet::dataset_files(&self.ctx.state(), &source, name).await
+    }
+
+    pub fn job_registry(&self) -> &JobRegistry {
+        &self.jobs
+    }
+
+    /// The settings of the REPL and of the runtime, then the DataFusion options.
+    fn setting_list(&self) -> Vec<Setting> {
+        settings::list(&self.settings, &self.memory, &self.ctx.copied_config())
+    }
+
+    /// Check settings on copies of the current ones, so that none is applied if one
+    /// is invalid.
+    fn check_settings(&self, settings: &[(String, String)]) -> anyhow::Result<()> {
+        let mut repl = self.settings.clone();
+        let mut memory = self.memory.clone();
+        let mut config = self.ctx.copied_config();
+        let list = self.setting_list();
+        for (key, value) in settings {
+            if repl.set(key, value)? || memory.set(key, value)? {
+                continue;
+            }
+            let key = &settings::find(&list, key)?.key;
+            config.options_mut().set(key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The DataFusion options of a new session.
+fn session_config() -> SessionConfig {
+    let mut config = SessionConfig::new();
+    config.options_mut().catalog.information_schema = true;
+    config
 }
 
 impl Default for DataFusionBackend {
@@ -100,21 +651,258 @@ impl Deref for DataFusionBackend {
     type Target = SessionContext;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.ctx
     }
 }
 
+/// A failed query as an error of the backend.
+fn backend_error(err: DataFusionError) -> Error {
+    Error::backend(query_error(err))
+}
+
 impl ReplDisplay for DataFrame {
-    async fn display(self) -> anyhow::Result<String> {
-        let batches = self.collect().await?;
-        let data = pretty_format_batches(&batches)?;
-        Ok(data.to_string())
+    async fn display_with_stats(
+        self,
+        settings: &ReplSettings,
+    ) -> anyhow::Result<(String, QueryStats)> {
+        let start = Instant::now();
+        let task_ctx = Arc::new(self.task_ctx());
+        let plan = self.create_physical_plan().await.map_err(query_error)?;
+        let planning = start.elapsed();
+
+        let stream = execute_stream(plan.clone(), task_ctx).map_err(query_error)?;
+        let (batches, truncated) = read_capped(stream, settings.max_rows).await?;
+
+        let stats = QueryStats {
+            rows: batches.iter().map(|batch| batch.num_rows()).sum(),
+            truncated,
+            planning,
+            execution: start.elapsed() - planning,
+            bytes_scanned: bytes_scanned(plan.as_ref()),
+        };
+        Ok((format_batches(&batches, truncated)?, stats))
+    }
+
+    fn arrow_schema(&self) -> SchemaRef {
+        Arc::new(self.schema().as_arrow().clone())
+    }
+
+    async fn batches(self) -> anyhow::Result<Vec<RecordBatch>> {
+        self.collect().await.map_err(query_error)
+    }
+
+    async fn capped_batches(
+        self,
+        max_rows: Option<usize>,
+    ) -> anyhow::Result<(Vec<RecordBatch>, bool)> {
+        let stream = self.execute_stream().await.map_err(query_error)?;
+        read_capped(stream, max_rows).await
     }
 }
 
+/// Read the stream up to `max_rows` rows, and tell whether some were left out. It
+/// stops once a row is left out, dropping the stream cancels the query.
+async fn read_capped(
+    mut stream: SendableRecordBatchStream,
+    max_rows: Option<usize>,
+) -> anyhow::Result<(Vec<RecordBatch>, bool)> {
+    let mut batches = Vec::new();
+    let mut shown = 0;
+    while let Some(batch) = stream.next().await {
+        let batch = batch.map_err(query_error)?;
+        let rows = batch.num_rows();
+        match max_rows {
+            Some(max_rows) if shown + rows > max_rows => {
+                let take = max_rows - shown;
+                if take > 0 {
+                    batches.push(batch.slice(0, take));
+                }
+                return Ok((batches, true));
+            }
+            _ => {
+                shown += rows;
+                batches.push(batch);
+            }
+        }
+    }
+    Ok((batches, false))
+}
+
 impl ReplDisplay for RecordBatch {
-    async fn display(self) -> anyhow::Result<String> {
-        let data = pretty_format_batches(&[self])?;
-        Ok(data.to_string())
+    async fn display_with_stats(
+        self,
+        settings: &ReplSettings,
+    ) -> anyhow::Result<(String, QueryStats)> {
+        let rows = self.num_rows();
+        let data = match settings.max_rows {
+            Some(max_rows) if rows > max_rows => format_batches(&[self.slice(0, max_rows)], true)?,
+            _ => format_batches(&[self], false)?,
+        };
+        let stats = QueryStats {
+            rows,
+            ..Default::default()
+        };
+        Ok((data, stats))
+    }
+
+    fn arrow_schema(&self) -> SchemaRef {
+        self.schema()
+    }
+
+    async fn batches(self) -> anyhow::Result<Vec<RecordBatch>> {
+        Ok(vec![self])
+    }
+}
+
+/// Sum of the `bytes_scanned` metric of all the operators, only file formats
+/// which report it (e.g. parquet) will have a value.
+fn bytes_scanned(plan: &dyn ExecutionPlan) -> Option<usize> {
+    let scanned = plan
+        .metrics()
+        .and_then(|metrics| metrics.sum_by_name("bytes_scanned"))
+        .map(|v| v.as_usize());
+    plan.children()
+        .into_iter()
+        .filter_map(|child| bytes_scanned(child.as_ref()))
+        .fold(scanned, |acc, v| Some(acc.unwrap_or_default() + v))
+}
+
+fn format_batches(batches: &[RecordBatch], truncated: bool) -> anyhow::Result<String> {
+    let mut data = pretty_format_batches(batches)?.to_string();
+    if truncated {
+        data.push_str(&format!("\n{}", MORE_ROWS));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use arrow::array::{Array, AsArray};
+    use datafusion::datasource::MemTable;
+
+    use crate::{Backend, ConnectOpts, DataFusionBackend, ReplDisplay, MORE_ROWS};
+
+    async fn users() -> DataFusionBackend {
+        let mut backend = DataFusionBackend::new();
+        let opts =
+            ConnectOpts::try_parse_from(["connect", "assets/users.ndjson", "--name", "users"])
+                .unwrap();
+        backend.connect(&opts).await.unwrap();
+        // the cap falls inside a batch
+        backend.set("batch_size", "7").await.unwrap();
+        backend
+    }
+
+    #[tokio::test]
+    async fn display_should_cap_the_rows_and_say_more_are_left() {
+        let mut backend = users().await;
+        backend.set("max_rows", "10").await.unwrap();
+        let df = backend.sql("SELECT email FROM users").await.unwrap();
+        let (data, stats) = df.display_with_stats(backend.settings()).await.unwrap();
+        assert_eq!(stats.rows, 10);
+        assert!(stats.truncated);
+        assert!(data.ends_with(MORE_ROWS));
+        assert_eq!(data.matches("@example.").count(), 10);
+
+        let df = backend.sql("SELECT email FROM users").await.unwrap();
+        let (batches, truncated) = df.capped_batches(Some(10)).await.unwrap();
+        assert!(truncated);
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 10);
+    }
+
+    #[tokio::test]
+    async fn display_should_show_all_the_rows_without_a_cap() {
+        let mut backend = users().await;
+        for max_rows in ["0", "off"] {
+            backend.set("max_rows", max_rows).await.unwrap();
+            assert_eq!(backend.settings().max_rows, None);
+            let df = backend.sql("SELECT email FROM users").await.unwrap();
+            let (data, stats) = df.display_with_stats(backend.settings()).await.unwrap();
+            assert_eq!(stats.rows, 100);
+            assert!(!stats.truncated);
+            assert!(!data.contains(MORE_ROWS));
+        }
+        // a cap of the size of the result leaves nothing out
+        let df = backend.sql("SELECT email FROM users").await.unwrap();
+        let (batches, truncated) = df.capped_batches(Some(100)).await.unwrap();
+        assert!(!truncated);
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 100);
+    }
+
+    #[tokio::test]
+    async fn query_over_the_memory_limit_should_say_how_to_raise_it() {
+        let mut backend = users().await;
+        backend.set("memory_limit", "1KB").await.unwrap();
+        backend.set("spill_dir", "off").await.unwrap();
+        let df = backend
+            .sql("SELECT * FROM users ORDER BY email")
+            .await
+            .unwrap();
+        let err = df.display(backend.settings()).await.unwrap_err();
+        let err = err.to_string();
+        assert!(
+            err.starts_with("Query exceeded the memory limit: "),
+            "{}",
+            err
+        );
+        assert!(err.ends_with("Raise it with `set memory_limit <size>`"));
+        assert!(!err.contains("Resources exhausted"));
+    }
+
+    /// The memory column of `list` for the dataset `name`.
+    async fn listed_memory(backend: &DataFusionBackend, name: &str) -> Option<String> {
+        let batch = backend.list().await.unwrap();
+        let names = batch.column(0).as_string::<i32>();
+        let memory = batch.column_by_name("memory").unwrap().as_string::<i32>();
+        let i = names.iter().position(|n| n == Some(name)).unwrap();
+        memory.is_valid(i).then(|| memory.value(i).to_string())
+    }
+
+    async fn is_in_memory(backend: &DataFusionBackend, name: &str) -> bool {
+        let table = backend.table_provider(name).await.unwrap();
+        table.as_any().is::<MemTable>()
+    }
+
+    #[tokio::test]
+    async fn cache_should_load_the_dataset_until_uncached() {
+        let mut backend = users().await;
+        let output = backend.cache("users", Some(3)).await.unwrap();
+        assert!(output.starts_with("Cached users: 100 rows, "));
+        assert!(output.ends_with(" in 3 partitions"));
+        assert!(is_in_memory(&backend, "users").await);
+        assert!(listed_memory(&backend, "users").await.is_some());
+        let err = backend.cache("users", None).await.unwrap_err();
+        assert_eq!(err.to_string(), "Dataset users is already cached");
+
+        let df = backend.sql("SELECT count(*) FROM users").await.unwrap();
+        assert!(df
+            .display(backend.settings())
+            .await
+            .unwrap()
+            .contains("| 100 "));
+
+        backend.uncache("users").await.unwrap();
+        assert!(!is_in_memory(&backend, "users").await);
+        assert_eq!(listed_memory(&backend, "users").await, None);
+    }
+
+    #[tokio::test]
+    async fn connect_should_forget_a_dropped_cached_dataset() {
+        let mut backend = users().await;
+        backend.cache("users", None).await.unwrap();
+        backend.sql("DROP TABLE users").await.unwrap();
+        let opts =
+            ConnectOpts::try_parse_from(["connect", "assets/juventus.csv", "--name", "users"])
+                .unwrap();
+        backend.connect(&opts).await.unwrap();
+
+        assert!(!is_in_memory(&backend, "users").await);
+        assert_eq!(listed_memory(&backend, "users").await, None);
+        let err = backend.uncache("users").await.unwrap_err();
+        assert_eq!(err.to_string(), "Dataset users is not cached");
+        let df = backend.sql("SELECT position FROM users").await.unwrap();
+        assert!(df.display(backend.settings()).await.is_ok());
     }
 }
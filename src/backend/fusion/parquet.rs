@@ -0,0 +1,478 @@
+use std::{
+    fmt::Write,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, bail};
+use arrow::{
+    array::{Array, ArrayRef, RecordBatch, StringArray, UInt64Array},
+    datatypes::{DataType, Field, Schema},
+    util::{display::array_value_to_string, pretty::pretty_format_batches},
+};
+use bytesize::ByteSize;
+use datafusion::{
+    datasource::{
+        file_format::parquet::ParquetFormat,
+        listing::ListingTable,
+        physical_plan::parquet::{RequestedStatistics, StatisticsConverter},
+        TableProvider,
+    },
+    execution::context::SessionState,
+    parquet::{
+        arrow::parquet_to_arrow_schema,
+        basic::{Compression, ConvertedType},
+        file::{footer::parse_metadata, metadata::ParquetMetaData, statistics::Statistics},
+    },
+};
+use futures::TryStreamExt;
+
+/// The parquet files of a dataset, listed as the dataset lists them, i.e. with its
+/// globs and its file extension.
+pub async fn dataset_files(
+    state: &SessionState,
+    provider: &Arc<dyn TableProvider>,
+    name: &str,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let Some(listing) = provider.as_any().downcast_ref::<ListingTable>() else {
+        bail!("Dataset {} is not read from files", name);
+    };
+    let options = listing.options();
+    if !options.format.as_any().is::<ParquetFormat>() {
+        bail!("Dataset {} is not read from parquet files", name);
+    }
+    let mut files = vec![];
+    for url in listing.table_paths() {
+        if url.scheme() != "file" {
+            bail!("Dataset {} is not read from local files", name);
+        }
+        let store = state.runtime_env().object_store(url)?;
+        let objects: Vec<_> = url
+            .list_all_files(state, store.as_ref(), &options.file_extension)
+            .await?
+            .try_collect()
+            .await?;
+        files.extend(
+            objects
+                .iter()
+                .map(|object| Path::new("/").join(object.location.as_ref())),
+        );
+    }
+    if files.is_empty() {
+        bail!("Dataset {} has no parquet file", name);
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The parquet files of a file or a directory.
+pub fn path_files(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    collect_files(path, &mut files)?;
+    if files.is_empty() {
+        bail!("No parquet file in {}", path.display());
+    }
+    Ok(files)
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if path.is_file() {
+        if path.extension().is_some_and(|ext| ext == "parquet") {
+            files.push(path.to_path_buf());
+        }
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        collect_files(&entry, files)?;
+    }
+    Ok(())
+}
+
+/// The footer of a parquet file, without reading its pages.
+pub fn read_metadata(path: &Path) -> anyhow::Result<ParquetMetaData> {
+    let file = File::open(path).map_err(|e| anyhow!("Failed to open {}: {}", path.display(), e))?;
+    let metadata = parse_metadata(&file)
+        .map_err(|e| anyhow!("Invalid parquet file {}: {}", path.display(), e))?;
+    Ok(metadata)
+}
+
+/// Render the metadata of a parquet file: the file, its row groups, the column
+/// chunks of the row groups and the key-value metadata.
+pub fn render(path: &Path, metadata: &ParquetMetaData) -> anyhow::Result<String> {
+    let mut out = String::new();
+    writeln!(out, "File: {}", path.display())?;
+    writeln!(
+        out,
+        "{}",
+        pretty_format_batches(&[file_batch(path, metadata)?])?
+    )?;
+    writeln!(out, "Row groups:")?;
+    writeln!(
+        out,
+        "{}",
+        pretty_format_batches(&[row_groups_batch(metadata)?])?
+    )?;
+    writeln!(out, "Column chunks:")?;
+    writeln!(
+        out,
+        "{}",
+        pretty_format_batches(&[columns_batch(metadata)?])?
+    )?;
+    match metadata.file_metadata().key_value_metadata() {
+        Some(kv) if !kv.is_empty() => {
+            let keys = StringArray::from_iter_values(kv.iter().map(|kv| &kv.key));
+            let values =
+                StringArray::from_iter(kv.iter().map(|kv| kv.value.as_deref().map(truncate)));
+            let batch = string_batch(&["key", "value"], vec![keys, values])?;
+            writeln!(out, "Key-value metadata:")?;
+            write!(out, "{}", pretty_format_batches(&[batch])?)?;
+        }
+        _ => write!(out, "Key-value metadata: none")?,
+    }
+    Ok(out)
+}
+
+fn file_batch(path: &Path, metadata: &ParquetMetaData) -> anyhow::Result<RecordBatch> {
+    let file = metadata.file_metadata();
+    let size = fs::metadata(path)?.len();
+    let has_page_index = metadata
+        .row_groups()
+        .iter()
+        .flat_map(|rg| rg.columns())
+        .any(|c| c.column_index_offset().is_some() || c.offset_index_offset().is_some());
+    let entries = [
+        ("version", file.version().to_string()),
+        (
+            "created_by",
+            file.created_by().unwrap_or("unknown").to_string(),
+        ),
+        ("rows", file.num_rows().to_string()),
+        ("row_groups", metadata.num_row_groups().to_string()),
+        ("columns", file.schema_descr().num_columns().to_string()),
+        ("size", ByteSize(size).to_string()),
+        ("page_index", yes_no(has_page_index).to_string()),
+    ];
+    let names = StringArray::from_iter_values(entries.iter().map(|(k, _)| k));
+    let values = StringArray::from_iter_values(entries.iter().map(|(_, v)| v));
+    string_batch(&["name", "value"], vec![names, values])
+}
+
+fn row_groups_batch(metadata: &ParquetMetaData) -> anyhow::Result<RecordBatch> {
+    let row_groups = metadata.row_groups();
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("row_group", DataType::UInt64, false),
+        Field::new("rows", DataType::UInt64, false),
+        Field::new("compressed", DataType::Utf8, false),
+        Field::new("uncompressed", DataType::Utf8, false),
+        Field::new("sorted_by", DataType::Utf8, true),
+    ]));
+    let sorted_by = row_groups.iter().map(|rg| {
+        let columns = rg.sorting_columns()?;
+        let schema = rg.schema_descr();
+        let names: Vec<_> = columns
+            .iter()
+            .map(|c| {
+                let name = schema.column(c.column_idx as usize).path().string();
+                let order = if c.descending { "DESC" } else { "ASC" };
+                format!("{} {}", name, order)
+            })
+            .collect();
+        Some(names.join(", "))
+    });
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(0..row_groups.len() as u64)),
+        Arc::new(UInt64Array::from_iter_values(
+            row_groups.iter().map(|rg| rg.num_rows() as u64),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            row_groups.iter().map(|rg| size(rg.compressed_size())),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            row_groups.iter().map(|rg| size(rg.total_byte_size())),
+        )),
+        Arc::new(StringArray::from_iter(sorted_by)),
+    ];
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+/// One row per column chunk, the min and max are typed as the arrow columns when
+/// the column is not nested.
+fn columns_batch(metadata: &ParquetMetaData) -> anyhow::Result<RecordBatch> {
+    let file = metadata.file_metadata();
+    let arrow_schema = parquet_to_arrow_schema(file.schema_descr(), file.key_value_metadata())?;
+    let mut min_max = vec![];
+    for column in file.schema_descr().columns() {
+        let path = column.path().parts();
+        let stats = match path {
+            [name] if arrow_schema.column_with_name(name).is_some() => {
+                let extract = |requested| {
+                    StatisticsConverter::try_new(name, requested, &arrow_schema)?.extract(metadata)
+                };
+                Some((
+                    extract(RequestedStatistics::Min)?,
+                    extract(RequestedStatistics::Max)?,
+                ))
+            }
+            _ => None,
+        };
+        min_max.push(stats);
+    }
+
+    let mut rows = ColumnRows::default();
+    for (idx, rg) in metadata.row_groups().iter().enumerate() {
+        for (col_idx, chunk) in rg.columns().iter().enumerate() {
+            let descr = chunk.column_descr();
+            let logical = match (descr.converted_type(), descr.logical_type()) {
+                (ConvertedType::NONE, Some(t)) => format!(" ({:?})", t),
+                (ConvertedType::NONE, None) => String::new(),
+                (t, _) => format!(" ({})", t),
+            };
+            let encodings: Vec<_> = chunk.encodings().iter().map(|e| e.to_string()).collect();
+            let stats = chunk.statistics();
+            let value = |array: &ArrayRef| {
+                (stats.is_some_and(|s| s.has_min_max_set()))
+                    .then(|| array_value_to_string(array, idx).ok())
+                    .flatten()
+            };
+            let (min, max) = match (&min_max[col_idx], stats) {
+                (Some((min, max)), _) => (value(min), value(max)),
+                (None, Some(stats)) => raw_min_max(stats).unzip(),
+                (None, None) => (None, None),
+            };
+            let page_index = match (chunk.column_index_offset(), chunk.offset_index_offset()) {
+                (Some(_), Some(_)) => "column, offset",
+                (Some(_), None) => "column",
+                (None, Some(_)) => "offset",
+                (None, None) => "none",
+            };
+            let bloom_filter = match (chunk.bloom_filter_offset(), chunk.bloom_filter_length()) {
+                (Some(_), Some(len)) => size(len as i64),
+                (Some(_), None) => "yes".to_string(),
+                _ => "none".to_string(),
+            };
+
+            rows.row_group.push(idx as u64);
+            rows.column.push(chunk.column_path().string());
+            rows.data_type
+                .push(format!("{}{}", chunk.column_type(), logical));
+            rows.codec.push(codec(chunk.compression()));
+            rows.encodings.push(encodings.join(", "));
+            rows.compressed.push(size(chunk.compressed_size()));
+            rows.uncompressed.push(size(chunk.uncompressed_size()));
+            rows.nulls.push(stats.map(|s| s.null_count()));
+            rows.distinct.push(stats.and_then(|s| s.distinct_count()));
+            rows.min.push(min);
+            rows.max.push(max);
+            rows.page_index.push(page_index);
+            rows.bloom_filter.push(bloom_filter);
+        }
+    }
+    rows.batch()
+}
+
+#[derive(Default)]
+struct ColumnRows {
+    row_group: Vec<u64>,
+    column: Vec<String>,
+    data_type: Vec<String>,
+    codec: Vec<String>,
+    encodings: Vec<String>,
+    compressed: Vec<String>,
+    uncompressed: Vec<String>,
+    nulls: Vec<Option<u64>>,
+    distinct: Vec<Option<u64>>,
+    min: Vec<Option<String>>,
+    max: Vec<Option<String>>,
+    page_index: Vec<&'static str>,
+    bloom_filter: Vec<String>,
+}
+
+impl ColumnRows {
+    fn batch(self) -> anyhow::Result<RecordBatch> {
+        let utf8 = |name, nullable| Field::new(name, DataType::Utf8, nullable);
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("row_group", DataType::UInt64, false),
+            utf8("column", false),
+            utf8("type", false),
+            utf8("codec", false),
+            utf8("encodings", false),
+            utf8("compressed", false),
+            utf8("uncompressed", false),
+            Field::new("nulls", DataType::UInt64, true),
+            Field::new("distinct", DataType::UInt64, true),
+            utf8("min", true),
+            utf8("max", true),
+            utf8("page_index", false),
+            utf8("bloom_filter", false),
+        ]));
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from(self.row_group)),
+            Arc::new(StringArray::from(self.column)),
+            Arc::new(StringArray::from(self.data_type)),
+            Arc::new(StringArray::from(self.codec)),
+            Arc::new(StringArray::from(self.encodings)),
+            Arc::new(StringArray::from(self.compressed)),
+            Arc::new(StringArray::from(self.uncompressed)),
+            Arc::new(UInt64Array::from(self.nulls)),
+            Arc::new(UInt64Array::from(self.distinct)),
+            Arc::new(StringArray::from(self.min)),
+            Arc::new(StringArray::from(self.max)),
+            Arc::new(StringArray::from(self.page_index)),
+            Arc::new(StringArray::from(self.bloom_filter)),
+        ];
+        Ok(RecordBatch::try_new(schema, columns)?)
+    }
+}
+
+fn string_batch(names: &[&str], columns: Vec<StringArray>) -> anyhow::Result<RecordBatch> {
+    let fields: Vec<_> = names
+        .iter()
+        .zip(&columns)
+        .map(|(name, column)| Field::new(*name, DataType::Utf8, column.null_count() > 0))
+        .collect();
+    let columns = columns
+        .into_iter()
+        .map(|c| Arc::new(c) as ArrayRef)
+        .collect();
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns,
+    )?)
+}
+
+/// The min and max of the nested columns, as their physical values.
+fn raw_min_max(stats: &Statistics) -> Option<(String, String)> {
+    if !stats.has_min_max_set() {
+        return None;
+    }
+    let min_max = match stats {
+        Statistics::Boolean(s) => (s.min().to_string(), s.max().to_string()),
+        Statistics::Int32(s) => (s.min().to_string(), s.max().to_string()),
+        Statistics::Int64(s) => (s.min().to_string(), s.max().to_string()),
+        Statistics::Float(s) => (s.min().to_string(), s.max().to_string()),
+        Statistics::Double(s) => (s.min().to_string(), s.max().to_string()),
+        Statistics::ByteArray(s) => (
+            String::from_utf8_lossy(s.min_bytes()).to_string(),
+            String::from_utf8_lossy(s.max_bytes()).to_string(),
+        ),
+        Statistics::Int96(_) | Statistics::FixedLenByteArray(_) => return None,
+    };
+    Some(min_max)
+}
+
+/// The levels are not stored in the files, only the codecs.
+fn codec(compression: Compression) -> String {
+    let name = match compression {
+        Compression::GZIP(_) => "GZIP",
+        Compression::BROTLI(_) => "BROTLI",
+        Compression::ZSTD(_) => "ZSTD",
+        c => return c.to_string(),
+    };
+    name.to_string()
+}
+
+fn size(bytes: i64) -> String {
+    ByteSize(bytes.max(0) as u64).to_string()
+}
+
+fn yes_no(b: bool) -> &'static str {
+    if b {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// The arrow schema is a long base64 string, keep the start of the long values.
+fn truncate(value: &str) -> String {
+    const MAX: usize = 60;
+    match value.char_indices().nth(MAX) {
+        Some((idx, _)) => format!("{}... ({} bytes)", &value[..idx], value.len()),
+        None => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::{ParquetReadOptions, SessionContext};
+
+    use super::*;
+
+    const SAMPLE: &str = "assets/sample.parquet";
+
+    /// The rendered values of a column of `batch`.
+    fn column(batch: &RecordBatch, name: &str) -> Vec<String> {
+        let array = batch.column_by_name(name).unwrap();
+        (0..array.len())
+            .map(|i| array_value_to_string(array, i).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn render_should_describe_the_footer() {
+        let path = Path::new(SAMPLE);
+        let metadata = read_metadata(path).unwrap();
+
+        let row_groups = row_groups_batch(&metadata).unwrap();
+        assert_eq!(column(&row_groups, "row_group"), vec!["0"]);
+        assert_eq!(column(&row_groups, "rows"), vec!["1000"]);
+
+        let columns = columns_batch(&metadata).unwrap();
+        assert_eq!(columns.num_rows(), 13);
+        assert!(column(&columns, "codec").iter().all(|c| c == "ZSTD"));
+        assert!(column(&columns, "page_index").iter().all(|c| c == "none"));
+        assert!(column(&columns, "bloom_filter").iter().all(|c| c == "none"));
+        let gender = column(&columns, "column")
+            .iter()
+            .position(|c| c == "gender")
+            .unwrap();
+        assert_eq!(column(&columns, "type")[gender], "BYTE_ARRAY (UTF8)");
+        assert_eq!(column(&columns, "nulls")[gender], "0");
+        assert_eq!(column(&columns, "min")[gender], "female");
+        assert_eq!(column(&columns, "max")[gender], "unknown");
+        assert!(column(&columns, "column").contains(&"finished.list.element".to_string()));
+
+        let rendered = render(path, &metadata).unwrap();
+        assert!(rendered.starts_with("File: assets/sample.parquet\n"));
+        assert!(rendered.contains("| row_groups | 1 "));
+        assert!(rendered.contains("| page_index | no "));
+        assert!(rendered.ends_with("Key-value metadata: none"));
+    }
+
+    #[tokio::test]
+    async fn dataset_files_should_follow_the_listing_of_the_dataset() {
+        let dir = std::env::temp_dir().join("taotie_dataset_files");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.parquet", "b.parquet", "c.parquet.bak"] {
+            fs::copy(SAMPLE, dir.join(name)).unwrap();
+        }
+        let ctx = SessionContext::new();
+        let all = dir.to_str().unwrap();
+        let glob = format!("{}/a*.parquet", all);
+        for (name, path) in [("all", all), ("glob", glob.as_str())] {
+            ctx.register_parquet(name, path, ParquetReadOptions::default())
+                .await
+                .unwrap();
+        }
+
+        let files = |name: &'static str| {
+            let ctx = ctx.clone();
+            async move {
+                let provider = ctx.table_provider(name).await.unwrap();
+                dataset_files(&ctx.state(), &provider, name).await.unwrap()
+            }
+        };
+        assert_eq!(
+            files("all").await,
+            vec![dir.join("a.parquet"), dir.join("b.parquet")]
+        );
+        assert_eq!(files("glob").await, vec![dir.join("a.parquet")]);
+    }
+}
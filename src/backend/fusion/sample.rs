@@ -0,0 +1,101 @@
+use anyhow::bail;
+
+use crate::{cli::SampleOpts, quote_ident};
+
+/// The rows are sorted or filtered by `seeded_random` of all their values, so that a
+/// seed always gives the same sample of the same data. The copies of a row are numbered
+/// among themselves and hashed with their number, so that they are drawn one by one.
+pub fn query(
+    name: &str,
+    columns: &[String],
+    opts: &SampleOpts,
+    seed: u64,
+) -> anyhow::Result<String> {
+    for column in &opts.by {
+        if !columns.contains(column) {
+            bail!("No column {} in {}", column, name);
+        }
+    }
+    let all = quoted(columns.iter()).join(", ");
+    let key = format!("seeded_random({}, {}, __copy)", seed as i64, all);
+    // the rows are partitioned by their hash as the nested values can't be compared
+    let source = format!(
+        "(SELECT *, row_number() OVER (PARTITION BY seeded_random({}, {})) AS __copy FROM {})",
+        seed as i64, all, name
+    );
+    let query = match (&opts.by[..], opts.fraction) {
+        ([], None) => format!(
+            "SELECT {} FROM {} ORDER BY {} LIMIT {}",
+            all,
+            source,
+            key,
+            opts.size()
+        ),
+        ([], Some(fraction)) => format!(
+            "SELECT {} FROM {} WHERE {} < {}",
+            all, source, key, fraction
+        ),
+        // each group has its own ranking of the rows
+        (by, fraction) => {
+            let by = quoted(by.iter()).join(", ");
+            let (count, limit) = match fraction {
+                // at least one row of the small groups
+                Some(fraction) => (
+                    format!(", count(*) OVER (PARTITION BY {}) AS __count", by),
+                    format!("ceil({} * __count)", fraction),
+                ),
+                None => (String::new(), opts.size().to_string()),
+            };
+            format!(
+                "SELECT {} FROM (
+                    SELECT *, row_number() OVER (PARTITION BY {} ORDER BY {}) AS __rank{} FROM {}
+                 ) WHERE __rank <= {} ORDER BY {}, __rank",
+                all, by, key, count, source, limit, by
+            )
+        }
+    };
+    Ok(query)
+}
+
+fn quoted<'a>(columns: impl Iterator<Item = &'a String>) -> Vec<String> {
+    columns.map(|c| quote_ident(c)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{array::AsArray, datatypes::Int64Type};
+    use clap::Parser;
+
+    use super::query;
+    use crate::{
+        backend::fusion::udf::test_utils::{self, assets_ctx},
+        cli::SampleOpts,
+    };
+
+    #[tokio::test]
+    async fn sample_should_draw_the_copies_of_a_row_one_by_one() {
+        let ctx = assets_ctx().await;
+        ctx.sql("CREATE VIEW genders AS SELECT gender FROM users")
+            .await
+            .unwrap();
+        let opts = SampleOpts::try_parse_from(["sample", "genders", "--fraction", "0.5"]).unwrap();
+        let sample = query("genders", &["gender".to_string()], &opts, 42).unwrap();
+        let sql = format!(
+            "SELECT s.gender, s.n, a.n FROM
+               (SELECT gender, count(*) AS n FROM ({}) GROUP BY gender) s
+               JOIN (SELECT gender, count(*) AS n FROM users GROUP BY gender) a
+               ON s.gender IS NOT DISTINCT FROM a.gender ORDER BY s.gender",
+            sample
+        );
+        let batch = test_utils::query(&ctx, &sql).await;
+        assert_eq!(batch.num_rows(), 3);
+        let sampled = batch.column(1).as_primitive::<Int64Type>();
+        let total = batch.column(2).as_primitive::<Int64Type>();
+        for i in 0..batch.num_rows() {
+            assert!(0 < sampled.value(i) && sampled.value(i) < total.value(i));
+        }
+        // the same seed draws the same rows
+        let again = test_utils::query(&ctx, &sql).await;
+        assert_eq!(batch.column(1), again.column(1));
+    }
+}
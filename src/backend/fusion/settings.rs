@@ -0,0 +1,137 @@
+use std::{fmt::Write, fs, path::Path, sync::Arc};
+
+use anyhow::{anyhow, bail};
+use arrow::{
+    array::{RecordBatch, StringArray},
+    datatypes::{DataType, Field, Schema},
+};
+use datafusion::prelude::SessionConfig;
+
+use super::memory::MemorySettings;
+use crate::cli::ReplSettings;
+
+/// A session setting of the REPL, of the runtime or of DataFusion.
+pub struct Setting {
+    pub key: String,
+    pub value: Option<String>,
+    pub description: String,
+}
+
+/// The settings of the REPL, of the runtime and of DataFusion.
+pub fn list(repl: &ReplSettings, memory: &MemorySettings, config: &SessionConfig) -> Vec<Setting> {
+    let local =
+        repl.entries()
+            .into_iter()
+            .chain(memory.entries())
+            .map(|(key, value, description)| Setting {
+                key: key.to_string(),
+                value: Some(value),
+                description: description.to_string(),
+            });
+    let options = config.options().entries().into_iter().map(|entry| Setting {
+        key: entry.key,
+        value: entry.value,
+        description: entry.description.to_string(),
+    });
+    local.chain(options).collect()
+}
+
+/// Find the setting named `key`, the DataFusion options can be named by their
+/// last segment when it is unique, e.g. `target_partitions`.
+pub fn find<'a>(settings: &'a [Setting], key: &str) -> anyhow::Result<&'a Setting> {
+    if let Some(setting) = settings.iter().find(|s| s.key == key) {
+        return Ok(setting);
+    }
+    let suffix = format!(".{}", key);
+    let found: Vec<_> = settings
+        .iter()
+        .filter(|s| s.key.ends_with(&suffix))
+        .collect();
+    match found.as_slice() {
+        [setting] => Ok(setting),
+        [] => bail!("Unknown setting: {}, use `show all` to list them", key),
+        found => {
+            let keys: Vec<_> = found.iter().map(|s| s.key.as_str()).collect();
+            bail!("Ambiguous setting {}, one of: {}", key, keys.join(", "))
+        }
+    }
+}
+
+pub fn settings_batch(settings: &[Setting]) -> anyhow::Result<RecordBatch> {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("name", DataType::Utf8, false),
+        Field::new("value", DataType::Utf8, true),
+        Field::new("description", DataType::Utf8, false),
+    ]));
+    let keys = StringArray::from_iter_values(settings.iter().map(|s| &s.key));
+    let values = StringArray::from_iter(settings.iter().map(|s| s.value.as_deref()));
+    let descriptions = StringArray::from_iter_values(settings.iter().map(|s| &s.description));
+    let batch = RecordBatch::try_new(
+        schema,
+        vec![Arc::new(keys), Arc::new(values), Arc::new(descriptions)],
+    )?;
+    Ok(batch)
+}
+
+/// Write the settings which differ from `defaults` as `key = value` lines.
+pub fn save(path: &Path, settings: &[Setting], defaults: &[Setting]) -> anyhow::Result<usize> {
+    let mut content = String::from("# taotie settings, load them with `settings load`\n");
+    let mut count = 0;
+    for setting in settings {
+        let Some(value) = &setting.value else {
+            continue;
+        };
+        let default = defaults.iter().find(|s| s.key == setting.key);
+        if default.is_some_and(|s| s.value.as_ref() == Some(value)) {
+            continue;
+        }
+        writeln!(content, "{} = {}", setting.key, value)?;
+        count += 1;
+    }
+    fs::write(path, content)
+        .map_err(|e| anyhow!("Failed to write settings to {}: {}", path.display(), e))?;
+    Ok(count)
+}
+
+/// Read the `key = value` lines of a settings file, empty lines and comments are skipped.
+pub fn load(path: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read settings from {}: {}", path.display(), e))?;
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.split_once('=') {
+            Some((key, value)) => Ok((key.trim().to_string(), value.trim().to_string())),
+            None => bail!("Invalid line in {}: {}", path.display(), line),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Backend, DataFusionBackend};
+
+    #[tokio::test]
+    async fn load_should_apply_nothing_if_a_line_is_invalid() {
+        let path = std::env::temp_dir().join("taotie_settings_invalid");
+        std::fs::write(&path, "max_rows = 10\ntarget_partitions = lots\n").unwrap();
+        let mut backend = DataFusionBackend::new();
+        assert!(backend.load_settings(&path).await.is_err());
+        assert_eq!(backend.settings().max_rows, Some(100));
+    }
+
+    #[tokio::test]
+    async fn save_should_write_the_changed_settings_only() {
+        let path = std::env::temp_dir().join("taotie_settings_saved");
+        let mut backend = DataFusionBackend::new();
+        assert_eq!(backend.save_settings(&path).await.unwrap(), 0);
+
+        backend.set("max_rows", "10").await.unwrap();
+        backend.set("target_partitions", "3").await.unwrap();
+        assert_eq!(backend.save_settings(&path).await.unwrap(), 2);
+        let mut loaded = DataFusionBackend::new();
+        assert_eq!(loaded.load_settings(&path).await.unwrap(), 2);
+        assert_eq!(loaded.settings().max_rows, Some(10));
+    }
+}
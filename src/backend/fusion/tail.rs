@@ -0,0 +1,157 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use arrow::{array::RecordBatch, compute::concat_batches};
+use datafusion::{
+    common::DFSchema,
+    datasource::physical_plan::{ArrowExec, CsvExec, NdJsonExec, ParquetExec},
+    physical_plan::{execute_stream_partitioned, ExecutionPlan, SendableRecordBatchStream},
+    prelude::SessionContext,
+};
+use futures::{future, StreamExt};
+
+use super::memory::query_error;
+use crate::quote_ident;
+
+/// The last `n` rows of a dataset in the order of its files. The partitions of a scan
+/// are consecutive ranges of the files, each of them keeps its last rows while they
+/// are read concurrently. The other datasets, e.g. views or the saved samples, have
+/// no such order: their rows are numbered as they are read.
+pub async fn last_rows(ctx: &SessionContext, name: &str, n: usize) -> anyhow::Result<RecordBatch> {
+    let df = ctx.table(name).await.map_err(query_error)?;
+    let task_ctx = Arc::new(df.task_ctx());
+    let plan = df
+        .clone()
+        .create_physical_plan()
+        .await
+        .map_err(query_error)?;
+    if !is_file_scan(&plan) {
+        return numbered_rows(ctx, name, df.schema(), n).await;
+    }
+    let streams = execute_stream_partitioned(plan.clone(), task_ctx).map_err(query_error)?;
+    let partitions =
+        future::try_join_all(streams.into_iter().map(|s| partition_tail(s, n))).await?;
+    let batches: Vec<_> = partitions.into_iter().flatten().collect();
+    let batch = concat_batches(&plan.schema(), &batches)?;
+    let rows = batch.num_rows();
+    Ok(batch.slice(rows.saturating_sub(n), rows.min(n)))
+}
+
+fn is_file_scan(plan: &Arc<dyn ExecutionPlan>) -> bool {
+    let plan = plan.as_any();
+    plan.is::<CsvExec>()
+        || plan.is::<NdJsonExec>()
+        || plan.is::<ParquetExec>()
+        || plan.is::<ArrowExec>()
+}
+
+/// The last `n` rows by `row_number()`, for the plans whose partitions are not in order.
+async fn numbered_rows(
+    ctx: &SessionContext,
+    name: &str,
+    schema: &DFSchema,
+    n: usize,
+) -> anyhow::Result<RecordBatch> {
+    let columns: Vec<_> = schema
+        .fields()
+        .iter()
+        .map(|f| quote_ident(f.name()))
+        .collect();
+    let sql = format!(
+        "SELECT {} FROM (SELECT *, row_number() OVER () AS __row FROM {} ORDER BY __row DESC LIMIT {}) ORDER BY __row",
+        columns.join(", "),
+        name,
+        n
+    );
+    let df = ctx.sql(&sql).await.map_err(query_error)?;
+    let schema = df.schema().inner().clone();
+    let batches = df.collect().await.map_err(query_error)?;
+    Ok(concat_batches(&schema, &batches)?)
+}
+
+async fn partition_tail(
+    mut stream: SendableRecordBatchStream,
+    n: usize,
+) -> anyhow::Result<VecDeque<RecordBatch>> {
+    let mut batches = VecDeque::new();
+    let mut rows = 0;
+    while let Some(batch) = stream.next().await {
+        let batch = batch.map_err(query_error)?;
+        rows += batch.num_rows();
+        batches.push_back(batch);
+        // drop the first batches once the others have enough rows
+        while let Some(first) = batches.front() {
+            if rows - first.num_rows() < n {
+                break;
+            }
+            rows -= first.num_rows();
+            batches.pop_front();
+        }
+    }
+    Ok(batches)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{AsArray, RecordBatch};
+    use clap::Parser;
+
+    use super::is_file_scan;
+    use crate::{Backend, ConnectOpts, DataFusionBackend};
+
+    async fn users() -> DataFusionBackend {
+        let mut backend = DataFusionBackend::new();
+        let opts =
+            ConnectOpts::try_parse_from(["connect", "assets/users.ndjson", "--name", "users"])
+                .unwrap();
+        backend.connect(&opts).await.unwrap();
+        backend
+    }
+
+    /// The last `n` emails of the users file, of the `gender` if given.
+    fn file_emails(n: usize, gender: Option<&str>) -> Vec<String> {
+        let content = std::fs::read_to_string("assets/users.ndjson").unwrap();
+        let users: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .filter(|user: &serde_json::Value| gender.is_none_or(|g| user["gender"] == g))
+            .collect();
+        users[users.len() - n..]
+            .iter()
+            .map(|user| user["email"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    fn emails(batch: &RecordBatch) -> Vec<String> {
+        let column = batch.column_by_name("email").unwrap().as_string::<i32>();
+        column
+            .iter()
+            .map(|email| email.unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn tail_should_keep_the_order_of_the_file() {
+        let mut backend = users().await;
+        // the rows are read by small batches in several partitions
+        backend.set("batch_size", "7").await.unwrap();
+        backend.set("target_partitions", "4").await.unwrap();
+        let batch = backend.tail("users", 9, None).await.unwrap();
+        assert_eq!(emails(&batch), file_emails(9, None));
+    }
+
+    #[tokio::test]
+    async fn tail_of_a_view_should_number_its_rows() {
+        let mut backend = users().await;
+        backend.set("target_partitions", "1").await.unwrap();
+        let sql = "SELECT email FROM users WHERE gender = 'female'";
+        backend.create_view("women", sql).await.unwrap();
+        for (name, file_scan) in [("users", true), ("women", false)] {
+            let df = backend.table(name).await.unwrap();
+            let plan = df.create_physical_plan().await.unwrap();
+            assert_eq!(is_file_scan(&plan), file_scan);
+        }
+        let batch = backend.tail("women", 3, None).await.unwrap();
+        assert_eq!(batch.num_columns(), 1);
+        assert_eq!(emails(&batch), file_emails(3, Some("female")));
+    }
+}
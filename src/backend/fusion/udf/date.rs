@@ -0,0 +1,236 @@
+use std::{any::Any, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, Date32Array, StringArray, TimestampNanosecondArray},
+    datatypes::{DataType, Date32Type, TimeUnit},
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use datafusion::{
+    error::Result,
+    logical_expr::{ColumnarValue, ScalarUDFImpl, Signature, Volatility},
+};
+
+use super::invoke_arrays;
+
+/// The formats tried when none is given, the most common unambiguous ones.
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%Y%m%d",
+    "%b %d, %Y",
+    "%B %d, %Y",
+    "%d %b %Y",
+    "%d %B %Y",
+];
+
+const TIMESTAMP_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%Y/%m/%d %H:%M:%S%.f",
+];
+
+/// `parse_date(value [, format, ...])`: the date at the start of the value, parsed
+/// with the first matching chrono format. The text after the date is ignored, so
+/// `"Apr 18, 1990 (29)"` is parsed by `'%b %d, %Y'`. Values which don't match are null.
+#[derive(Debug)]
+pub struct ParseDate {
+    signature: Signature,
+}
+
+impl ParseDate {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::variadic(vec![DataType::Utf8], Volatility::Immutable),
+        }
+    }
+}
+
+impl ScalarUDFImpl for ParseDate {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "parse_date"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Date32)
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        invoke_arrays(args, |arrays| {
+            let dates = parse_rows(arrays, DATE_FORMATS, parse_date)
+                .map(|date| date.map(Date32Type::from_naive_date));
+            Ok(Arc::new(Date32Array::from_iter(dates)) as ArrayRef)
+        })
+    }
+}
+
+/// `parse_timestamp(value [, format, ...])`: the naive timestamp at the start of the
+/// value, as `parse_date` does. Values with a date only are at midnight.
+#[derive(Debug)]
+pub struct ParseTimestamp {
+    signature: Signature,
+}
+
+impl ParseTimestamp {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::variadic(vec![DataType::Utf8], Volatility::Immutable),
+        }
+    }
+}
+
+impl ScalarUDFImpl for ParseTimestamp {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "parse_timestamp"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Timestamp(TimeUnit::Nanosecond, None))
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        invoke_arrays(args, |arrays| {
+            let timestamps = parse_rows(arrays, TIMESTAMP_FORMATS, |value, format| {
+                parse_timestamp(value, format)
+                    .or_else(|| parse_date(value, format).and_then(|d| d.and_hms_opt(0, 0, 0)))
+            })
+            .map(|ts| ts.and_then(|ts| ts.and_utc().timestamp_nanos_opt()));
+            Ok(Arc::new(TimestampNanosecondArray::from_iter(timestamps)) as ArrayRef)
+        })
+    }
+}
+
+/// Parse every value with the formats of its row, or the default ones if none is given.
+fn parse_rows<'a, T>(
+    arrays: &'a [ArrayRef],
+    defaults: &'a [&'a str],
+    parse: impl Fn(&str, &str) -> Option<T> + 'a,
+) -> impl Iterator<Item = Option<T>> + 'a {
+    let values = arrays[0].as_string::<i32>();
+    let formats: Vec<&StringArray> = arrays[1..].iter().map(|a| a.as_string()).collect();
+    (0..values.len()).map(move |i| {
+        if values.is_null(i) {
+            return None;
+        }
+        let value = values.value(i).trim();
+        if formats.is_empty() {
+            return defaults.iter().find_map(|format| parse(value, format));
+        }
+        formats
+            .iter()
+            .filter(|formats| formats.is_valid(i))
+            .find_map(|formats| parse(value, formats.value(i)))
+    })
+}
+
+fn parse_date(value: &str, format: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_and_remainder(value, format)
+        .ok()
+        .map(|(date, _)| date)
+}
+
+fn parse_timestamp(value: &str, format: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_and_remainder(value, format)
+        .ok()
+        .map(|(ts, _)| ts)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{AsArray, RecordBatch};
+    use arrow::util::display::array_value_to_string;
+
+    use super::super::test_utils::{assets_ctx, query};
+
+    fn values(batch: &RecordBatch, column: usize) -> Vec<String> {
+        (0..batch.num_rows())
+            .map(|i| array_value_to_string(batch.column(column), i).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn parse_date_should_ignore_the_age_after_the_date_of_birth() {
+        let ctx = assets_ctx().await;
+        let batch = query(
+            &ctx,
+            "SELECT parse_date(dob, '%b %d, %Y') FROM juve WHERE name = 'Wojciech Szczesny'",
+        )
+        .await;
+        assert_eq!(values(&batch, 0), vec!["1990-04-18"]);
+    }
+
+    #[tokio::test]
+    async fn parse_date_should_parse_all_the_dates_of_birth_with_default_formats() {
+        let ctx = assets_ctx().await;
+        let batch = query(
+            &ctx,
+            "SELECT count(*), count(parse_date(dob)), min(parse_date(dob)) FROM juve",
+        )
+        .await;
+        let total = batch
+            .column(0)
+            .as_primitive::<arrow::datatypes::Int64Type>();
+        let parsed = batch
+            .column(1)
+            .as_primitive::<arrow::datatypes::Int64Type>();
+        assert_eq!(total.value(0), parsed.value(0));
+        assert!(values(&batch, 2)[0].starts_with("19"));
+    }
+
+    #[tokio::test]
+    async fn parse_date_should_try_the_formats_in_order_and_return_null_otherwise() {
+        let ctx = assets_ctx().await;
+        let batch = query(
+            &ctx,
+            "SELECT parse_date('18/04/1990', '%Y-%m-%d', '%d/%m/%Y'), parse_date('nope', '%Y-%m-%d')",
+        )
+        .await;
+        assert_eq!(values(&batch, 0), vec!["1990-04-18"]);
+        assert!(batch.column(1).is_null(0));
+    }
+
+    #[tokio::test]
+    async fn parse_timestamp_should_parse_the_naive_timestamps_of_users() {
+        let ctx = assets_ctx().await;
+        let batch = query(
+            &ctx,
+            "SELECT parse_timestamp(created_at) FROM users WHERE email = 'jeanette.jbki6fsg@example.org'",
+        )
+        .await;
+        assert_eq!(values(&batch, 0), vec!["2020-03-01T04:02:07.535890"]);
+
+        let batch = query(
+            &ctx,
+            "SELECT count(*) FROM users WHERE parse_timestamp(created_at) IS NULL",
+        )
+        .await;
+        assert_eq!(values(&batch, 0), vec!["0"]);
+    }
+
+    #[tokio::test]
+    async fn parse_timestamp_should_put_dates_at_midnight() {
+        let ctx = assets_ctx().await;
+        let batch = query(
+            &ctx,
+            "SELECT parse_timestamp(dob, '%b %d, %Y') FROM juve LIMIT 1",
+        )
+        .await;
+        assert_eq!(values(&batch, 0), vec!["1990-04-18T00:00:00"]);
+    }
+}
@@ -0,0 +1,198 @@
+use std::{any::Any, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, StringArray},
+    datatypes::DataType,
+};
+use datafusion::{
+    error::{DataFusionError, Result},
+    logical_expr::{ColumnarValue, ScalarUDFImpl, Signature, Volatility},
+};
+use serde_json::Value;
+
+use super::invoke_arrays;
+
+/// `json_get(json, path)`: the value at a path like `$.user.emails[0]` (or
+/// `user.emails.0`) of a JSON text. Strings are returned unquoted, the other values
+/// as JSON. Missing values and invalid JSON are null.
+#[derive(Debug)]
+pub struct JsonGet {
+    signature: Signature,
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl JsonGet {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::exact(
+                vec![DataType::Utf8, DataType::Utf8],
+                Volatility::Immutable,
+            ),
+        }
+    }
+}
+
+impl ScalarUDFImpl for JsonGet {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "json_get"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Utf8)
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        invoke_arrays(args, |arrays| {
+            let docs = arrays[0].as_string::<i32>();
+            let paths = arrays[1].as_string::<i32>();
+            let mut parsed: Option<(&str, Vec<Segment>)> = None;
+            let mut values = Vec::with_capacity(docs.len());
+            for (doc, path) in docs.iter().zip(paths.iter()) {
+                let (Some(doc), Some(path)) = (doc, path) else {
+                    values.push(None);
+                    continue;
+                };
+                // the path is a literal most of the time, parse it once
+                if parsed.as_ref().is_none_or(|(p, _)| *p != path) {
+                    parsed = Some((path, parse_path(path)?));
+                }
+                let segments = &parsed.as_ref().expect("path is parsed").1;
+                values.push(get(doc, segments));
+            }
+            Ok(Arc::new(StringArray::from(values)) as ArrayRef)
+        })
+    }
+}
+
+fn get(doc: &str, segments: &[Segment]) -> Option<String> {
+    let doc: Value = serde_json::from_str(doc).ok()?;
+    let value = segments
+        .iter()
+        .try_fold(&doc, |value, segment| match segment {
+            Segment::Key(key) => value.get(key),
+            Segment::Index(idx) => value.get(idx),
+        })?;
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        v => Some(v.to_string()),
+    }
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment>> {
+    let invalid = || DataFusionError::Execution(format!("Invalid JSON path {}", path));
+    let rest = path.strip_prefix('$').unwrap_or(path);
+    let mut segments = vec![];
+    for part in rest.split('.').filter(|p| !p.is_empty()) {
+        let (key, mut indexes) = match part.find('[') {
+            Some(pos) => (&part[..pos], &part[pos..]),
+            None => (part, ""),
+        };
+        if !key.is_empty() {
+            match key.parse::<usize>() {
+                Ok(idx) => segments.push(Segment::Index(idx)),
+                Err(_) => segments.push(Segment::Key(key.to_string())),
+            }
+        }
+        while !indexes.is_empty() {
+            let end = indexes.find(']').ok_or_else(invalid)?;
+            let idx = indexes[1..end].trim().parse().map_err(|_| invalid())?;
+            segments.push(Segment::Index(idx));
+            indexes = &indexes[end + 1..];
+            if !indexes.is_empty() && !indexes.starts_with('[') {
+                return Err(invalid());
+            }
+        }
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc};
+
+    use arrow::{
+        array::{AsArray, RecordBatch, StringArray},
+        datatypes::{DataType, Field, Schema},
+    };
+    use datafusion::datasource::MemTable;
+
+    use super::super::test_utils::{assets_ctx, query};
+    use super::{parse_path, Segment};
+
+    /// The users as JSON texts, one row per line of the file.
+    async fn lines_ctx() -> datafusion::prelude::SessionContext {
+        let ctx = assets_ctx().await;
+        let content = fs::read_to_string("assets/users.ndjson").unwrap();
+        let schema = Arc::new(Schema::new(vec![Field::new("doc", DataType::Utf8, false)]));
+        let lines = StringArray::from(content.lines().collect::<Vec<_>>());
+        let batch = RecordBatch::try_new(schema.clone(), vec![Arc::new(lines)]).unwrap();
+        let table = MemTable::try_new(schema, vec![vec![batch]]).unwrap();
+        ctx.register_table("lines", Arc::new(table)).unwrap();
+        ctx
+    }
+
+    #[test]
+    fn parse_path_should_support_both_notations() {
+        let expected = vec![
+            Segment::Key("a".to_string()),
+            Segment::Index(0),
+            Segment::Key("b".to_string()),
+        ];
+        assert_eq!(parse_path("$.a[0].b").unwrap(), expected);
+        assert_eq!(parse_path("a.0.b").unwrap(), expected);
+        assert!(parse_path("$.a[x]").is_err());
+    }
+
+    #[tokio::test]
+    async fn json_get_should_extract_the_fields_of_users() {
+        let ctx = lines_ctx().await;
+        let batch = query(
+            &ctx,
+            "SELECT json_get(doc, '$.email'), json_get(doc, '$.recent_watched[0]'), json_get(doc, 'finished'),
+                    json_get(doc, '$.missing'), json_get('not json', '$.email')
+             FROM lines LIMIT 1",
+        )
+        .await;
+        assert_eq!(
+            batch.column(0).as_string::<i32>().value(0),
+            "jeanette.jbki6fsg@example.org"
+        );
+        assert_eq!(batch.column(1).as_string::<i32>().value(0), "158594");
+        assert!(batch
+            .column(2)
+            .as_string::<i32>()
+            .value(0)
+            .starts_with("[499213,"));
+        assert!(batch.column(3).is_null(0));
+        assert!(batch.column(4).is_null(0));
+    }
+
+    #[tokio::test]
+    async fn json_get_should_match_the_parsed_dataset() {
+        let ctx = lines_ctx().await;
+        let batch = query(
+            &ctx,
+            "SELECT count(*) FROM lines JOIN users ON json_get(doc, 'email') = users.email
+             WHERE json_get(doc, 'created_at') = users.created_at",
+        )
+        .await;
+        let count = batch
+            .column(0)
+            .as_primitive::<arrow::datatypes::Int64Type>();
+        assert_eq!(count.value(0), 100);
+    }
+}
@@ -0,0 +1,222 @@
+use std::{any::Any, collections::HashSet, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, BooleanArray, Float64Array, Int64Array, ListArray},
+    datatypes::DataType,
+    row::{Row, RowConverter, Rows, SortField},
+};
+use datafusion::{
+    common::plan_err,
+    error::Result,
+    logical_expr::{ColumnarValue, ScalarUDFImpl, Signature, Volatility},
+};
+
+use super::invoke_arrays;
+
+/// The set operations missing from DataFusion, which already has `array_union`,
+/// `array_intersect` and `array_except` returning lists. These ones return a
+/// measure of the two sets of distinct non null elements.
+#[derive(Debug, Clone, Copy)]
+enum SetMeasure {
+    Overlap,
+    Jaccard,
+    IsSubset,
+}
+
+macro_rules! set_udf {
+    ($name:ident, $fn_name:literal, $measure:expr, $doc:literal) => {
+        #[doc = $doc]
+        #[derive(Debug)]
+        pub struct $name {
+            signature: Signature,
+        }
+
+        impl $name {
+            pub fn new() -> Self {
+                Self {
+                    signature: Signature::any(2, Volatility::Immutable),
+                }
+            }
+        }
+
+        impl ScalarUDFImpl for $name {
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+
+            fn name(&self) -> &str {
+                $fn_name
+            }
+
+            fn signature(&self) -> &Signature {
+                &self.signature
+            }
+
+            fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+                return_type($fn_name, $measure, arg_types)
+            }
+
+            fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+                invoke_arrays(args, |arrays| measure($measure, &arrays[0], &arrays[1]))
+            }
+        }
+    };
+}
+
+set_udf!(
+    ListOverlap,
+    "list_overlap",
+    SetMeasure::Overlap,
+    "`list_overlap(a, b)`: the number of distinct elements in both lists."
+);
+set_udf!(
+    ListJaccard,
+    "list_jaccard",
+    SetMeasure::Jaccard,
+    "`list_jaccard(a, b)`: the size of the intersection over the size of the union, null if both lists are empty."
+);
+set_udf!(
+    ListIsSubset,
+    "list_is_subset",
+    SetMeasure::IsSubset,
+    "`list_is_subset(a, b)`: whether all the elements of `a` are in `b`."
+);
+
+fn return_type(name: &str, measure: SetMeasure, arg_types: &[DataType]) -> Result<DataType> {
+    match arg_types {
+        [DataType::List(a), DataType::List(b)] if a.data_type() == b.data_type() => {}
+        [a, b] => {
+            return plan_err!(
+                "{} expects two lists of the same type, got {} and {}",
+                name,
+                a,
+                b
+            )
+        }
+        _ => return plan_err!("{} expects two lists", name),
+    }
+    Ok(match measure {
+        SetMeasure::Overlap => DataType::Int64,
+        SetMeasure::Jaccard => DataType::Float64,
+        SetMeasure::IsSubset => DataType::Boolean,
+    })
+}
+
+fn measure(measure: SetMeasure, left: &ArrayRef, right: &ArrayRef) -> Result<ArrayRef> {
+    let left = left.as_list::<i32>();
+    let right = right.as_list::<i32>();
+    // compare the elements of any type by their row format
+    let converter = RowConverter::new(vec![SortField::new(left.value_type())])?;
+    let left_rows = converter.convert_columns(&[left.values().clone()])?;
+    let right_rows = converter.convert_columns(&[right.values().clone()])?;
+
+    let sets = (0..left.len()).map(|i| {
+        if left.is_null(i) || right.is_null(i) {
+            return None;
+        }
+        let a = distinct_rows(left, &left_rows, i);
+        let b = distinct_rows(right, &right_rows, i);
+        Some((a.intersection(&b).count(), a.len(), b.len()))
+    });
+
+    let array: ArrayRef = match measure {
+        SetMeasure::Overlap => Arc::new(Int64Array::from_iter(
+            sets.map(|set| set.map(|(common, _, _)| common as i64)),
+        )),
+        SetMeasure::Jaccard => Arc::new(Float64Array::from_iter(sets.map(|set| {
+            let (common, a, b) = set?;
+            let union = a + b - common;
+            (union > 0).then(|| common as f64 / union as f64)
+        }))),
+        SetMeasure::IsSubset => Arc::new(BooleanArray::from_iter(
+            sets.map(|set| set.map(|(common, a, _)| common == a)),
+        )),
+    };
+    Ok(array)
+}
+
+/// The distinct non null elements of the list at `idx`.
+fn distinct_rows<'a>(list: &ListArray, rows: &'a Rows, idx: usize) -> HashSet<Row<'a>> {
+    let offsets = list.value_offsets();
+    let values = list.values();
+    (offsets[idx] as usize..offsets[idx + 1] as usize)
+        .filter(|&j| values.is_valid(j))
+        .map(|j| rows.row(j))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::AsArray,
+        datatypes::{Float64Type, Int64Type},
+    };
+
+    use super::super::test_utils::{assets_ctx, query};
+
+    #[tokio::test]
+    async fn list_overlap_should_count_the_common_elements() {
+        let ctx = assets_ctx().await;
+        let batch = query(
+            &ctx,
+            "SELECT list_overlap(finished, finished), cardinality(array_distinct(finished)),
+                    list_overlap(recent_watched, finished)
+             FROM users",
+        )
+        .await;
+        let overlap = batch.column(0).as_primitive::<Int64Type>();
+        let distinct = batch
+            .column(1)
+            .as_primitive::<arrow::datatypes::UInt64Type>();
+        for i in 0..batch.num_rows() {
+            assert_eq!(overlap.value(i) as u64, distinct.value(i));
+        }
+        // the ids of the lists of a user are in disjoint ranges
+        let disjoint = batch.column(2).as_primitive::<Int64Type>();
+        assert!(disjoint.iter().all(|v| v == Some(0)));
+    }
+
+    #[tokio::test]
+    async fn list_jaccard_should_measure_the_similarity() {
+        let ctx = assets_ctx().await;
+        let batch = query(
+            &ctx,
+            "SELECT list_jaccard(recent_watched, recent_watched), list_jaccard(finished, started_but_not_finished),
+                    list_jaccard(make_array(1, 2, 3), make_array(2, 3, 4)), list_jaccard(make_array(1), make_array(2))
+             FROM users WHERE email = 'jeanette.jbki6fsg@example.org'",
+        )
+        .await;
+        assert_eq!(batch.column(0).as_primitive::<Float64Type>().value(0), 1.0);
+        assert_eq!(batch.column(1).as_primitive::<Float64Type>().value(0), 0.0);
+        assert_eq!(batch.column(2).as_primitive::<Float64Type>().value(0), 0.5);
+        assert_eq!(batch.column(3).as_primitive::<Float64Type>().value(0), 0.0);
+    }
+
+    #[tokio::test]
+    async fn list_is_subset_should_ignore_duplicates_and_nulls() {
+        let ctx = assets_ctx().await;
+        let batch = query(
+            &ctx,
+            "SELECT list_is_subset(make_array(1, 1, NULL), make_array(1, 2)), list_is_subset(make_array(3), make_array(1, 2)),
+                    list_is_subset(array_slice(finished, 1, 3), finished)
+             FROM users LIMIT 1",
+        )
+        .await;
+        let subset = batch.column(0).as_boolean();
+        assert!(subset.value(0));
+        assert!(!batch.column(1).as_boolean().value(0));
+        assert!(batch.column(2).as_boolean().value(0));
+    }
+
+    #[tokio::test]
+    async fn list_functions_should_reject_other_types() {
+        let ctx = assets_ctx().await;
+        let err = ctx
+            .sql("SELECT list_overlap(finished, email) FROM users")
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("list_overlap expects two lists of the same type"));
+    }
+}
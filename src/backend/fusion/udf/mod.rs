@@ -0,0 +1,77 @@
+//! Scalar functions for the messy fields of real-world datasets, registered in every
+//! session of the backend.
+
+mod date;
+mod json;
+mod list;
+mod random;
+mod text;
+
+use arrow::array::ArrayRef;
+use datafusion::{
+    common::ScalarValue,
+    error::Result,
+    logical_expr::{ColumnarValue, ScalarUDF},
+    prelude::SessionContext,
+};
+
+pub fn register_all(ctx: &SessionContext) {
+    ctx.register_udf(ScalarUDF::from(date::ParseDate::new()));
+    ctx.register_udf(ScalarUDF::from(date::ParseTimestamp::new()));
+    ctx.register_udf(ScalarUDF::from(text::RegexExtract::new()));
+    ctx.register_udf(ScalarUDF::from(text::EmailDomain::new()));
+    ctx.register_udf(ScalarUDF::from(list::ListOverlap::new()));
+    ctx.register_udf(ScalarUDF::from(list::ListJaccard::new()));
+    ctx.register_udf(ScalarUDF::from(list::ListIsSubset::new()));
+    ctx.register_udf(ScalarUDF::from(json::JsonGet::new()));
+    ctx.register_udf(ScalarUDF::from(random::SeededRandom::new()));
+}
+
+/// Run `f` on the arguments as arrays of the same length, the result is a scalar
+/// when all the arguments are.
+fn invoke_arrays(
+    args: &[ColumnarValue],
+    f: impl Fn(&[ArrayRef]) -> Result<ArrayRef>,
+) -> Result<ColumnarValue> {
+    let scalars = args
+        .iter()
+        .all(|arg| matches!(arg, ColumnarValue::Scalar(_)));
+    let arrays = ColumnarValue::values_to_arrays(args)?;
+    let result = f(&arrays)?;
+    if scalars {
+        return Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(
+            &result, 0,
+        )?));
+    }
+    Ok(ColumnarValue::Array(result))
+}
+
+#[cfg(test)]
+pub(super) mod test_utils {
+    use arrow::array::RecordBatch;
+    use datafusion::prelude::{CsvReadOptions, NdJsonReadOptions, SessionContext};
+
+    /// A session with the functions and the bundled assets, as `users` and `juve`.
+    pub async fn assets_ctx() -> SessionContext {
+        let ctx = SessionContext::new();
+        super::register_all(&ctx);
+        ctx.register_json(
+            "users",
+            "assets/users.ndjson",
+            NdJsonReadOptions::default().file_extension(".ndjson"),
+        )
+        .await
+        .unwrap();
+        ctx.register_csv("juve", "assets/juventus.csv", CsvReadOptions::default())
+            .await
+            .unwrap();
+        ctx
+    }
+
+    pub async fn query(ctx: &SessionContext, sql: &str) -> RecordBatch {
+        let df = ctx.sql(sql).await.unwrap();
+        let schema = df.schema().inner().clone();
+        let batches = df.collect().await.unwrap();
+        arrow::compute::concat_batches(&schema, &batches).unwrap()
+    }
+}
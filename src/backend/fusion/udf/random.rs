@@ -0,0 +1,130 @@
+use std::{any::Any, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, Float64Array},
+    datatypes::{DataType, Int64Type},
+    row::{RowConverter, SortField},
+};
+use datafusion::{
+    common::plan_err,
+    error::Result,
+    logical_expr::{ColumnarValue, ScalarUDFImpl, Signature, Volatility},
+};
+
+use super::invoke_arrays;
+
+/// `seeded_random(seed, value, ...)`: a number in [0, 1) drawn from the seed and the
+/// values, the same for the same values whatever the order of the rows. Sorting or
+/// filtering on it gives reproducible samples, where equal rows are all kept or not.
+#[derive(Debug)]
+pub struct SeededRandom {
+    signature: Signature,
+}
+
+impl SeededRandom {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::variadic_any(Volatility::Immutable),
+        }
+    }
+}
+
+impl ScalarUDFImpl for SeededRandom {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "seeded_random"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        match arg_types {
+            [seed, _, ..] if seed.is_integer() => Ok(DataType::Float64),
+            _ => plan_err!("seeded_random expects an integer seed and at least one value"),
+        }
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        invoke_arrays(args, |arrays| {
+            let seeds = arrow::compute::cast(&arrays[0], &DataType::Int64)?;
+            let seeds = seeds.as_primitive::<Int64Type>();
+            // hash the values of any type by their row format
+            let fields = arrays[1..]
+                .iter()
+                .map(|a| SortField::new(a.data_type().clone()))
+                .collect();
+            let rows = RowConverter::new(fields)?.convert_columns(&arrays[1..])?;
+            let values = (0..rows.num_rows()).map(|i| {
+                let seed = if seeds.is_null(i) { 0 } else { seeds.value(i) };
+                unit(hash(seed as u64, rows.row(i).as_ref()))
+            });
+            Ok(Arc::new(Float64Array::from_iter_values(values)) as ArrayRef)
+        })
+    }
+}
+
+/// FNV-1a, which is the same on all platforms and versions, then mixed as in
+/// splitmix64 to spread the close hashes.
+fn hash(seed: u64, bytes: &[u8]) -> u64 {
+    let mut h = 0xcbf29ce484222325 ^ seed;
+    for b in bytes {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h ^= h >> 30;
+    h = h.wrapping_mul(0xbf58476d1ce4e5b9);
+    h ^= h >> 27;
+    h = h.wrapping_mul(0x94d049bb133111eb);
+    h ^ (h >> 31)
+}
+
+/// The 53 high bits as a float in [0, 1).
+fn unit(h: u64) -> f64 {
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{array::AsArray, datatypes::Float64Type};
+
+    use super::super::test_utils::{assets_ctx, query};
+
+    #[tokio::test]
+    async fn seeded_random_should_depend_on_the_seed_and_the_values_only() {
+        let ctx = assets_ctx().await;
+        let sql = "SELECT seeded_random(42, email, finished), seeded_random(7, email, finished)
+                   FROM users ORDER BY email";
+        let batch = query(&ctx, sql).await;
+        let other = query(&ctx, &sql.replace("ORDER BY email", "ORDER BY email DESC")).await;
+        let first = batch.column(0).as_primitive::<Float64Type>();
+        let reversed = other.column(0).as_primitive::<Float64Type>();
+        for i in 0..batch.num_rows() {
+            assert_eq!(first.value(i), reversed.value(batch.num_rows() - 1 - i));
+            assert!((0.0..1.0).contains(&first.value(i)));
+        }
+        assert_ne!(batch.column(0), batch.column(1));
+    }
+
+    #[tokio::test]
+    async fn seeded_random_should_be_uniform() {
+        let ctx = assets_ctx().await;
+        let batch = query(
+            &ctx,
+            "SELECT avg(seeded_random(1, email)), sum(CASE WHEN seeded_random(1, email) < 0.5 THEN 1 ELSE 0 END)
+             FROM users",
+        )
+        .await;
+        let mean = batch.column(0).as_primitive::<Float64Type>().value(0);
+        assert!((0.4..0.6).contains(&mean));
+        let half = batch
+            .column(1)
+            .as_primitive::<arrow::datatypes::Int64Type>()
+            .value(0);
+        assert!((35..65).contains(&half));
+    }
+}
@@ -0,0 +1,215 @@
+use std::{any::Any, collections::HashMap, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, StringArray},
+    datatypes::{DataType, Int64Type},
+};
+use datafusion::{
+    error::{DataFusionError, Result},
+    logical_expr::{ColumnarValue, ScalarUDFImpl, Signature, TypeSignature, Volatility},
+};
+use regex::Regex;
+
+use super::invoke_arrays;
+
+/// `regex_extract(value, pattern [, group])`: the text matched by a group of the
+/// pattern, by default the first group if the pattern has one, else the whole match.
+/// Values which don't match are null.
+#[derive(Debug)]
+pub struct RegexExtract {
+    signature: Signature,
+}
+
+impl RegexExtract {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::one_of(
+                vec![
+                    TypeSignature::Exact(vec![DataType::Utf8, DataType::Utf8]),
+                    TypeSignature::Exact(vec![DataType::Utf8, DataType::Utf8, DataType::Int64]),
+                ],
+                Volatility::Immutable,
+            ),
+        }
+    }
+}
+
+impl ScalarUDFImpl for RegexExtract {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "regex_extract"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Utf8)
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        invoke_arrays(args, |arrays| {
+            let values = arrays[0].as_string::<i32>();
+            let patterns = arrays[1].as_string::<i32>();
+            let groups = arrays.get(2).map(|a| a.as_primitive::<Int64Type>());
+            // the pattern is a literal most of the time, compile it once per batch
+            let mut compiled: HashMap<&str, Regex> = HashMap::new();
+            let mut extracted = Vec::with_capacity(values.len());
+            for i in 0..values.len() {
+                if values.is_null(i) || patterns.is_null(i) {
+                    extracted.push(None);
+                    continue;
+                }
+                let pattern = patterns.value(i);
+                if !compiled.contains_key(pattern) {
+                    let regex = Regex::new(pattern).map_err(|e| {
+                        DataFusionError::Execution(format!("Invalid pattern {}: {}", pattern, e))
+                    })?;
+                    compiled.insert(pattern, regex);
+                }
+                let regex = &compiled[pattern];
+                let group = match groups {
+                    Some(groups) if groups.is_null(i) => {
+                        extracted.push(None);
+                        continue;
+                    }
+                    Some(groups) => groups.value(i) as usize,
+                    None => usize::from(regex.captures_len() > 1),
+                };
+                let matched = regex
+                    .captures(values.value(i))
+                    .and_then(|caps| caps.get(group))
+                    .map(|m| m.as_str().to_string());
+                extracted.push(matched);
+            }
+            Ok(Arc::new(StringArray::from(extracted)) as ArrayRef)
+        })
+    }
+}
+
+/// `email_domain(email)`: the lowercase domain of an email address, null if the
+/// value is not one.
+#[derive(Debug)]
+pub struct EmailDomain {
+    signature: Signature,
+}
+
+impl EmailDomain {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::exact(vec![DataType::Utf8], Volatility::Immutable),
+        }
+    }
+}
+
+impl ScalarUDFImpl for EmailDomain {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "email_domain"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Utf8)
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        invoke_arrays(args, |arrays| {
+            let domains = arrays[0].as_string::<i32>().iter().map(|email| {
+                let (local, domain) = email?.trim().rsplit_once('@')?;
+                (!local.is_empty() && domain.contains('.') && !domain.ends_with('.'))
+                    .then(|| domain.to_lowercase())
+            });
+            Ok(Arc::new(StringArray::from_iter(domains)) as ArrayRef)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{array::AsArray, datatypes::Int64Type};
+
+    use super::super::test_utils::{assets_ctx, query};
+
+    #[tokio::test]
+    async fn regex_extract_should_return_the_first_group() {
+        let ctx = assets_ctx().await;
+        let batch = query(
+            &ctx,
+            r"SELECT regex_extract(dob, '\((\d+)\)'), regex_extract(dob, '\d{4}'), regex_extract(dob, '(\w+) (\d+)', 2)
+              FROM juve WHERE name = 'Wojciech Szczesny'",
+        )
+        .await;
+        assert_eq!(batch.column(0).as_string::<i32>().value(0), "29");
+        assert_eq!(batch.column(1).as_string::<i32>().value(0), "1990");
+        assert_eq!(batch.column(2).as_string::<i32>().value(0), "18");
+    }
+
+    #[tokio::test]
+    async fn regex_extract_should_return_null_without_match() {
+        let ctx = assets_ctx().await;
+        let batch = query(
+            &ctx,
+            r"SELECT count(*) FROM juve WHERE regex_extract(position, '^(\d+)$') IS NULL",
+        )
+        .await;
+        let total = query(&ctx, "SELECT count(*) FROM juve").await;
+        assert_eq!(
+            batch.column(0).as_primitive::<Int64Type>().value(0),
+            total.column(0).as_primitive::<Int64Type>().value(0)
+        );
+    }
+
+    #[tokio::test]
+    async fn regex_extract_should_fail_on_invalid_pattern() {
+        let ctx = assets_ctx().await;
+        let df = ctx
+            .sql("SELECT regex_extract(name, '(') FROM juve")
+            .await
+            .unwrap();
+        let err = df.collect().await.unwrap_err();
+        assert!(err.to_string().contains("Invalid pattern ("));
+    }
+
+    #[tokio::test]
+    async fn email_domain_should_extract_the_domains_of_users() {
+        let ctx = assets_ctx().await;
+        let batch = query(
+            &ctx,
+            "SELECT email_domain(email), count(*) FROM users GROUP BY 1 ORDER BY 2 DESC",
+        )
+        .await;
+        let domains = batch.column(0).as_string::<i32>();
+        let counts = batch.column(1).as_primitive::<Int64Type>();
+        let found: Vec<_> = (0..batch.num_rows())
+            .map(|i| (domains.value(i), counts.value(i)))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("example.com", 40),
+                ("example.net", 32),
+                ("example.org", 28)
+            ]
+        );
+
+        let batch = query(
+            &ctx,
+            "SELECT email_domain('Jane@Example.ORG'), email_domain('jane'), email_domain('@example.org')",
+        )
+        .await;
+        assert_eq!(batch.column(0).as_string::<i32>().value(0), "example.org");
+        assert!(batch.column(1).is_null(0));
+        assert!(batch.column(2).is_null(0));
+    }
+}
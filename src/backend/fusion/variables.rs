@@ -0,0 +1,384 @@
+use std::{collections::BTreeMap, ops::ControlFlow, sync::Arc};
+
+use anyhow::{anyhow, bail};
+use arrow::{
+    array::{RecordBatch, StringArray},
+    datatypes::{DataType, Field, Schema},
+    util::display::array_value_to_string,
+};
+use datafusion::{
+    common::ScalarValue,
+    logical_expr::LogicalPlan,
+    prelude::{DataFrame, SessionContext},
+    sql::{
+        parser::Statement as DFStatement,
+        sqlparser::ast::{
+            visit_expressions_mut, DataType as SQLDataType, ExactNumberInfo, Expr as SQLExpr,
+            Ident, Statement as SQLStatement, TimezoneInfo, Value,
+        },
+    },
+};
+
+/// The variables of the session defined with `let`, by name.
+pub type Variables = BTreeMap<String, ScalarValue>;
+
+/// Plan a query, its placeholders `$name` or `:name` are bound to the parameters
+/// given with the command or else to the variables of the session.
+///
+/// The query is never rewritten as a string: it is planned as a prepared statement
+/// whose parameters are declared with the types of the variables, then the values
+/// are bound to the plan and checked against the types of the placeholders.
+pub async fn plan(
+    ctx: &SessionContext,
+    sql: &str,
+    params: &[(String, String)],
+    variables: &Variables,
+) -> anyhow::Result<DataFrame> {
+    let state = ctx.state();
+    let dialect = state.config().options().sql_parser.dialect.clone();
+    let names = match state.sql_to_statement(sql, &dialect)? {
+        // only the queries can be prepared, the placeholders of the other statements are errors
+        DFStatement::Statement(mut statement) if matches!(*statement, SQLStatement::Query(_)) => {
+            let names = number_placeholders(&mut statement, params, variables);
+            (!names.is_empty()).then_some((statement, names))
+        }
+        _ => None,
+    };
+    let Some((statement, names)) = names else {
+        if let Some((name, _)) = params.first() {
+            bail!("The query has no parameter ${}", name);
+        }
+        return Ok(ctx.sql(sql).await?);
+    };
+    if let Some((name, _)) = params.iter().find(|(name, _)| !names.contains(name)) {
+        bail!("The query has no parameter ${}", name);
+    }
+    let is_bound =
+        |name: &String| params.iter().any(|(n, _)| n == name) || variables.contains_key(name);
+    if let Some(name) = names.iter().find(|name| !is_bound(name)) {
+        bail!(
+            "Unknown variable ${}, define it with `let {} = <value>`",
+            name,
+            name
+        );
+    }
+
+    let data_types = names
+        .iter()
+        .map_while(|name| declared_type(name, params, variables))
+        .collect();
+    let prepare = SQLStatement::Prepare {
+        name: Ident::new("taotie"),
+        data_types,
+        statement,
+    };
+    let plan = state
+        .statement_to_plan(DFStatement::Statement(Box::new(prepare)))
+        .await?;
+    let LogicalPlan::Prepare(prepare) = plan else {
+        bail!("Expect a prepared statement, got {}", plan.display());
+    };
+    let df = DataFrame::new(state, prepare.input.as_ref().clone());
+
+    let types = df.logical_plan().get_parameter_types()?;
+    let values = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let data_type = types.get(&format!("${}", i + 1)).cloned().flatten();
+            bind(name, data_type, params, variables)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(df.with_param_values(values)?)
+}
+
+/// Number the named placeholders as `$1`, `$2`... and return their names, the ones
+/// with a declared type come first since the types of a prepared statement are positional.
+fn number_placeholders(
+    statement: &mut SQLStatement,
+    params: &[(String, String)],
+    variables: &Variables,
+) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    let _ = visit_expressions_mut(statement, |expr| {
+        if let SQLExpr::Value(Value::Placeholder(id)) = expr {
+            let name = id[1..].to_string();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    names.sort_by_key(|name| declared_type(name, params, variables).is_none());
+
+    let _ = visit_expressions_mut(statement, |expr| {
+        if let SQLExpr::Value(Value::Placeholder(id)) = expr {
+            let idx = names.iter().position(|name| name == &id[1..]).unwrap_or(0);
+            *id = format!("${}", idx + 1);
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    names
+}
+
+/// The SQL type of a placeholder bound to a variable, the parameters of the command
+/// are typed by the query instead.
+fn declared_type(
+    name: &str,
+    params: &[(String, String)],
+    variables: &Variables,
+) -> Option<SQLDataType> {
+    if params.iter().any(|(n, _)| n == name) {
+        return None;
+    }
+    let sql_type = match variables.get(name)?.data_type() {
+        DataType::Boolean => SQLDataType::Boolean,
+        DataType::Int8 => SQLDataType::TinyInt(None),
+        DataType::Int16 => SQLDataType::SmallInt(None),
+        DataType::Int32 => SQLDataType::Int(None),
+        DataType::Int64 => SQLDataType::BigInt(None),
+        DataType::UInt8 => SQLDataType::UnsignedTinyInt(None),
+        DataType::UInt16 => SQLDataType::UnsignedSmallInt(None),
+        DataType::UInt32 => SQLDataType::UnsignedInt(None),
+        DataType::UInt64 => SQLDataType::UnsignedBigInt(None),
+        DataType::Float32 => SQLDataType::Real,
+        DataType::Float64 => SQLDataType::Double,
+        DataType::Utf8 | DataType::LargeUtf8 => SQLDataType::Varchar(None),
+        DataType::Date32 => SQLDataType::Date,
+        DataType::Timestamp(_, None) => SQLDataType::Timestamp(None, TimezoneInfo::None),
+        DataType::Timestamp(_, Some(_)) => SQLDataType::Timestamp(None, TimezoneInfo::Tz),
+        DataType::Decimal128(precision, scale) => SQLDataType::Decimal(
+            ExactNumberInfo::PrecisionAndScale(precision as u64, scale as u64),
+        ),
+        DataType::Interval(_) => SQLDataType::Interval,
+        _ => return None,
+    };
+    Some(sql_type)
+}
+
+/// The value of a placeholder whose type is known once the query is planned.
+fn bind(
+    name: &str,
+    data_type: Option<DataType>,
+    params: &[(String, String)],
+    variables: &Variables,
+) -> anyhow::Result<ScalarValue> {
+    if let Some((_, value)) = params.iter().find(|(n, _)| n == name) {
+        return match data_type {
+            Some(data_type) => ScalarValue::try_from_string(value.clone(), &data_type)
+                .map_err(|e| anyhow!("Invalid value {} of parameter ${}: {}", value, name, e)),
+            None => Ok(ScalarValue::Utf8(Some(value.clone()))),
+        };
+    }
+
+    let value = variables
+        .get(name)
+        .ok_or_else(|| anyhow!("Unknown variable ${}", name))?;
+    match data_type {
+        Some(data_type) if data_type != value.data_type() => {
+            value.cast_to(&data_type).map_err(|_| {
+                anyhow!(
+                    "Variable ${} is {}, it can't be used as {}",
+                    name,
+                    value.data_type(),
+                    data_type
+                )
+            })
+        }
+        _ => Ok(value.clone()),
+    }
+}
+
+/// The dataset named by `name`, which may be a string variable like `$table`.
+pub fn resolve_name<'a>(name: &'a str, variables: &'a Variables) -> anyhow::Result<&'a str> {
+    let Some(var) = name.strip_prefix('$').or_else(|| name.strip_prefix(':')) else {
+        return Ok(name);
+    };
+    match variables.get(var) {
+        Some(ScalarValue::Utf8(Some(name))) => Ok(name),
+        Some(value) => bail!(
+            "Variable {} is {}, expect the name of a dataset",
+            name,
+            value.data_type()
+        ),
+        None => bail!(
+            "Unknown variable {}, define it with `let {} = <value>`",
+            name,
+            var
+        ),
+    }
+}
+
+pub fn variables_batch(variables: &Variables) -> anyhow::Result<RecordBatch> {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("name", DataType::Utf8, false),
+        Field::new("value", DataType::Utf8, false),
+        Field::new("data_type", DataType::Utf8, false),
+    ]));
+    let names = StringArray::from_iter_values(variables.keys());
+    let values = StringArray::from_iter_values(variables.values().map(format_value));
+    let types =
+        StringArray::from_iter_values(variables.values().map(|v| v.data_type().to_string()));
+    let batch = RecordBatch::try_new(
+        schema,
+        vec![Arc::new(names), Arc::new(values), Arc::new(types)],
+    )?;
+    Ok(batch)
+}
+
+/// Render a value as in the tables, e.g. the timestamps as dates and times.
+pub fn format_value(value: &ScalarValue) -> String {
+    value
+        .to_array()
+        .ok()
+        .and_then(|array| array_value_to_string(&array, 0).ok())
+        .unwrap_or_else(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{AsArray, Int64Array},
+        compute::concat_batches,
+        datatypes::Int64Type,
+    };
+
+    use super::*;
+
+    fn context() -> SessionContext {
+        let ctx = SessionContext::new();
+        let batch = RecordBatch::try_from_iter(vec![
+            ("a", Arc::new(Int64Array::from(vec![1, 2, 3])) as _),
+            ("s", Arc::new(StringArray::from(vec!["x", "y", "z"])) as _),
+        ])
+        .unwrap();
+        ctx.register_batch("t", batch).unwrap();
+        ctx
+    }
+
+    fn variables(values: &[(&str, ScalarValue)]) -> Variables {
+        values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    fn params(values: &[(&str, &str)]) -> Vec<(String, String)> {
+        values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    async fn query(
+        sql: &str,
+        params: &[(String, String)],
+        variables: &Variables,
+    ) -> anyhow::Result<RecordBatch> {
+        let df = plan(&context(), sql, params, variables).await?;
+        let schema = df.schema().inner().clone();
+        Ok(concat_batches(&schema, &df.collect().await?)?)
+    }
+
+    fn ints(batch: &RecordBatch) -> Vec<i64> {
+        batch
+            .column(0)
+            .as_primitive::<Int64Type>()
+            .values()
+            .to_vec()
+    }
+
+    #[test]
+    fn placeholders_should_be_numbered_by_name() {
+        let ctx = SessionContext::new();
+        let sql = "SELECT * FROM t WHERE a > $min AND s = :name OR a < $min";
+        let DFStatement::Statement(mut statement) =
+            ctx.state().sql_to_statement(sql, "generic").unwrap()
+        else {
+            panic!("expect a SQL statement");
+        };
+        let vars = variables(&[("min", ScalarValue::Int64(Some(1)))]);
+        let names = number_placeholders(&mut statement, &params(&[("name", "y")]), &vars);
+
+        // the declared types of the variables come first
+        assert_eq!(names, vec!["min", "name"]);
+        assert_eq!(
+            statement.to_string(),
+            "SELECT * FROM t WHERE a > $1 AND s = $2 OR a < $1"
+        );
+    }
+
+    #[tokio::test]
+    async fn plan_should_bind_variables_and_params() {
+        let vars = variables(&[
+            ("min", ScalarValue::Int64(Some(1))),
+            ("name", ScalarValue::Utf8(Some("z".to_string()))),
+        ]);
+        let sql = "SELECT a FROM t WHERE a > $min AND a >= :min AND s <> $name";
+        assert_eq!(ints(&query(sql, &[], &vars).await.unwrap()), vec![2]);
+
+        // the parameters of the command are parsed as the types of the query
+        let sql = "SELECT a FROM t WHERE a > $min AND s <> :name";
+        let batch = query(sql, &params(&[("min", "0")]), &vars).await.unwrap();
+        assert_eq!(ints(&batch), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn plan_should_declare_the_types_of_variables() {
+        // a lone placeholder has no type unless the variable declares it
+        let vars = variables(&[("n", ScalarValue::Int64(Some(5)))]);
+        let batch = query("SELECT $n AS n", &[], &vars).await.unwrap();
+        assert_eq!(batch.schema().field(0).data_type(), &DataType::Int64);
+        assert_eq!(ints(&batch), vec![5]);
+    }
+
+    #[tokio::test]
+    async fn plan_should_check_the_types_of_variables() {
+        let vars = variables(&[("b", ScalarValue::Binary(Some(vec![1])))]);
+        let err = query("SELECT a FROM t WHERE a > $b", &[], &vars)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("can't be used as Int64"),
+            "{}",
+            err
+        );
+
+        let err = query(
+            "SELECT a FROM t WHERE a > $min",
+            &params(&[("min", "x")]),
+            &vars,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("Invalid value x"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn plan_should_reject_unknown_names() {
+        let vars = Variables::new();
+        let err = query("SELECT a FROM t WHERE a > $nope", &[], &vars)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Unknown variable $nope"),
+            "{}",
+            err
+        );
+
+        let err = query("SELECT a FROM t", &params(&[("min", "1")]), &vars)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no parameter $min"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn plan_should_keep_placeholders_in_strings() {
+        let vars = variables(&[("name", ScalarValue::Utf8(Some("x".to_string())))]);
+        let batch = query("SELECT ':name' AS s, a FROM t WHERE s = :name", &[], &vars)
+            .await
+            .unwrap();
+        assert_eq!(batch.column(0).as_string::<i32>().value(0), ":name");
+        assert_eq!(batch.num_rows(), 1);
+    }
+}
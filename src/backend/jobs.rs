@@ -0,0 +1,238 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail};
+use tokio::{sync::watch, task::AbortHandle};
+
+/// The commands in flight in the backend and the background jobs, shown by the
+/// `jobs` command. Clones share the same registry.
+#[derive(Debug, Clone, Default)]
+pub struct JobRegistry {
+    inner: Arc<Mutex<Registry>>,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    // the background jobs are numbered from 1, the foreground commands have their own keys
+    next_id: usize,
+    next_command: usize,
+    jobs: BTreeMap<usize, Entry>,
+    commands: BTreeMap<usize, Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobState {
+    Running,
+    Finished { rows: usize },
+    Failed(String),
+    Cancelled,
+}
+
+/// A snapshot of a job, foreground commands have no id.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: Option<usize>,
+    pub command: String,
+    pub state: JobState,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug)]
+struct Entry {
+    command: String,
+    started: Instant,
+    ended: Option<Instant>,
+    state: watch::Sender<JobState>,
+    abort: Option<AbortHandle>,
+}
+
+/// Removes the foreground command from the registry when it is done or dropped.
+pub struct JobGuard {
+    key: usize,
+    registry: JobRegistry,
+}
+
+/// The temporary table where the result of a background job is kept.
+pub fn job_table(id: usize) -> String {
+    format!("job_{}", id)
+}
+
+impl JobRegistry {
+    /// Register a foreground command, until the guard is dropped.
+    pub fn start(&self, command: impl Into<String>) -> JobGuard {
+        let mut registry = self.inner.lock().unwrap();
+        registry.next_command += 1;
+        let key = registry.next_command;
+        registry.commands.insert(key, Entry::new(command.into()));
+        JobGuard {
+            key,
+            registry: self.clone(),
+        }
+    }
+
+    /// Run `job` in the background, it returns the number of rows of the result.
+    pub fn spawn<F, Fut>(&self, command: impl Into<String>, job: F) -> usize
+    where
+        F: FnOnce(usize) -> Fut,
+        Fut: Future<Output = anyhow::Result<usize>> + Send + 'static,
+    {
+        let mut registry = self.inner.lock().unwrap();
+        registry.next_id += 1;
+        let id = registry.next_id;
+        let fut = job(id);
+        let this = self.clone();
+        let handle = tokio::spawn(async move {
+            let state = match fut.await {
+                Ok(rows) => JobState::Finished { rows },
+                Err(err) => JobState::Failed(err.to_string()),
+            };
+            this.update(id, state);
+        });
+        let mut entry = Entry::new(command.into());
+        entry.abort = Some(handle.abort_handle());
+        registry.jobs.insert(id, entry);
+        id
+    }
+
+    /// Wait for a background job to be done.
+    pub async fn wait(&self, id: usize) -> anyhow::Result<Job> {
+        let mut rx = self.with_job(id, |entry| Ok(entry.state.subscribe()))?;
+        rx.wait_for(|state| *state != JobState::Running).await?;
+        self.get(id).ok_or_else(|| anyhow!("No such job: {}", id))
+    }
+
+    pub fn cancel(&self, id: usize) -> anyhow::Result<()> {
+        self.with_job(id, |entry| {
+            if *entry.state.borrow() != JobState::Running {
+                bail!("Job {} is already done", id);
+            }
+            if let Some(abort) = &entry.abort {
+                abort.abort();
+            }
+            Ok(())
+        })?;
+        self.update(id, JobState::Cancelled);
+        Ok(())
+    }
+
+    pub fn get(&self, id: usize) -> Option<Job> {
+        let registry = self.inner.lock().unwrap();
+        registry.jobs.get(&id).map(|entry| entry.snapshot(Some(id)))
+    }
+
+    /// The background jobs, then the foreground commands in flight.
+    pub fn list(&self) -> Vec<Job> {
+        let registry = self.inner.lock().unwrap();
+        let jobs = registry
+            .jobs
+            .iter()
+            .map(|(id, entry)| entry.snapshot(Some(*id)));
+        let commands = registry.commands.values().map(|entry| entry.snapshot(None));
+        jobs.chain(commands).collect()
+    }
+
+    /// Remove the jobs which are done, returns their ids and final states.
+    pub fn clear(&self) -> Vec<(usize, JobState)> {
+        let mut registry = self.inner.lock().unwrap();
+        let mut done = Vec::new();
+        registry.jobs.retain(|id, entry| {
+            let state = entry.state.borrow().clone();
+            if state == JobState::Running {
+                return true;
+            }
+            done.push((*id, state));
+            false
+        });
+        done
+    }
+
+    fn with_job<T>(
+        &self,
+        id: usize,
+        f: impl FnOnce(&Entry) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let registry = self.inner.lock().unwrap();
+        let entry = registry
+            .jobs
+            .get(&id)
+            .ok_or_else(|| anyhow!("No such job: {}", id))?;
+        f(entry)
+    }
+
+    /// Move a running job to its final state, a job cancelled in the meantime stays cancelled.
+    fn update(&self, id: usize, state: JobState) {
+        let mut registry = self.inner.lock().unwrap();
+        if let Some(entry) = registry.jobs.get_mut(&id) {
+            if *entry.state.borrow() == JobState::Running {
+                entry.ended = Some(Instant::now());
+                entry.state.send_replace(state);
+            }
+        }
+    }
+}
+
+impl Entry {
+    fn new(command: String) -> Self {
+        Self {
+            command,
+            started: Instant::now(),
+            ended: None,
+            state: watch::Sender::new(JobState::Running),
+            abort: None,
+        }
+    }
+
+    fn snapshot(&self, id: Option<usize>) -> Job {
+        let ended = self.ended.unwrap_or_else(Instant::now);
+        Job {
+            id,
+            command: self.command.clone(),
+            state: self.state.borrow().clone(),
+            elapsed: ended - self.started,
+        }
+    }
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        let mut registry = self.registry.inner.lock().unwrap();
+        registry.commands.remove(&self.key);
+    }
+}
+
+impl fmt::Display for JobState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Running => write!(f, "running"),
+            Self::Finished { rows } => write!(f, "finished, {} rows", rows),
+            Self::Failed(err) => write!(f, "failed: {}", err),
+            Self::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn clear_should_keep_running_jobs() {
+        let registry = JobRegistry::default();
+        let done = registry.spawn("done", |_| async { Ok(3) });
+        let running = registry.spawn("running", |_| std::future::pending());
+        registry.wait(done).await.unwrap();
+
+        assert_eq!(
+            registry.clear(),
+            vec![(done, JobState::Finished { rows: 3 })]
+        );
+        let ids: Vec<_> = registry.list().iter().map(|job| job.id).collect();
+        assert_eq!(ids, vec![Some(running)]);
+        assert!(registry.get(done).is_none());
+    }
+}
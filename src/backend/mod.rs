@@ -1,3 +1,5 @@
 mod fusion;
+mod jobs;
 
 pub use fusion::DataFusionBackend;
+pub use jobs::{job_table, Job, JobRegistry, JobState};
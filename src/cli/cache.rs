@@ -0,0 +1,77 @@
+use clap::{ArgMatches, Parser};
+
+use crate::{Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+pub struct CacheOpts {
+    #[arg(help = "The name of the dataset")]
+    pub name: String,
+
+    #[arg(
+        short,
+        long,
+        help = "The number of partitions of the cached data, those of the scan by default"
+    )]
+    pub partitions: Option<usize>,
+}
+
+pub fn cache(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: CacheOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for CacheOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        Ok(backend.cache(&self.name, self.partitions).await?)
+    }
+}
+
+impl TryFrom<ArgMatches> for CacheOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let name = args
+            .get_one::<String>("name")
+            .expect("expect name")
+            .to_string();
+        let partitions = args.get_one::<usize>("partitions").copied();
+        Ok(CacheOpts { name, partitions })
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct UncacheOpts {
+    #[arg(help = "The name of the cached dataset")]
+    pub name: String,
+}
+
+pub fn uncache(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: UncacheOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for UncacheOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        backend.uncache(&self.name).await?;
+        Ok(format!(
+            "Dataset {} is read from its source again",
+            self.name
+        ))
+    }
+}
+
+impl TryFrom<ArgMatches> for UncacheOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let name = args
+            .get_one::<String>("name")
+            .expect("expect name")
+            .to_string();
+        Ok(UncacheOpts { name })
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use clap::{ArgMatches, Parser};
 use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
 
@@ -6,6 +8,7 @@ use crate::{Backend, CmdExecutor, ReplContext, ReplMsg};
 use super::ReplResult;
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum DatasetConn {
     Postgres(String),
     Csv(FileOpts),
@@ -14,13 +17,15 @@ pub enum DatasetConn {
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct FileOpts {
     pub filename: String,
     pub ext: String,
-    pub compression: FileCompressionType,
+    pub(crate) compression: FileCompressionType,
 }
 
-#[derive(Debug, Parser)]
+#[derive(Debug, Clone, Parser)]
+#[non_exhaustive]
 pub struct ConnectOpts {
     #[arg(value_parser = verify_conn_str, help = "Connection string to the dataset, could be postgres or local file (support: csv, json, parquet)")]
     pub conn: DatasetConn,
@@ -33,6 +38,16 @@ pub struct ConnectOpts {
     pub name: String,
 }
 
+impl DatasetConn {
+    /// The file of the dataset, or the connection string of a database.
+    pub fn path(&self) -> &str {
+        match self {
+            DatasetConn::Postgres(conn) | DatasetConn::Parquet(conn) => conn,
+            DatasetConn::Csv(opts) | DatasetConn::NdJson(opts) => &opts.filename,
+        }
+    }
+}
+
 pub fn connect(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
     let opts: ConnectOpts = args.try_into()?;
     let (msg, rx) = ReplMsg::new(opts);
@@ -63,7 +78,19 @@ impl TryFrom<ArgMatches> for ConnectOpts {
     }
 }
 
-fn verify_conn_str(s: &str) -> Result<DatasetConn, String> {
+/// Parse a dataset given on the command line in the form of `name=conn`.
+pub(crate) fn parse_dataset(s: &str) -> Result<ConnectOpts, String> {
+    let (name, conn) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid dataset {}, expect name=conn", s))?;
+    Ok(ConnectOpts {
+        conn: verify_conn_str(conn)?,
+        table: None,
+        name: name.to_string(),
+    })
+}
+
+pub(crate) fn verify_conn_str(s: &str) -> Result<DatasetConn, String> {
     let conn_str = s.to_string();
     if conn_str.starts_with("postgres://") {
         return Ok(DatasetConn::Postgres(conn_str));
@@ -72,44 +99,87 @@ fn verify_conn_str(s: &str) -> Result<DatasetConn, String> {
         return Ok(DatasetConn::Parquet(conn_str));
     }
 
-    // process .csv, .csv.gz, .csv.bz2, .csv.xz, .csv.zstd
-    let exts = conn_str.split('.').rev().collect::<Vec<_>>();
-    let len = exts.len();
-    let mut exts = exts.into_iter().take(len - 1);
-    let (ext1, ext2) = (exts.next(), exts.next());
-    match (ext1, ext2) {
-        (Some(ext1), Some(ext2)) => {
-            let compression = match ext1 {
-                "gz" => FileCompressionType::GZIP,
-                "bz2" => FileCompressionType::BZIP2,
-                "xz" => FileCompressionType::XZ,
-                "zstd" => FileCompressionType::ZSTD,
-                v => return Err(format!("Invalid compression type: {}", v)),
-            };
-            let opts = FileOpts {
-                filename: s.to_string(),
-                ext: ext2.to_string(),
-                compression,
-            };
-            match ext1 {
-                "csv" => Ok(DatasetConn::Csv(opts)),
-                "json" | "jsonl" | "ndjson" => Ok(DatasetConn::NdJson(opts)),
-                v => Err(format!("Invalid compression type: {}", v)),
-            }
-        }
-        (Some(ext1), None) => {
-            let opts = FileOpts {
-                filename: s.to_string(),
-                ext: ext1.to_string(),
-                compression: FileCompressionType::UNCOMPRESSED,
-            };
-            match ext1 {
-                "csv" => Ok(DatasetConn::Csv(opts)),
-                "json" | "jsonl" | "ndjson" => Ok(DatasetConn::NdJson(opts)),
-                "parquet" => Ok(DatasetConn::Parquet(s.to_string())),
-                v => Err(format!("Invalid compression type: {}", v)),
-            }
+    // process .csv, .csv.gz, .csv.bz2, .csv.xz, .csv.zstd, and the same for json
+    let file_name = Path::new(s)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(s);
+    let mut exts = file_name.rsplit('.').take(file_name.matches('.').count());
+    let last = exts.next();
+    let (kind, compression) = match last {
+        Some("gz") => (exts.next(), FileCompressionType::GZIP),
+        Some("bz2") => (exts.next(), FileCompressionType::BZIP2),
+        Some("xz") => (exts.next(), FileCompressionType::XZ),
+        Some("zstd") => (exts.next(), FileCompressionType::ZSTD),
+        ext => (ext, FileCompressionType::UNCOMPRESSED),
+    };
+    let Some(kind) = kind else {
+        return Err(format!("Invalid connection string: {}", s));
+    };
+    // the files are filtered by the whole extension, the compression included
+    let ext = if compression.is_compressed() {
+        format!("{}.{}", kind, last.unwrap_or_default())
+    } else {
+        kind.to_string()
+    };
+    let opts = FileOpts {
+        filename: s.to_string(),
+        ext,
+        compression,
+    };
+    match kind {
+        "csv" => Ok(DatasetConn::Csv(opts)),
+        "json" | "jsonl" | "ndjson" => Ok(DatasetConn::NdJson(opts)),
+        v => Err(format!("Invalid file type: {}", v)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+
+    use super::{verify_conn_str, DatasetConn, FileOpts};
+
+    fn file_opts(conn: DatasetConn) -> FileOpts {
+        match conn {
+            DatasetConn::Csv(opts) | DatasetConn::NdJson(opts) => opts,
+            conn => panic!("not a file: {:?}", conn),
         }
-        _ => Err(format!("Invalid connection string: {}", s)),
+    }
+
+    #[test]
+    fn verify_conn_str_should_read_the_kind_and_compression() {
+        let conn = verify_conn_str("users.csv").unwrap();
+        assert!(matches!(conn, DatasetConn::Csv(_)));
+        let opts = file_opts(conn);
+        assert_eq!(opts.ext, "csv");
+        assert_eq!(opts.compression, FileCompressionType::UNCOMPRESSED);
+
+        let conn = verify_conn_str("users.csv.gz").unwrap();
+        assert!(matches!(conn, DatasetConn::Csv(_)));
+        let opts = file_opts(conn);
+        assert_eq!(opts.ext, "csv.gz");
+        assert_eq!(opts.compression, FileCompressionType::GZIP);
+
+        let conn = verify_conn_str("users.ndjson.zstd").unwrap();
+        assert!(matches!(conn, DatasetConn::NdJson(_)));
+        let opts = file_opts(conn);
+        assert_eq!(opts.ext, "ndjson.zstd");
+        assert_eq!(opts.compression, FileCompressionType::ZSTD);
+    }
+
+    #[test]
+    fn verify_conn_str_should_ignore_the_dots_of_the_directories() {
+        let opts = file_opts(verify_conn_str("./dir/x.csv").unwrap());
+        assert_eq!(opts.filename, "./dir/x.csv");
+        assert_eq!(opts.ext, "csv");
+        assert_eq!(opts.compression, FileCompressionType::UNCOMPRESSED);
+    }
+
+    #[test]
+    fn verify_conn_str_should_reject_unknown_files() {
+        assert!(verify_conn_str("users").is_err());
+        assert!(verify_conn_str("./dir.d/users").is_err());
+        assert!(verify_conn_str("users.txt").is_err());
     }
 }
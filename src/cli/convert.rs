@@ -0,0 +1,104 @@
+use clap::{ArgMatches, Parser};
+
+use crate::{Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::{verify_conn_str, DatasetConn, ReplResult};
+
+#[derive(Debug, Parser)]
+#[non_exhaustive]
+pub struct ConvertOpts {
+    #[arg(value_parser = verify_conn_str, help = "The file to read (support: csv, json, parquet, compressed or not)")]
+    pub input: DatasetConn,
+
+    #[arg(value_parser = verify_conn_str, help = "The file to write, its format is found the same way as the input's")]
+    pub output: DatasetConn,
+
+    #[arg(
+        short,
+        long,
+        value_parser = parse_key_value,
+        help = "The type of a column instead of the inferred one, as column=SQL type, e.g. zip=varchar"
+    )]
+    pub schema: Vec<(String, String)>,
+
+    #[arg(
+        long,
+        help = "The compression of the parquet output, e.g. snappy or zstd(3)"
+    )]
+    pub compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "The max number of rows of the row groups of the parquet output"
+    )]
+    pub row_group_size: Option<usize>,
+
+    #[arg(
+        short,
+        long = "option",
+        value_parser = parse_key_value,
+        help = "An option of the parquet writer as key=value, e.g. bloom_filter_on_write=true"
+    )]
+    pub options: Vec<(String, String)>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Write a directory with a sub-directory per value of the columns, e.g. gender=male"
+    )]
+    pub partition_by: Vec<String>,
+}
+
+pub fn convert(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: ConvertOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for ConvertOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let rows = backend.convert(&self).await?;
+        Ok(format!(
+            "Converted {} rows from {} to {}",
+            rows,
+            self.input.path(),
+            self.output.path()
+        ))
+    }
+}
+
+impl TryFrom<ArgMatches> for ConvertOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let conn = |name| {
+            args.get_one::<DatasetConn>(name)
+                .expect("expect conn")
+                .to_owned()
+        };
+        let pairs = |name| {
+            args.get_many::<(String, String)>(name)
+                .map(|values| values.cloned().collect())
+                .unwrap_or_default()
+        };
+        Ok(ConvertOpts {
+            input: conn("input"),
+            output: conn("output"),
+            schema: pairs("schema"),
+            compression: args.get_one::<String>("compression").cloned(),
+            row_group_size: args.get_one::<usize>("row_group_size").copied(),
+            options: pairs("options"),
+            partition_by: args
+                .get_many::<String>("partition_by")
+                .map(|values| values.cloned().collect())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid value {}, expect key=value", s))?;
+    Ok((key.trim().to_string(), value.trim().to_string()))
+}
@@ -0,0 +1,86 @@
+use clap::{ArgMatches, Parser};
+
+use crate::{Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+pub struct CountOpts {
+    #[arg(help = "The name of the dataset")]
+    pub name: String,
+
+    #[arg(
+        long = "where",
+        num_args = 1..,
+        allow_hyphen_values = true,
+        help = "Only count the rows matching a SQL condition, e.g. --where gender = 'female'"
+    )]
+    pub filter: Vec<String>,
+}
+
+pub(crate) fn count_query(name: &str, filter: Option<&str>) -> String {
+    match filter {
+        Some(filter) => format!("SELECT count(*) FROM {} WHERE {}", name, filter),
+        None => format!("SELECT count(*) FROM {}", name),
+    }
+}
+
+pub fn count(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: CountOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for CountOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let filter = (!self.filter.is_empty()).then(|| self.filter.join(" "));
+        let count = backend.count(&self.name, filter.as_deref()).await?;
+        Ok(count.to_string())
+    }
+}
+
+impl TryFrom<ArgMatches> for CountOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let name = args
+            .get_one::<String>("name")
+            .expect("expect name")
+            .to_string();
+        let filter = args
+            .get_many::<String>("filter")
+            .map(|values| values.cloned().collect())
+            .unwrap_or_default();
+        Ok(CountOpts { name, filter })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::CountOpts;
+    use crate::{Backend, CmdExecutor, ConnectOpts, DataFusionBackend, LetOpts};
+
+    async fn count(backend: &mut DataFusionBackend, args: &[&str]) -> String {
+        let opts = CountOpts::try_parse_from([&["count", "users"], args].concat()).unwrap();
+        opts.execute(backend).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn count_should_count_the_rows_matching_the_condition() {
+        let mut backend = DataFusionBackend::new();
+        let opts =
+            ConnectOpts::try_parse_from(["connect", "assets/users.ndjson", "--name", "users"])
+                .unwrap();
+        backend.connect(&opts).await.unwrap();
+
+        assert_eq!(count(&mut backend, &[]).await, "100");
+        let female = count(&mut backend, &["--where", "gender", "=", "'female'"]).await;
+        assert_eq!(female, "36");
+
+        let opts = LetOpts::try_parse_from(["let", "g", "=", "'male'"]).unwrap();
+        opts.execute(&mut backend).await.unwrap();
+        assert_eq!(count(&mut backend, &["--where", "gender = $g"]).await, "31");
+    }
+}
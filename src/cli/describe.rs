@@ -1,6 +1,8 @@
+use std::time::Instant;
+
 use clap::{ArgMatches, Parser};
 
-use crate::{Backend, CmdExecutor, ReplContext, ReplDisplay, ReplMsg};
+use crate::{display_timed, Backend, CmdExecutor, ReplContext, ReplMsg};
 
 use super::ReplResult;
 
@@ -8,6 +10,12 @@ use super::ReplResult;
 pub struct DescribeOpts {
     #[arg(help = "The name of the dataset")]
     pub name: String,
+
+    #[arg(
+        long,
+        help = "Read the statistics of the parquet footers instead of the data, the bounds are shown as >= or <="
+    )]
+    pub fast: bool,
 }
 
 pub fn describe(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
@@ -18,8 +26,13 @@ pub fn describe(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
 
 impl CmdExecutor for DescribeOpts {
     async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let start = Instant::now();
+        if self.fast {
+            let batch = backend.describe_fast(&self.name).await?;
+            return display_timed(start, batch, backend.settings()).await;
+        }
         let df = backend.describe(&self.name).await?;
-        df.display().await
+        display_timed(start, df, backend.settings()).await
     }
 }
 
@@ -31,6 +44,7 @@ impl TryFrom<ArgMatches> for DescribeOpts {
             .get_one::<String>("name")
             .expect("expect name")
             .to_string();
-        Ok(DescribeOpts { name })
+        let fast = args.get_flag("fast");
+        Ok(DescribeOpts { name, fast })
     }
 }
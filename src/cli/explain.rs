@@ -0,0 +1,49 @@
+use clap::{ArgMatches, Parser};
+
+use crate::{Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+pub struct ExplainOpts {
+    #[arg(help = "The SQL query to explain")]
+    pub query: String,
+
+    #[arg(long, help = "Execute the query and show the metrics of each operator")]
+    pub analyze: bool,
+
+    #[arg(long, help = "Show the schema of the plans and more details")]
+    pub verbose: bool,
+}
+
+pub fn explain(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: ExplainOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for ExplainOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        Ok(backend
+            .explain(&self.query, self.analyze, self.verbose)
+            .await?)
+    }
+}
+
+impl TryFrom<ArgMatches> for ExplainOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let query = args
+            .get_one::<String>("query")
+            .expect("expect query")
+            .to_string();
+        let analyze = args.get_flag("analyze");
+        let verbose = args.get_flag("verbose");
+        Ok(ExplainOpts {
+            query,
+            analyze,
+            verbose,
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use std::time::Instant;
+
+use clap::{ArgMatches, Parser};
+
+use crate::{display_timed, Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+#[non_exhaustive]
+pub struct FreqOpts {
+    #[arg(help = "The name of the dataset")]
+    pub name: String,
+
+    #[arg(help = "The column of the values to count, e.g. \"kit number\"")]
+    pub column: String,
+
+    #[arg(
+        long,
+        default_value_t = 20,
+        help = "The number of most frequent values to show, the nulls are shown besides"
+    )]
+    pub top: usize,
+
+    #[arg(
+        long,
+        help = "Show the shares as fractions of 1 instead of percentages"
+    )]
+    pub normalize: bool,
+
+    #[arg(
+        long,
+        help = "Count the elements of a list column instead of the lists, e.g. finished"
+    )]
+    pub explode: bool,
+}
+
+pub fn freq(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: FreqOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for FreqOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let start = Instant::now();
+        let df = backend.freq(&self).await?;
+        display_timed(start, df, backend.settings()).await
+    }
+}
+
+impl TryFrom<ArgMatches> for FreqOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let name = args
+            .get_one::<String>("name")
+            .expect("expect name")
+            .to_string();
+        let column = args
+            .get_one::<String>("column")
+            .expect("expect column")
+            .to_string();
+        Ok(FreqOpts {
+            name,
+            column,
+            top: args.get_one::<usize>("top").copied().unwrap_or(20),
+            normalize: args.get_flag("normalize"),
+            explode: args.get_flag("explode"),
+        })
+    }
+}
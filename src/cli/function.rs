@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use clap::{ArgMatches, Parser, Subcommand};
+
+use crate::{Backend, CmdExecutor, ReplContext, ReplDisplay, ReplMsg};
+
+use super::ReplResult;
+
+const FUNCTIONS_FILE: &str = ".taotie_functions";
+
+#[derive(Debug, Parser)]
+#[non_exhaustive]
+pub struct FunctionOpts {
+    #[command(subcommand)]
+    pub action: FunctionAction,
+}
+
+#[derive(Debug, Subcommand)]
+#[non_exhaustive]
+pub enum FunctionAction {
+    #[command(
+        about = "Create a SQL function, e.g. function create full_name(first, last) AS concat(first, ' ', last)"
+    )]
+    Create {
+        #[arg(
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            required = true,
+            help = "The function as name(arg, ...) AS <SQL expression of the args>"
+        )]
+        definition: Vec<String>,
+    },
+
+    #[command(about = "List the SQL functions")]
+    List,
+
+    #[command(about = "Drop a SQL function")]
+    Drop {
+        #[arg(help = "The name of the function")]
+        name: String,
+    },
+
+    #[command(about = "Load the SQL functions of a file, where they are saved from now on")]
+    Load {
+        #[arg(
+            short,
+            long,
+            help = "The functions file, ~/.taotie_functions by default"
+        )]
+        file: Option<String>,
+    },
+}
+
+impl FunctionOpts {
+    pub fn new(action: FunctionAction) -> Self {
+        Self { action }
+    }
+}
+
+pub fn function(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: FunctionOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for FunctionOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        match self.action {
+            FunctionAction::Create { definition } => {
+                let name = backend.create_function(&definition.join(" ")).await?;
+                Ok(format!("Function {} created", name))
+            }
+            FunctionAction::List => {
+                let data = backend.functions().await?;
+                data.display(backend.settings()).await
+            }
+            FunctionAction::Drop { name } => {
+                backend.drop_function(&name).await?;
+                Ok(format!("Function {} dropped", name))
+            }
+            FunctionAction::Load { file } => {
+                let path = match file {
+                    Some(file) => PathBuf::from(file),
+                    None => dirs::home_dir()
+                        .expect("expect home dir")
+                        .join(FUNCTIONS_FILE),
+                };
+                let count = backend.load_functions(&path).await?;
+                // nothing to report for a missing or empty file, e.g. at startup
+                if count == 0 {
+                    return Ok(String::new());
+                }
+                Ok(format!(
+                    "{} functions loaded from {}",
+                    count,
+                    path.display()
+                ))
+            }
+        }
+    }
+}
+
+impl TryFrom<ArgMatches> for FunctionOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let action = match args.subcommand() {
+            Some(("create", args)) => FunctionAction::Create {
+                definition: args
+                    .get_many::<String>("definition")
+                    .map(|values| values.cloned().collect())
+                    .unwrap_or_default(),
+            },
+            Some(("list", _)) => FunctionAction::List,
+            Some(("drop", args)) => FunctionAction::Drop {
+                name: args
+                    .get_one::<String>("name")
+                    .expect("expect name")
+                    .to_string(),
+            },
+            Some(("load", args)) => FunctionAction::Load {
+                file: args.get_one::<String>("file").cloned(),
+            },
+            _ => unreachable!("the action of function is required"),
+        };
+        Ok(FunctionOpts { action })
+    }
+}
@@ -1,6 +1,8 @@
+use std::time::Instant;
+
 use clap::{ArgMatches, Parser};
 
-use crate::{Backend, CmdExecutor, ReplContext, ReplDisplay, ReplMsg};
+use crate::{display_timed, Backend, CmdExecutor, ReplContext, ReplMsg};
 
 use super::ReplResult;
 
@@ -9,10 +11,17 @@ pub struct HeadOpts {
     #[arg(help = "The name of the dataset")]
     pub name: String,
 
-    #[arg(short, long, help = "The number of rows to show")]
+    #[arg(short, long, help = "The number of rows to show, 5 by default")]
     pub n: Option<usize>,
 }
 
+impl HeadOpts {
+    /// The number of rows to show.
+    pub fn size(&self) -> usize {
+        self.n.unwrap_or(5)
+    }
+}
+
 pub fn head(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
     let opts: HeadOpts = args.try_into()?;
     let (msg, rx) = ReplMsg::new(opts);
@@ -21,8 +30,9 @@ pub fn head(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
 
 impl CmdExecutor for HeadOpts {
     async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
-        let df = backend.head(&self.name, self.n.unwrap_or(5)).await?;
-        df.display().await
+        let start = Instant::now();
+        let df = backend.head(&self.name, self.size()).await?;
+        display_timed(start, df, backend.settings()).await
     }
 }
 
@@ -34,7 +44,7 @@ impl TryFrom<ArgMatches> for HeadOpts {
             .get_one::<String>("name")
             .expect("expect name")
             .to_string();
-        let n = args.get_one::<usize>("n").copied().unwrap_or(5);
-        Ok(HeadOpts { name, n: Some(n) })
+        let n = args.get_one::<usize>("n").copied();
+        Ok(HeadOpts { name, n })
     }
 }
@@ -0,0 +1,123 @@
+use clap::{ArgMatches, Parser};
+
+use crate::{Backend, CmdExecutor, ReplContext, ReplDisplay, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+pub struct JobsOpts {
+    #[arg(
+        long,
+        help = "Remove the jobs which are done, and drop the tables of their results"
+    )]
+    pub clear: bool,
+}
+
+pub fn jobs(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: JobsOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for JobsOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        if self.clear {
+            let count = backend.clear_jobs().await?;
+            return Ok(format!("{} jobs cleared", count));
+        }
+        let df = backend.jobs().await?;
+        df.display(backend.settings()).await
+    }
+}
+
+impl TryFrom<ArgMatches> for JobsOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let clear = args.get_flag("clear");
+        Ok(JobsOpts { clear })
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct WaitOpts {
+    #[arg(help = "The id of the background job")]
+    pub id: usize,
+}
+
+pub fn wait(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: WaitOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for WaitOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        Ok(backend.wait(self.id).await?)
+    }
+}
+
+impl TryFrom<ArgMatches> for WaitOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let id = *args.get_one::<usize>("id").expect("expect id");
+        Ok(WaitOpts { id })
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct CancelOpts {
+    #[arg(help = "The id of the background job")]
+    pub id: usize,
+}
+
+pub fn cancel(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: CancelOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for CancelOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        backend.cancel(self.id).await?;
+        Ok(format!("Job {} cancelled", self.id))
+    }
+}
+
+impl TryFrom<ArgMatches> for CancelOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let id = *args.get_one::<usize>("id").expect("expect id");
+        Ok(CancelOpts { id })
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct ResultOpts {
+    #[arg(help = "The id of the background job")]
+    pub id: usize,
+}
+
+pub fn result(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: ResultOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for ResultOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let df = backend.result(self.id).await?;
+        df.display(backend.settings()).await
+    }
+}
+
+impl TryFrom<ArgMatches> for ResultOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let id = *args.get_one::<usize>("id").expect("expect id");
+        Ok(ResultOpts { id })
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::anyhow;
+use clap::{ArgMatches, Parser};
+
+use crate::{Backend, CmdExecutor, ReplContext, ReplDisplay, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+pub struct LetOpts {
+    #[arg(
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        help = "The variable and its value as a SQL expression, e.g. since = '2024-05-01', \
+                used as $since or :since in queries. Without it the variables are listed"
+    )]
+    pub assignment: Vec<String>,
+}
+
+pub fn let_var(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: LetOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for LetOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        if self.assignment.is_empty() {
+            let data = backend.variables().await?;
+            return data.display(backend.settings()).await;
+        }
+
+        let assignment = self.assignment.join(" ");
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid assignment {}, expect name = value", assignment))?;
+        let name = name.trim().trim_start_matches(['$', ':']);
+        let value = backend.define(name, value.trim()).await?;
+        Ok(format!("{} = {}", name, value))
+    }
+}
+
+impl TryFrom<ArgMatches> for LetOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let assignment = args
+            .get_many::<String>("assignment")
+            .map(|values| values.cloned().collect())
+            .unwrap_or_default();
+        Ok(LetOpts { assignment })
+    }
+}
@@ -15,6 +15,6 @@ pub fn list(_args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
 impl CmdExecutor for ListOpts {
     async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
         let df = backend.list().await?;
-        df.display().await
+        df.display(backend.settings()).await
     }
 }
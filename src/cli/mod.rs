@@ -1,23 +1,64 @@
+mod cache;
 mod connect;
+mod convert;
+mod count;
 mod describe;
+mod explain;
+mod freq;
+mod function;
 mod head;
+mod jobs;
+mod let_var;
 mod list;
+mod pager;
+mod parquet_meta;
+mod query;
+mod sample;
 mod schema;
+mod set;
+mod settings;
+mod show;
 mod sql;
+mod tail;
+mod timing;
+mod top;
+mod view;
+
+use std::fmt;
 
 use enum_dispatch::enum_dispatch;
 
+pub use cache::*;
 pub use connect::*;
+pub use convert::*;
+pub use count::*;
 pub use describe::*;
+pub use explain::*;
+pub use freq::*;
+pub use function::*;
 pub use head::*;
+pub use jobs::*;
+pub use let_var::*;
 pub use list::*;
+pub use pager::*;
+pub use parquet_meta::*;
+pub use query::*;
+pub use sample::*;
 pub use schema::*;
+pub use set::*;
+pub use settings::*;
+pub use show::*;
 pub use sql::*;
+pub use tail::*;
+pub use timing::*;
+pub use top::*;
+pub use view::*;
 
 use clap::Parser;
 
 #[derive(Debug, Parser)]
 #[enum_dispatch(CmdExecutor)]
+#[non_exhaustive]
 pub enum ReplCommand {
     #[command(
         name = "connect",
@@ -28,6 +69,15 @@ pub enum ReplCommand {
     #[command(name = "list", about = "List all registered datasets")]
     List(ListOpts),
 
+    #[command(name = "cache", about = "Load a dataset in memory, it keeps its name")]
+    Cache(CacheOpts),
+
+    #[command(
+        name = "uncache",
+        about = "Read a cached dataset from its source again"
+    )]
+    Uncache(UncacheOpts),
+
     #[command(name = "schema", about = "Describe the schema of a dataset")]
     Schema(SchemaOpts),
 
@@ -37,8 +87,195 @@ pub enum ReplCommand {
     #[command(name = "head", about = "Show first few rows of a dataset")]
     Head(HeadOpts),
 
+    #[command(
+        name = "tail",
+        about = "Show last few rows of a dataset, in the order of its files or of a column"
+    )]
+    Tail(TailOpts),
+
+    #[command(
+        name = "top",
+        about = "Show the rows with the largest values of a column"
+    )]
+    Top(TopOpts),
+
+    #[command(
+        name = "count",
+        about = "Count the rows of a dataset, or those matching a condition"
+    )]
+    Count(CountOpts),
+
+    #[command(
+        name = "freq",
+        about = "Count the values of a column, the most frequent first, with their shares"
+    )]
+    Freq(FreqOpts),
+
+    #[command(
+        name = "sample",
+        about = "Show random rows of a dataset, of each group with --by"
+    )]
+    Sample(SampleOpts),
+
     #[command(name = "sql", about = "Query a dataset using given SQL")]
     Sql(SqlOpts),
+
+    #[command(
+        name = "let",
+        about = "Define a variable used as $name in queries, e.g. let since = '2024-05-01'"
+    )]
+    Let(LetOpts),
+
+    #[command(
+        name = "view",
+        about = "Create or drop a view, which is listed as a dataset"
+    )]
+    View(ViewOpts),
+
+    #[command(
+        name = "function",
+        about = "Create, list or drop SQL functions, saved between sessions"
+    )]
+    Function(FunctionOpts),
+
+    #[command(
+        name = "query",
+        about = "Save queries with parameters and run them later"
+    )]
+    Query(QueryOpts),
+
+    #[command(
+        name = "explain",
+        about = "Show the logical and physical plans of a query"
+    )]
+    Explain(ExplainOpts),
+
+    #[command(
+        name = "parquet-meta",
+        about = "Show the row groups, column chunks and statistics of parquet files"
+    )]
+    ParquetMeta(ParquetMetaOpts),
+
+    #[command(
+        name = "convert",
+        about = "Convert a file to another format, e.g. convert users.csv.gz users.parquet"
+    )]
+    Convert(ConvertOpts),
+
+    #[command(name = "set", about = "Change a session setting, e.g. set timeout 30s")]
+    Set(SetOpts),
+
+    #[command(
+        name = "show",
+        about = "Show a session setting, or all of them, e.g. show target_partitions"
+    )]
+    Show(ShowOpts),
+
+    #[command(
+        name = "settings",
+        about = "Save the changed settings to a file, or load them"
+    )]
+    Settings(SettingsOpts),
+
+    #[command(
+        name = "timing",
+        about = "Turn the timing footer of query results on or off"
+    )]
+    Timing(TimingOpts),
+
+    #[command(
+        name = "jobs",
+        about = "Show the commands running in the backend and the background jobs"
+    )]
+    Jobs(JobsOpts),
+
+    #[command(name = "wait", about = "Wait for a background job to be done")]
+    Wait(WaitOpts),
+
+    #[command(name = "cancel", about = "Cancel a background job")]
+    Cancel(CancelOpts),
+
+    #[command(name = "result", about = "Show the result of a background job")]
+    Result(ResultOpts),
 }
 
 pub type ReplResult = Result<Option<String>, reedline_repl_rs::Error>;
+
+impl ReplCommand {
+    /// Commands changing the catalog or the settings of the backend, they are run
+    /// one at a time while the other commands run concurrently.
+    pub fn is_mutation(&self) -> bool {
+        matches!(
+            self,
+            Self::Connect(_)
+                | Self::Cache(_)
+                | Self::Uncache(_)
+                | Self::View(_)
+                | Self::Function(_)
+                | Self::Let(_)
+                | Self::Set(_)
+                | Self::Settings(_)
+                | Self::Timing(_)
+        ) || matches!(self, Self::Sample(opts) if opts.save_as.is_some())
+            || matches!(self, Self::Jobs(opts) if opts.clear)
+            || matches!(self, Self::Query(opts) if matches!(opts.action, QueryAction::Save { .. } | QueryAction::Drop { .. }))
+    }
+}
+
+impl fmt::Display for ReplCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect(opts) => write!(f, "connect --name {}", opts.name),
+            Self::List(_) => write!(f, "list"),
+            Self::Cache(opts) => write!(f, "cache {}", opts.name),
+            Self::Uncache(opts) => write!(f, "uncache {}", opts.name),
+            Self::Schema(opts) => write!(f, "schema {}", opts.name),
+            Self::Describe(opts) if opts.fast => write!(f, "describe --fast {}", opts.name),
+            Self::Describe(opts) => write!(f, "describe {}", opts.name),
+            Self::Head(opts) => write!(f, "head {}", opts.name),
+            Self::Tail(opts) => write!(f, "tail {}", opts.name),
+            Self::Top(opts) => write!(f, "top {} --by {}", opts.name, opts.by),
+            Self::Count(opts) if opts.filter.is_empty() => write!(f, "count {}", opts.name),
+            Self::Count(opts) => write!(f, "count {} --where {}", opts.name, opts.filter.join(" ")),
+            Self::Freq(opts) => write!(f, "freq {} {}", opts.name, opts.column),
+            Self::Sample(opts) => write!(f, "sample {}", opts.name),
+            Self::Sql(opts) if opts.background => write!(f, "sql --background {}", opts.query),
+            Self::Sql(opts) => write!(f, "sql {}", opts.query),
+            Self::Let(opts) => write!(f, "let {}", opts.assignment.join(" ")),
+            Self::View(opts) => match &opts.action {
+                ViewAction::Create { name, .. } => write!(f, "view create {}", name),
+                ViewAction::Drop { name } => write!(f, "view drop {}", name),
+            },
+            Self::Function(opts) => match &opts.action {
+                FunctionAction::Create { definition } => {
+                    write!(f, "function create {}", definition.join(" "))
+                }
+                FunctionAction::List => write!(f, "function list"),
+                FunctionAction::Drop { name } => write!(f, "function drop {}", name),
+                FunctionAction::Load { .. } => write!(f, "function load"),
+            },
+            Self::Query(opts) => match &opts.action {
+                QueryAction::Save { name, .. } => write!(f, "query save {}", name),
+                QueryAction::Run { name, .. } => write!(f, "query run {}", name),
+                QueryAction::List => write!(f, "query list"),
+                QueryAction::Drop { name } => write!(f, "query drop {}", name),
+            },
+            Self::Explain(opts) => write!(f, "explain {}", opts.query),
+            Self::ParquetMeta(opts) => write!(f, "parquet-meta {}", opts.target),
+            Self::Convert(opts) => {
+                write!(f, "convert {} {}", opts.input.path(), opts.output.path())
+            }
+            Self::Set(opts) => write!(f, "set {} {}", opts.key, opts.value),
+            Self::Show(opts) => write!(f, "show {}", opts.key),
+            Self::Settings(opts) => match opts.action {
+                SettingsAction::Save => write!(f, "settings save"),
+                SettingsAction::Load => write!(f, "settings load"),
+            },
+            Self::Timing(_) => write!(f, "timing"),
+            Self::Jobs(_) => write!(f, "jobs"),
+            Self::Wait(opts) => write!(f, "wait {}", opts.id),
+            Self::Cancel(opts) => write!(f, "cancel {}", opts.id),
+            Self::Result(opts) => write!(f, "result {}", opts.id),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+use reedline_repl_rs::crossterm::terminal;
+
+const DEFAULT_PAGER: &str = "less -SRX";
+
+/// Show the output in a pager (like `less`) if it doesn't fit in the terminal.
+/// Returns false if the output should be printed by the REPL as usual.
+pub fn page(output: &str) -> bool {
+    if !io::stdout().is_terminal() {
+        return false;
+    }
+    let Ok((_, rows)) = terminal::size() else {
+        return false;
+    };
+    if output.lines().count() < rows as usize {
+        return false;
+    }
+
+    match spawn_pager(output) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("Failed to run pager: {}", err);
+            false
+        }
+    }
+}
+
+fn spawn_pager(output: &str) -> io::Result<()> {
+    let pager = env::var("TAOTIE_PAGER")
+        .or_else(|_| env::var("PAGER"))
+        .unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+    let mut args = pager.split_whitespace();
+    let program = args.next().unwrap_or("less");
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // the user may quit the pager before reading everything
+        match stdin.write_all(output.as_bytes()) {
+            Err(err) if err.kind() != io::ErrorKind::BrokenPipe => return Err(err),
+            _ => {}
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
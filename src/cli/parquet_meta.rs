@@ -0,0 +1,35 @@
+use clap::{ArgMatches, Parser};
+
+use crate::{Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+pub struct ParquetMetaOpts {
+    #[arg(help = "A parquet file, a directory of parquet files or the name of a dataset")]
+    pub target: String,
+}
+
+pub fn parquet_meta(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: ParquetMetaOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for ParquetMetaOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        Ok(backend.parquet_meta(&self.target).await?)
+    }
+}
+
+impl TryFrom<ArgMatches> for ParquetMetaOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let target = args
+            .get_one::<String>("target")
+            .expect("expect target")
+            .to_string();
+        Ok(ParquetMetaOpts { target })
+    }
+}
@@ -0,0 +1,253 @@
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, Write},
+    path::PathBuf,
+    time::Instant,
+};
+
+use anyhow::anyhow;
+use clap::{ArgMatches, Parser, Subcommand};
+
+use crate::{display_timed, Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+pub struct QueryOpts {
+    #[command(subcommand)]
+    pub action: QueryAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum QueryAction {
+    #[command(
+        about = "Save a query, it may have parameters, e.g. query save since \"SELECT ... WHERE created_at > $since\""
+    )]
+    Save {
+        #[arg(help = "The name of the query")]
+        name: String,
+
+        #[arg(help = "The SQL of the query, $name is a parameter")]
+        query: String,
+    },
+
+    #[command(about = "Run a saved query, e.g. query run since since=2024-01-01")]
+    Run {
+        #[arg(help = "The name of the query")]
+        name: String,
+
+        #[arg(value_parser = parse_param, help = "The values of the parameters, as name=value")]
+        params: Vec<(String, String)>,
+    },
+
+    #[command(about = "List the saved queries")]
+    List,
+
+    #[command(about = "Delete a saved query")]
+    Drop {
+        #[arg(help = "The name of the query")]
+        name: String,
+    },
+}
+
+pub fn query(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: QueryOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for QueryOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let queries = SavedQueries::new(backend.settings().queries_file.clone());
+        match self.action {
+            QueryAction::Save { name, query } => {
+                queries.update(|queries| {
+                    queries.insert(name.clone(), query);
+                    Ok(())
+                })?;
+                Ok(format!("Query {} saved", name))
+            }
+            QueryAction::Run { name, params } => {
+                let start = Instant::now();
+                let query = queries
+                    .read()?
+                    .remove(&name)
+                    .ok_or_else(|| anyhow!("No such query: {}", name))?;
+                let df = backend.sql_with_params(&query, &params).await?;
+                display_timed(start, df, backend.settings()).await
+            }
+            QueryAction::List => {
+                let list = queries
+                    .read()?
+                    .iter()
+                    .map(|(name, query)| format!("{}: {}", name, query))
+                    .collect::<Vec<_>>();
+                Ok(list.join("\n"))
+            }
+            QueryAction::Drop { name } => {
+                queries.update(|queries| {
+                    queries
+                        .remove(&name)
+                        .ok_or_else(|| anyhow!("No such query: {}", name))
+                })?;
+                Ok(format!("Query {} dropped", name))
+            }
+        }
+    }
+}
+
+impl TryFrom<ArgMatches> for QueryOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let name = |args: &ArgMatches| {
+            args.get_one::<String>("name")
+                .expect("expect name")
+                .to_string()
+        };
+        let action = match args.subcommand() {
+            Some(("save", args)) => QueryAction::Save {
+                name: name(args),
+                query: args
+                    .get_one::<String>("query")
+                    .expect("expect query")
+                    .to_string(),
+            },
+            Some(("run", args)) => QueryAction::Run {
+                name: name(args),
+                params: args
+                    .get_many::<(String, String)>("params")
+                    .map(|params| params.cloned().collect())
+                    .unwrap_or_default(),
+            },
+            Some(("list", _)) => QueryAction::List,
+            Some(("drop", args)) => QueryAction::Drop { name: name(args) },
+            _ => unreachable!("the action of query is required"),
+        };
+        Ok(QueryOpts { action })
+    }
+}
+
+/// The saved queries by name, kept in a JSON file between sessions. The file is
+/// locked while it is read or changed, as several REPLs may share it.
+struct SavedQueries {
+    path: PathBuf,
+}
+
+impl SavedQueries {
+    fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn read(&self) -> anyhow::Result<BTreeMap<String, String>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+        file.lock_shared()?;
+        self.parse(&file)
+    }
+
+    /// Change the queries and write them back, nothing is written if `f` fails.
+    fn update<T>(
+        &self,
+        f: impl FnOnce(&mut BTreeMap<String, String>) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)?;
+        file.lock()?;
+        let mut queries = self.parse(&file)?;
+        let ret = f(&mut queries)?;
+        file.set_len(0)?;
+        file.rewind()?;
+        file.write_all(serde_json::to_string_pretty(&queries)?.as_bytes())?;
+        Ok(ret)
+    }
+
+    fn parse(&self, mut file: &File) -> anyhow::Result<BTreeMap<String, String>> {
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        if content.trim().is_empty() {
+            return Ok(BTreeMap::new());
+        }
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Invalid saved queries in {}: {}", self.path.display(), e))
+    }
+}
+
+fn parse_param(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid parameter {}, expect name=value", s))?;
+    Ok((name.trim_start_matches('$').to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::QueryOpts;
+    use crate::{Backend, CmdExecutor, ConnectOpts, DataFusionBackend, ReplCommand};
+
+    async fn run(backend: &mut DataFusionBackend, args: &[&str]) -> anyhow::Result<String> {
+        let opts = QueryOpts::try_parse_from([&["query"], args].concat()).unwrap();
+        opts.execute(backend).await
+    }
+
+    #[tokio::test]
+    async fn saved_query_should_run_with_the_values_of_its_params() {
+        let path = std::env::temp_dir().join("taotie_queries_saved.json");
+        let _ = std::fs::remove_file(&path);
+        let mut backend = DataFusionBackend::new();
+        backend
+            .set("queries_file", path.to_str().unwrap())
+            .await
+            .unwrap();
+        let opts =
+            ConnectOpts::try_parse_from(["connect", "assets/users.ndjson", "--name", "users"])
+                .unwrap();
+        backend.connect(&opts).await.unwrap();
+
+        let sql = "SELECT count(*) AS n FROM users WHERE gender = $gender";
+        let output = run(&mut backend, &["save", "by_gender", sql])
+            .await
+            .unwrap();
+        assert_eq!(output, "Query by_gender saved");
+        let listed = run(&mut backend, &["list"]).await.unwrap();
+        assert_eq!(listed, format!("by_gender: {}", sql));
+
+        let output = run(&mut backend, &["run", "by_gender", "gender=female"])
+            .await
+            .unwrap();
+        assert!(output.contains("| 36 |"), "{}", output);
+        let output = run(&mut backend, &["run", "by_gender", "$gender=male"])
+            .await
+            .unwrap();
+        assert!(output.contains("| 31 |"), "{}", output);
+
+        run(&mut backend, &["drop", "by_gender"]).await.unwrap();
+        assert_eq!(run(&mut backend, &["list"]).await.unwrap(), "");
+        let err = run(&mut backend, &["drop", "by_gender"]).await.unwrap_err();
+        assert_eq!(err.to_string(), "No such query: by_gender");
+        let err = run(&mut backend, &["run", "by_gender"]).await.unwrap_err();
+        assert_eq!(err.to_string(), "No such query: by_gender");
+    }
+
+    #[test]
+    fn saving_or_dropping_a_query_should_be_a_mutation() {
+        let cmd = |args: &[&str]| {
+            let opts = QueryOpts::try_parse_from([&["query"], args].concat()).unwrap();
+            ReplCommand::from(opts).is_mutation()
+        };
+        assert!(cmd(&["save", "q", "SELECT 1"]));
+        assert!(cmd(&["drop", "q"]));
+        assert!(!cmd(&["run", "q"]));
+        assert!(!cmd(&["list"]));
+    }
+}
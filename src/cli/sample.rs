@@ -0,0 +1,97 @@
+use std::time::Instant;
+
+use clap::{ArgMatches, Parser};
+
+use crate::{display_timed, Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+#[non_exhaustive]
+pub struct SampleOpts {
+    #[arg(help = "The name of the dataset")]
+    pub name: String,
+
+    #[arg(
+        short,
+        long,
+        conflicts_with = "fraction",
+        help = "The number of rows, of each group with --by, 10 by default"
+    )]
+    pub n: Option<usize>,
+
+    #[arg(
+        long,
+        value_parser = parse_fraction,
+        help = "The fraction of the rows to keep, of each group with --by"
+    )]
+    pub fraction: Option<f64>,
+
+    #[arg(
+        long,
+        help = "The seed of a reproducible sample, a random one by default"
+    )]
+    pub seed: Option<u64>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Sample each group of the columns, e.g. --by gender"
+    )]
+    pub by: Vec<String>,
+
+    #[arg(long, help = "Register the sample as a dataset of this name")]
+    pub save_as: Option<String>,
+}
+
+impl SampleOpts {
+    /// The number of rows, of each group.
+    pub fn size(&self) -> usize {
+        self.n.unwrap_or(10)
+    }
+}
+
+pub fn sample(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: SampleOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for SampleOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let start = Instant::now();
+        // the sample may borrow the backend which registers it
+        let settings = backend.settings().clone();
+        let df = backend.sample(&self).await?;
+        display_timed(start, df, &settings).await
+    }
+}
+
+impl TryFrom<ArgMatches> for SampleOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let name = args
+            .get_one::<String>("name")
+            .expect("expect name")
+            .to_string();
+        Ok(SampleOpts {
+            name,
+            n: args.get_one::<usize>("n").copied(),
+            fraction: args.get_one::<f64>("fraction").copied(),
+            seed: args.get_one::<u64>("seed").copied(),
+            by: args
+                .get_many::<String>("by")
+                .map(|values| values.cloned().collect())
+                .unwrap_or_default(),
+            save_as: args.get_one::<String>("save_as").cloned(),
+        })
+    }
+}
+
+fn parse_fraction(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(fraction) if fraction > 0.0 && fraction <= 1.0 => Ok(fraction),
+        _ => Err(format!("Invalid fraction {}, expect a number in (0, 1]", s)),
+    }
+}
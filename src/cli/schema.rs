@@ -19,7 +19,7 @@ pub fn schema(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
 impl CmdExecutor for SchemaOpts {
     async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
         let df = backend.schema(&self.name).await?;
-        df.display().await
+        df.display(backend.settings()).await
     }
 }
 
@@ -0,0 +1,198 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::anyhow;
+use clap::{ArgMatches, Parser};
+
+use crate::{Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::ReplResult;
+
+pub const DEFAULT_MAX_ROWS: usize = 100;
+const QUERIES_FILE: &str = ".taotie_queries.json";
+
+#[derive(Debug, Parser)]
+#[non_exhaustive]
+pub struct SetOpts {
+    #[arg(help = "The setting to change, e.g. timeout")]
+    pub key: String,
+
+    #[arg(help = "The new value, e.g. 30s, or off to disable")]
+    pub value: String,
+}
+
+/// Settings of the REPL session which are kept by the backend.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ReplSettings {
+    pub timeout: Option<Duration>,
+    pub max_rows: Option<usize>,
+    pub timing: bool,
+    /// Where the saved queries are kept.
+    pub queries_file: PathBuf,
+}
+
+impl SetOpts {
+    pub fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+}
+
+pub fn set(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: SetOpts = args.try_into()?;
+    // the pager is part of the UI, so it is not sent to the backend
+    if opts.key == "pager" {
+        return match parse_switch(&opts.value) {
+            Ok(pager) => {
+                ctx.pager = pager;
+                Ok(Some(format!("{} = {}", opts.key, opts.value)))
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                Ok(None)
+            }
+        };
+    }
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for SetOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        backend.set(&self.key, &self.value).await?;
+        Ok(format!("{} = {}", self.key, self.value))
+    }
+}
+
+impl TryFrom<ArgMatches> for SetOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let key = args
+            .get_one::<String>("key")
+            .expect("expect key")
+            .to_string();
+        let value = args
+            .get_one::<String>("value")
+            .expect("expect value")
+            .to_string();
+        Ok(SetOpts { key, value })
+    }
+}
+
+impl ReplSettings {
+    /// Apply a REPL level setting, returns false if the key is unknown.
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<bool> {
+        match key {
+            "timeout" => self.timeout = parse_timeout(value)?,
+            "max_rows" => self.max_rows = parse_max_rows(value)?,
+            "timing" => self.timing = parse_switch(value)?,
+            "queries_file" => self.queries_file = PathBuf::from(value),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+impl ReplSettings {
+    /// The REPL level settings, as key, value and description.
+    pub fn entries(&self) -> Vec<(&'static str, String, &'static str)> {
+        let timeout = self.timeout.map_or("off".to_string(), |t| {
+            humantime::format_duration(t).to_string()
+        });
+        let max_rows = self.max_rows.map_or("off".to_string(), |n| n.to_string());
+        let timing = if self.timing { "on" } else { "off" };
+        vec![
+            (
+                "timeout",
+                timeout,
+                "Cancel the commands running longer than it",
+            ),
+            (
+                "max_rows",
+                max_rows,
+                "The maximum number of rows shown by a query",
+            ),
+            (
+                "timing",
+                timing.to_string(),
+                "Show the statistics of the queries",
+            ),
+            (
+                "queries_file",
+                self.queries_file.display().to_string(),
+                "The file of the saved queries",
+            ),
+        ]
+    }
+}
+
+impl Default for ReplSettings {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            max_rows: Some(DEFAULT_MAX_ROWS),
+            timing: false,
+            queries_file: dirs::home_dir().unwrap_or_default().join(QUERIES_FILE),
+        }
+    }
+}
+
+pub(crate) fn parse_switch(value: &str) -> anyhow::Result<bool> {
+    match value {
+        "on" | "true" => Ok(true),
+        "off" | "false" => Ok(false),
+        v => Err(anyhow!("Invalid value {}, expect on or off", v)),
+    }
+}
+
+fn parse_max_rows(value: &str) -> anyhow::Result<Option<usize>> {
+    match value {
+        "off" | "none" | "0" => Ok(None),
+        v => Ok(Some(v.parse()?)),
+    }
+}
+
+fn parse_timeout(value: &str) -> anyhow::Result<Option<Duration>> {
+    match value {
+        "off" | "none" | "0" => Ok(None),
+        v => {
+            let timeout = humantime::parse_duration(v)
+                .map_err(|e| anyhow!("Invalid timeout {}: {}", v, e))?;
+            Ok(Some(timeout))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::parse_timeout;
+
+    #[test]
+    fn parse_timeout_should_accept_durations() {
+        let timeout = parse_timeout("30s").unwrap();
+        assert_eq!(timeout, Some(Duration::from_secs(30)));
+        let timeout = parse_timeout("1m 30s").unwrap();
+        assert_eq!(timeout, Some(Duration::from_secs(90)));
+        let timeout = parse_timeout("500ms").unwrap();
+        assert_eq!(timeout, Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn parse_timeout_should_turn_off_the_timeout() {
+        for value in ["off", "none", "0"] {
+            assert_eq!(parse_timeout(value).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn parse_timeout_should_reject_invalid_durations() {
+        assert!(parse_timeout("30").is_err());
+        assert!(parse_timeout("soon").is_err());
+        assert!(parse_timeout("-1s").is_err());
+    }
+}
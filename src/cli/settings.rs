@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use clap::{ArgMatches, Parser, ValueEnum};
+
+use crate::{Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::ReplResult;
+
+const SETTINGS_FILE: &str = ".taotie_settings";
+
+#[derive(Debug, Parser)]
+pub struct SettingsOpts {
+    #[arg(
+        value_enum,
+        help = "Save the changed settings to the file, or load them from it"
+    )]
+    pub action: SettingsAction,
+
+    #[arg(short, long, help = "The settings file, ~/.taotie_settings by default")]
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SettingsAction {
+    Save,
+    Load,
+}
+
+pub fn settings(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: SettingsOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for SettingsOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let path = match self.file {
+            Some(file) => PathBuf::from(file),
+            None => dirs::home_dir()
+                .expect("expect home dir")
+                .join(SETTINGS_FILE),
+        };
+        let msg = match self.action {
+            SettingsAction::Save => {
+                let count = backend.save_settings(&path).await?;
+                format!("{} settings saved to {}", count, path.display())
+            }
+            SettingsAction::Load => {
+                let count = backend.load_settings(&path).await?;
+                format!("{} settings loaded from {}", count, path.display())
+            }
+        };
+        Ok(msg)
+    }
+}
+
+impl TryFrom<ArgMatches> for SettingsOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let action = *args
+            .get_one::<SettingsAction>("action")
+            .expect("expect action");
+        let file = args.get_one::<String>("file").cloned();
+        Ok(SettingsOpts { action, file })
+    }
+}
@@ -0,0 +1,36 @@
+use clap::{ArgMatches, Parser};
+
+use crate::{Backend, CmdExecutor, ReplContext, ReplDisplay, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+pub struct ShowOpts {
+    #[arg(help = "The setting to show, e.g. max_rows or datafusion.execution.batch_size, or all")]
+    pub key: String,
+}
+
+pub fn show(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: ShowOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for ShowOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let data = backend.show(&self.key).await?;
+        data.display(backend.settings()).await
+    }
+}
+
+impl TryFrom<ArgMatches> for ShowOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let key = args
+            .get_one::<String>("key")
+            .expect("expect key")
+            .to_string();
+        Ok(ShowOpts { key })
+    }
+}
@@ -1,6 +1,8 @@
+use std::time::Instant;
+
 use clap::{ArgMatches, Parser};
 
-use crate::{Backend, CmdExecutor, ReplContext, ReplDisplay, ReplMsg};
+use crate::{display_timed, Backend, CmdExecutor, ReplContext, ReplMsg};
 
 use super::ReplResult;
 
@@ -8,6 +10,13 @@ use super::ReplResult;
 pub struct SqlOpts {
     #[arg(help = "The SQL query")]
     pub query: String,
+
+    #[arg(
+        short,
+        long,
+        help = "Run the query in the background, the result is kept as table job_<id>"
+    )]
+    pub background: bool,
 }
 
 pub fn sql(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
@@ -18,8 +27,16 @@ pub fn sql(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
 
 impl CmdExecutor for SqlOpts {
     async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        if self.background {
+            let id = backend.sql_background(&self.query).await?;
+            return Ok(format!(
+                "Job {} started, use `wait {}` or `result {}` to get its result",
+                id, id, id
+            ));
+        }
+        let start = Instant::now();
         let df = backend.sql(&self.query).await?;
-        df.display().await
+        display_timed(start, df, backend.settings()).await
     }
 }
 
@@ -31,6 +48,7 @@ impl TryFrom<ArgMatches> for SqlOpts {
             .get_one::<String>("query")
             .expect("expect query")
             .to_string();
-        Ok(SqlOpts { query })
+        let background = args.get_flag("background");
+        Ok(SqlOpts { query, background })
     }
 }
@@ -0,0 +1,95 @@
+use std::time::Instant;
+
+use clap::{ArgMatches, Parser};
+
+use crate::{display_timed, quote_ident, Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+pub struct TailOpts {
+    #[arg(help = "The name of the dataset")]
+    pub name: String,
+
+    #[arg(short, long, help = "The number of rows to show, 5 by default")]
+    pub n: Option<usize>,
+
+    #[arg(
+        long,
+        help = "The column ordering the rows, the order of the files by default"
+    )]
+    pub order_by: Option<String>,
+}
+
+impl TailOpts {
+    /// The number of rows to show.
+    pub fn size(&self) -> usize {
+        self.n.unwrap_or(5)
+    }
+}
+
+/// The last `size` rows in the order of `column`: the first ones of the reverse
+/// order, shown in the order.
+pub(crate) fn tail_query(name: &str, column: &str, size: usize) -> String {
+    let column = quote_ident(column);
+    format!(
+        "SELECT * FROM (SELECT * FROM {} ORDER BY {} DESC NULLS FIRST LIMIT {}) ORDER BY {}",
+        name, column, size, column
+    )
+}
+
+pub fn tail(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: TailOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for TailOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let start = Instant::now();
+        let data = backend
+            .tail(&self.name, self.size(), self.order_by.as_deref())
+            .await?;
+        display_timed(start, data, backend.settings()).await
+    }
+}
+
+impl TryFrom<ArgMatches> for TailOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let name = args
+            .get_one::<String>("name")
+            .expect("expect name")
+            .to_string();
+        let n = args.get_one::<usize>("n").copied();
+        let order_by = args.get_one::<String>("order_by").cloned();
+        Ok(TailOpts { name, n, order_by })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{array::AsArray, datatypes::Int64Type};
+    use clap::Parser;
+
+    use super::TailOpts;
+    use crate::{Backend, ConnectOpts, DataFusionBackend};
+
+    #[tokio::test]
+    async fn tail_should_order_by_a_column_of_any_name() {
+        let mut backend = DataFusionBackend::new();
+        let opts =
+            ConnectOpts::try_parse_from(["connect", "assets/juventus.csv", "--name", "juve"])
+                .unwrap();
+        backend.connect(&opts).await.unwrap();
+        let opts = TailOpts::try_parse_from(["tail", "juve", "--order-by", "kit number"]).unwrap();
+        assert_eq!(opts.size(), 5);
+        let batch = backend
+            .tail(&opts.name, 2, opts.order_by.as_deref())
+            .await
+            .unwrap();
+        let kits = batch.column(4).as_primitive::<Int64Type>();
+        assert_eq!(kits.values().to_vec(), [37, 77]);
+    }
+}
@@ -0,0 +1,97 @@
+use clap::{ArgMatches, Parser};
+
+use crate::{Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+pub struct TimingOpts {
+    #[arg(help = "on or off, toggle the timing footer if not given")]
+    pub state: Option<String>,
+}
+
+pub fn timing(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: TimingOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for TimingOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let state = match self.state {
+            Some(state) => state,
+            None if backend.settings().timing => "off".to_string(),
+            None => "on".to_string(),
+        };
+        backend.set("timing", &state).await?;
+        Ok(format!("Timing is {}", state))
+    }
+}
+
+impl TryFrom<ArgMatches> for TimingOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let state = args.get_one::<String>("state").map(|s| s.to_string());
+        Ok(TimingOpts { state })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::TimingOpts;
+    use crate::{cli::SqlOpts, CmdExecutor, DataFusionBackend, QueryStats};
+
+    fn sql(query: &str) -> SqlOpts {
+        SqlOpts {
+            query: query.to_string(),
+            background: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn timing_should_toggle_the_footer_of_the_results() {
+        let mut backend = DataFusionBackend::new();
+        let query = "SELECT * FROM (VALUES (1), (2)) AS t(n)";
+        let data = sql(query).execute(&mut backend).await.unwrap();
+        assert!(!data.contains("in set"));
+
+        let state = TimingOpts { state: None };
+        assert_eq!(state.execute(&mut backend).await.unwrap(), "Timing is on");
+        let data = sql(query).execute(&mut backend).await.unwrap();
+        let footer = data.lines().last().unwrap();
+        assert!(footer.starts_with("2 rows in set (planning: "));
+        assert!(footer.contains(", execution: "));
+
+        let state = TimingOpts { state: None };
+        assert_eq!(state.execute(&mut backend).await.unwrap(), "Timing is off");
+        let data = sql(query).execute(&mut backend).await.unwrap();
+        assert!(!data.contains("in set"));
+    }
+
+    #[test]
+    fn query_stats_should_show_the_rows_and_the_times() {
+        let stats = QueryStats {
+            rows: 1,
+            planning: Duration::from_millis(2),
+            execution: Duration::from_millis(30),
+            ..Default::default()
+        };
+        assert_eq!(
+            stats.to_string(),
+            "1 row in set (planning: 2.000ms, execution: 30.000ms)"
+        );
+        let stats = QueryStats {
+            rows: 100,
+            truncated: true,
+            bytes_scanned: Some(2048),
+            ..stats
+        };
+        assert_eq!(
+            stats.to_string(),
+            "100+ rows in set (planning: 2.000ms, execution: 30.000ms, scanned: 2.0 KB)"
+        );
+    }
+}
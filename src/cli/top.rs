@@ -0,0 +1,87 @@
+use std::time::Instant;
+
+use clap::{ArgMatches, Parser};
+
+use crate::{display_timed, quote_ident, Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+pub struct TopOpts {
+    #[arg(help = "The name of the dataset")]
+    pub name: String,
+
+    #[arg(long, help = "The column of the values, the nulls are last")]
+    pub by: String,
+
+    #[arg(short, long, help = "The number of rows to show, 10 by default")]
+    pub n: Option<usize>,
+}
+
+impl TopOpts {
+    /// The number of rows to show.
+    pub fn size(&self) -> usize {
+        self.n.unwrap_or(10)
+    }
+}
+
+pub(crate) fn top_query(name: &str, by: &str, size: usize) -> String {
+    format!(
+        "SELECT * FROM {} ORDER BY {} DESC NULLS LAST LIMIT {}",
+        name,
+        quote_ident(by),
+        size
+    )
+}
+
+pub fn top(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: TopOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for TopOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let start = Instant::now();
+        let df = backend.top(&self.name, &self.by, self.size()).await?;
+        display_timed(start, df, backend.settings()).await
+    }
+}
+
+impl TryFrom<ArgMatches> for TopOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let name = args
+            .get_one::<String>("name")
+            .expect("expect name")
+            .to_string();
+        let by = args.get_one::<String>("by").expect("expect by").to_string();
+        let n = args.get_one::<usize>("n").copied();
+        Ok(TopOpts { name, by, n })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{array::AsArray, datatypes::Int64Type};
+    use clap::Parser;
+
+    use super::TopOpts;
+    use crate::{Backend, ConnectOpts, DataFusionBackend};
+
+    #[tokio::test]
+    async fn top_should_order_by_a_column_of_any_name() {
+        let mut backend = DataFusionBackend::new();
+        let opts =
+            ConnectOpts::try_parse_from(["connect", "assets/juventus.csv", "--name", "juve"])
+                .unwrap();
+        backend.connect(&opts).await.unwrap();
+        let opts = TopOpts::try_parse_from(["top", "juve", "--by", "kit number"]).unwrap();
+        assert_eq!(opts.size(), 10);
+        let df = backend.top(&opts.name, &opts.by, 2).await.unwrap();
+        let batches = df.collect().await.unwrap();
+        let kits = batches[0].column(4).as_primitive::<Int64Type>();
+        assert_eq!(kits.values().to_vec(), [77, 37]);
+    }
+}
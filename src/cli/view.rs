@@ -0,0 +1,128 @@
+use clap::{ArgMatches, Parser, Subcommand};
+
+use crate::{Backend, CmdExecutor, ReplContext, ReplMsg};
+
+use super::ReplResult;
+
+#[derive(Debug, Parser)]
+pub struct ViewOpts {
+    #[command(subcommand)]
+    pub action: ViewAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ViewAction {
+    #[command(about = "Register a query as a view, e.g. view create adults \"SELECT ...\"")]
+    Create {
+        #[arg(help = "The name of the view")]
+        name: String,
+
+        #[arg(help = "The query of the view")]
+        query: String,
+    },
+
+    #[command(about = "Drop a view")]
+    Drop {
+        #[arg(help = "The name of the view")]
+        name: String,
+    },
+}
+
+pub fn view(args: ArgMatches, ctx: &mut ReplContext) -> ReplResult {
+    let opts: ViewOpts = args.try_into()?;
+    let (msg, rx) = ReplMsg::new(opts);
+    Ok(ctx.send(msg, rx))
+}
+
+impl CmdExecutor for ViewOpts {
+    async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        match self.action {
+            ViewAction::Create { name, query } => {
+                backend.create_view(&name, &query).await?;
+                Ok(format!("View {} created", name))
+            }
+            ViewAction::Drop { name } => {
+                backend.drop_view(&name).await?;
+                Ok(format!("View {} dropped", name))
+            }
+        }
+    }
+}
+
+impl TryFrom<ArgMatches> for ViewOpts {
+    type Error = reedline_repl_rs::Error;
+
+    fn try_from(args: ArgMatches) -> Result<Self, Self::Error> {
+        let name = |args: &ArgMatches| {
+            args.get_one::<String>("name")
+                .expect("expect name")
+                .to_string()
+        };
+        let action = match args.subcommand() {
+            Some(("create", args)) => ViewAction::Create {
+                name: name(args),
+                query: args
+                    .get_one::<String>("query")
+                    .expect("expect query")
+                    .to_string(),
+            },
+            Some(("drop", args)) => ViewAction::Drop { name: name(args) },
+            _ => unreachable!("the action of view is required"),
+        };
+        Ok(ViewOpts { action })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::AsArray;
+    use clap::Parser;
+
+    use super::ViewOpts;
+    use crate::{Backend, CmdExecutor, ConnectOpts, DataFusionBackend, ReplDisplay};
+
+    async fn run(backend: &mut DataFusionBackend, args: &[&str]) -> anyhow::Result<String> {
+        let opts = ViewOpts::try_parse_from([&["view"], args].concat()).unwrap();
+        opts.execute(backend).await
+    }
+
+    async fn table_type(backend: &DataFusionBackend, name: &str) -> Option<String> {
+        let batch = backend.list().await.unwrap();
+        let names = batch.column(0).as_string::<i32>();
+        let types = batch.column(1).as_string::<i32>();
+        let i = names.iter().position(|n| n == Some(name))?;
+        Some(types.value(i).to_string())
+    }
+
+    #[tokio::test]
+    async fn view_should_be_listed_and_read_like_a_dataset() {
+        let mut backend = DataFusionBackend::new();
+        let opts =
+            ConnectOpts::try_parse_from(["connect", "assets/users.ndjson", "--name", "users"])
+                .unwrap();
+        backend.connect(&opts).await.unwrap();
+
+        let sql = "SELECT email, gender FROM users WHERE gender = 'female'";
+        let output = run(&mut backend, &["create", "women", sql]).await.unwrap();
+        assert_eq!(output, "View women created");
+        assert_eq!(table_type(&backend, "women").await.as_deref(), Some("VIEW"));
+        assert_eq!(
+            table_type(&backend, "users").await.as_deref(),
+            Some("BASE TABLE")
+        );
+
+        let head = backend.head("women", 3).await.unwrap();
+        let head = head.display(backend.settings()).await.unwrap();
+        assert_eq!(head.matches("| female |").count(), 3);
+        let describe = backend.describe("women").await.unwrap();
+        let describe = describe.display(backend.settings()).await.unwrap();
+        assert!(describe.contains("| total      | 36.0 "), "{}", describe);
+        let schema = backend.schema("women").await.unwrap();
+        let schema = schema.display(backend.settings()).await.unwrap();
+        assert!(schema.contains("email") && schema.contains("gender"));
+
+        let output = run(&mut backend, &["drop", "women"]).await.unwrap();
+        assert_eq!(output, "View women dropped");
+        assert_eq!(table_type(&backend, "women").await, None);
+    }
+}
@@ -0,0 +1,59 @@
+use std::fmt;
+
+/// The result type of the [`Taotie`](crate::Taotie) session.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors returned by the [`Taotie`](crate::Taotie) session.
+///
+/// New variants may be added in minor releases, the errors of the underlying
+/// engine are kept opaque behind [`BackendError`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The connection string is not a supported dataset.
+    #[error("{0}")]
+    InvalidConnection(String),
+
+    /// The backend does not implement the command.
+    #[error("{0} is not supported by this backend")]
+    Unsupported(&'static str),
+
+    /// The backend failed to run the command, e.g. an unknown dataset or an invalid query.
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+}
+
+/// An error raised by a [`Backend`](crate::Backend), its cause is only available
+/// through [`source`](std::error::Error::source).
+pub struct BackendError(anyhow::Error);
+
+impl BackendError {
+    pub fn new(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self(anyhow::anyhow!(err.into()))
+    }
+}
+
+impl Error {
+    /// The error of a command the backend failed to run.
+    pub(crate) fn backend(err: impl Into<anyhow::Error>) -> Self {
+        Self::Backend(BackendError(err.into()))
+    }
+}
+
+impl fmt::Debug for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for BackendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
@@ -1,43 +1,315 @@
+//! Taotie, your dataset exploration REPL.
+//!
+//! Besides the `taotie` binary, the crate can be embedded: [`Taotie`] is a session
+//! with typed async methods returning [`RecordBatch`]es, and the engine behind it
+//! can be replaced by implementing [`Backend`].
+
 mod backend;
 mod cli;
+mod error;
+#[cfg(feature = "python")]
+mod python;
+mod server;
+mod session;
 
-use std::{ops::Deref, process, thread};
+use std::{
+    fmt,
+    future::{self, Future},
+    ops::Deref,
+    path::Path,
+    process,
+    sync::{Arc, Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
 
-use cli::{ConnectOpts, DescribeOpts, HeadOpts, ListOpts, SchemaOpts, SqlOpts};
+use anyhow::anyhow;
+use arrow::{
+    array::{AsArray, RecordBatch},
+    compute::concat_batches,
+    datatypes::{DataType, SchemaRef, UInt64Type},
+};
+use bytesize::ByteSize;
+use cli::{
+    CacheOpts, CancelOpts, CountOpts, DescribeOpts, ExplainOpts, HeadOpts, JobsOpts, LetOpts,
+    ListOpts, ParquetMetaOpts, QueryOpts, ResultOpts, SchemaOpts, SettingsOpts, ShowOpts, SqlOpts,
+    TailOpts, TimingOpts, TopOpts, UncacheOpts, ViewOpts, WaitOpts,
+};
 use crossbeam_channel as mpsc;
 use enum_dispatch::enum_dispatch;
 
-use backend::DataFusionBackend;
-pub use cli::ReplCommand;
-use reedline_repl_rs::CallBackMap;
-use tokio::runtime::Runtime;
+pub use backend::DataFusionBackend;
+pub use cli::{
+    ConnectOpts, ConvertOpts, DatasetConn, FileOpts, FreqOpts, FunctionAction, FunctionOpts,
+    ReplCommand, ReplSettings, SampleOpts, SetOpts,
+};
+pub use error::{BackendError, Error, Result};
+use reedline_repl_rs::{reedline::ExternalPrinter, CallBackMap};
+pub use server::{
+    serve_flight, serve_http, serve_kernel, serve_pg, FlightOpts, KernelOpts, PgOpts, ServeOpts,
+};
+pub use session::Taotie;
+use tokio::{
+    runtime::Runtime,
+    sync::{Notify, OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock},
+    time,
+};
+
+/// How long the prompt waits for a command before it comes back, the output of the
+/// command is then printed when it is done.
+const DETACH_AFTER: Duration = Duration::from_secs(3);
+
+/// The note under the results cut at `max_rows`.
+pub(crate) const MORE_ROWS: &str = "... more rows, use `set max_rows` to show more";
 
 #[enum_dispatch]
 trait CmdExecutor {
     async fn execute<T: Backend>(self, backend: &mut T) -> anyhow::Result<String>;
 }
 
-trait Backend {
-    async fn connect(&mut self, opts: &ConnectOpts) -> anyhow::Result<()>;
-    async fn list(&self) -> anyhow::Result<impl ReplDisplay>;
-    async fn schema(&self, name: &str) -> anyhow::Result<impl ReplDisplay>;
-    async fn describe(&self, name: &str) -> anyhow::Result<impl ReplDisplay>;
-    async fn head(&self, name: &str, size: usize) -> anyhow::Result<impl ReplDisplay>;
-    async fn sql(&self, sql: &str) -> anyhow::Result<impl ReplDisplay>;
+/// The engine running the commands of the REPL and of the [`Taotie`] session.
+///
+/// The REPL drives a backend from a single thread, so the futures of the
+/// commands are not required to be `Send`. The commands a backend does not
+/// implement return [`Error::Unsupported`].
+#[allow(async_fn_in_trait, unused_variables)]
+pub trait Backend {
+    /// Register the dataset of `opts.conn` as `opts.name`.
+    async fn connect(&mut self, opts: &ConnectOpts) -> Result<()>;
+    /// The registered datasets, with their names, types and the memory used by the
+    /// cached ones.
+    async fn list(&self) -> Result<impl ReplDisplay>;
+    /// Load a dataset in memory under the same name and describe what was cached.
+    async fn cache(&mut self, name: &str, partitions: Option<usize>) -> Result<String> {
+        Err(Error::Unsupported("cache"))
+    }
+    /// Put back the source of a cached dataset.
+    async fn uncache(&mut self, name: &str) -> Result<()> {
+        Err(Error::Unsupported("uncache"))
+    }
+    /// The columns of a dataset, with their types and nullability.
+    async fn schema(&self, name: &str) -> Result<impl ReplDisplay>;
+    /// Summary statistics of the columns of a dataset.
+    async fn describe(&self, name: &str) -> Result<impl ReplDisplay>;
+    /// The statistics of `describe` that the footers of a parquet dataset have, without
+    /// reading the data.
+    async fn describe_fast(&self, name: &str) -> Result<impl ReplDisplay> {
+        Err::<RecordBatch, _>(Error::Unsupported("describe_fast"))
+    }
+    /// The first `size` rows of a dataset.
+    async fn head(&self, name: &str, size: usize) -> Result<impl ReplDisplay>;
+    /// The last `size` rows of a dataset, in the order of its files or of `order_by`.
+    /// Only the order of a column is supported by default.
+    async fn tail(
+        &self,
+        name: &str,
+        size: usize,
+        order_by: Option<&str>,
+    ) -> Result<impl ReplDisplay> {
+        let Some(column) = order_by else {
+            return Err(Error::Unsupported("tail without --order-by"));
+        };
+        collect(self.sql(&cli::tail_query(name, column, size)).await?).await
+    }
+    /// The `size` rows with the largest values of `by`.
+    async fn top(&self, name: &str, by: &str, size: usize) -> Result<impl ReplDisplay> {
+        collect(self.sql(&cli::top_query(name, by, size)).await?).await
+    }
+    /// The number of rows of a dataset, of those matching `filter` if any.
+    async fn count(&self, name: &str, filter: Option<&str>) -> Result<u64> {
+        let batch = collect(self.sql(&cli::count_query(name, filter)).await?).await?;
+        let count =
+            arrow::compute::cast(batch.column(0), &DataType::UInt64).map_err(Error::backend)?;
+        Ok(count.as_primitive::<UInt64Type>().value(0))
+    }
+    /// The counts of the values of `opts.column`, with their shares of the rows.
+    async fn freq(&self, opts: &FreqOpts) -> Result<impl ReplDisplay> {
+        Err::<RecordBatch, _>(Error::Unsupported("freq"))
+    }
+    /// Random rows of a dataset, the same ones for the same seed, registered as a
+    /// dataset if `opts.save_as` is set.
+    async fn sample(&mut self, opts: &SampleOpts) -> Result<impl ReplDisplay> {
+        Err::<RecordBatch, _>(Error::Unsupported("sample"))
+    }
+    /// A query, its placeholders like `$since` or `:since` are bound to the variables
+    /// of the session.
+    async fn sql(&self, sql: &str) -> Result<impl ReplDisplay>;
+    /// A query with parameters like `$since`, the values are parsed as the types
+    /// inferred for the parameters, the missing ones are taken from the variables.
+    async fn sql_with_params(
+        &self,
+        sql: &str,
+        params: &[(String, String)],
+    ) -> Result<impl ReplDisplay> {
+        Err::<RecordBatch, _>(Error::Unsupported("sql_with_params"))
+    }
+    /// Define a variable of the session as the value of a SQL expression, returns
+    /// the value with its type.
+    async fn define(&mut self, name: &str, value: &str) -> Result<String> {
+        Err(Error::Unsupported("define"))
+    }
+    /// The variables of the session, with their values and types.
+    async fn variables(&self) -> Result<impl ReplDisplay> {
+        Err::<RecordBatch, _>(Error::Unsupported("variables"))
+    }
+    /// Register a query as a view, which is listed with the datasets.
+    async fn create_view(&mut self, name: &str, sql: &str) -> Result<()> {
+        Err(Error::Unsupported("create_view"))
+    }
+    async fn drop_view(&mut self, name: &str) -> Result<()> {
+        Err(Error::Unsupported("drop_view"))
+    }
+    /// Register a SQL function defined as `name(arg, ...) AS <expression>`, returns its
+    /// name. The functions are saved to the file they were loaded from.
+    async fn create_function(&mut self, definition: &str) -> Result<String> {
+        Err(Error::Unsupported("create_function"))
+    }
+    async fn drop_function(&mut self, name: &str) -> Result<()> {
+        Err(Error::Unsupported("drop_function"))
+    }
+    /// The SQL functions of the session, with their arguments and expressions.
+    async fn functions(&self) -> Result<impl ReplDisplay> {
+        Err::<RecordBatch, _>(Error::Unsupported("functions"))
+    }
+    /// Register the functions of a file, which is where the functions are saved from now on.
+    async fn load_functions(&mut self, path: &Path) -> Result<usize> {
+        Err(Error::Unsupported("load_functions"))
+    }
+    /// The footer metadata of the parquet files of a path or of a dataset: row groups,
+    /// column chunks with their encodings, codecs and statistics, page indexes,
+    /// bloom filters and key-value metadata.
+    async fn parquet_meta(&self, target: &str) -> Result<String> {
+        Err(Error::Unsupported("parquet_meta"))
+    }
+    /// Write the rows of `opts.input` to `opts.output` in the format of its extension,
+    /// returns the number of rows written.
+    async fn convert(&self, opts: &ConvertOpts) -> Result<u64> {
+        Err(Error::Unsupported("convert"))
+    }
+    /// The rendered plans of a query, executed first if `analyze` is set.
+    async fn explain(&self, sql: &str, analyze: bool, verbose: bool) -> Result<String> {
+        Err(Error::Unsupported("explain"))
+    }
+    /// Change a session setting, unknown keys and invalid values are an error.
+    async fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        Err(Error::Unsupported("set"))
+    }
+    /// The value and the description of a session setting, or of all of them for `all`.
+    async fn show(&self, key: &str) -> Result<impl ReplDisplay> {
+        Err::<RecordBatch, _>(Error::Unsupported("show"))
+    }
+    /// Write the settings which differ from the defaults to `path`, returns how many.
+    async fn save_settings(&self, path: &Path) -> Result<usize> {
+        Err(Error::Unsupported("save_settings"))
+    }
+    /// Apply the settings written by [`Backend::save_settings`], returns how many.
+    async fn load_settings(&mut self, path: &Path) -> Result<usize> {
+        Err(Error::Unsupported("load_settings"))
+    }
+    /// The commands in flight and the background jobs, with their states.
+    async fn jobs(&self) -> Result<impl ReplDisplay> {
+        Err::<RecordBatch, _>(Error::Unsupported("jobs"))
+    }
+    /// Run a query in the background and return the id of the job, the result is
+    /// kept as a table named after the job.
+    async fn sql_background(&self, sql: &str) -> Result<usize> {
+        Err(Error::Unsupported("sql_background"))
+    }
+    /// Wait for a background job to be done and describe how it ended.
+    async fn wait(&self, id: usize) -> Result<String> {
+        Err(Error::Unsupported("wait"))
+    }
+    async fn cancel(&self, id: usize) -> Result<()> {
+        Err(Error::Unsupported("cancel"))
+    }
+    /// The result of a finished background job.
+    async fn result(&self, id: usize) -> Result<impl ReplDisplay> {
+        Err::<RecordBatch, _>(Error::Unsupported("result"))
+    }
+    /// Remove the jobs which are done and their results, returns how many.
+    async fn clear_jobs(&self) -> Result<usize> {
+        Err(Error::Unsupported("clear_jobs"))
+    }
+    fn settings(&self) -> &ReplSettings;
+}
+
+/// The data returned by a [`Backend`], rendered as a table by the REPL or collected
+/// as record batches by the other front ends.
+#[allow(async_fn_in_trait)]
+pub trait ReplDisplay {
+    /// Render the data as a table capped to `settings.max_rows`, with the statistics
+    /// of the query.
+    async fn display_with_stats(
+        self,
+        settings: &ReplSettings,
+    ) -> anyhow::Result<(String, QueryStats)>;
+
+    async fn display(self, settings: &ReplSettings) -> anyhow::Result<String>
+    where
+        Self: Sized,
+    {
+        let (data, _) = self.display_with_stats(settings).await?;
+        Ok(data)
+    }
+
+    /// The arrow schema of the data, known before it is collected.
+    fn arrow_schema(&self) -> SchemaRef;
+
+    /// Collect the data as record batches, for the front ends other than the REPL.
+    fn batches(self) -> impl Future<Output = anyhow::Result<Vec<RecordBatch>>> + Send;
+
+    /// Collect the first `max_rows` rows, and whether some were left out. Queries
+    /// are stopped once the rows are read.
+    async fn capped_batches(
+        self,
+        max_rows: Option<usize>,
+    ) -> anyhow::Result<(Vec<RecordBatch>, bool)>
+    where
+        Self: Sized,
+    {
+        let mut batches = self.batches().await?;
+        let mut shown = 0;
+        for (i, batch) in batches.iter_mut().enumerate() {
+            match max_rows {
+                Some(max_rows) if shown + batch.num_rows() > max_rows => {
+                    *batch = batch.slice(0, max_rows - shown);
+                    batches.truncate(i + 1);
+                    return Ok((batches, true));
+                }
+                _ => shown += batch.num_rows(),
+            }
+        }
+        Ok((batches, false))
+    }
 }
 
-trait ReplDisplay {
-    async fn display(self) -> anyhow::Result<String>;
+/// Statistics of an executed query, shown as a footer when timing is on.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct QueryStats {
+    pub rows: usize,
+    /// The rows are those shown, the query was stopped at `max_rows`.
+    pub truncated: bool,
+    pub planning: Duration,
+    pub execution: Duration,
+    pub bytes_scanned: Option<usize>,
 }
 
 pub struct ReplContext {
     // 使用 channel 使得 UI 和后端解耦，即使后端换了，UI端的代码也不需要修改
     pub tx: mpsc::Sender<ReplMsg>,
+    // 用于取消前台正在执行的命令 (Ctrl-C)，后台的任务不受影响
+    foreground: Arc<Mutex<Option<Arc<Notify>>>>,
+    // 提示符返回后仍在执行的命令，完成时通过 REPL 的 printer 输出
+    printer: Arc<OnceLock<ExternalPrinter<String>>>,
+    // 输出超过一屏时使用 pager 显示
+    pager: bool,
 }
 
 pub struct ReplMsg {
     cmd: ReplCommand,
-    tx: oneshot::Sender<String>,
+    tx: oneshot::Sender<anyhow::Result<String>>,
+    cancel: Arc<Notify>,
 }
 
 pub type ReplCallbacks = CallBackMap<ReplContext, reedline_repl_rs::Error>;
@@ -46,44 +318,158 @@ pub fn get_callbacks() -> ReplCallbacks {
     let mut callbacks = ReplCallbacks::new();
     callbacks.insert("connect".to_string(), cli::connect);
     callbacks.insert("list".to_string(), cli::list);
+    callbacks.insert("cache".to_string(), cli::cache);
+    callbacks.insert("uncache".to_string(), cli::uncache);
     callbacks.insert("describe".to_string(), cli::describe);
     callbacks.insert("schema".to_string(), cli::schema);
     callbacks.insert("head".to_string(), cli::head);
+    callbacks.insert("tail".to_string(), cli::tail);
+    callbacks.insert("top".to_string(), cli::top);
+    callbacks.insert("count".to_string(), cli::count);
+    callbacks.insert("freq".to_string(), cli::freq);
+    callbacks.insert("sample".to_string(), cli::sample);
     callbacks.insert("sql".to_string(), cli::sql);
+    callbacks.insert("let".to_string(), cli::let_var);
+    callbacks.insert("view".to_string(), cli::view);
+    callbacks.insert("function".to_string(), cli::function);
+    callbacks.insert("query".to_string(), cli::query);
+    callbacks.insert("explain".to_string(), cli::explain);
+    callbacks.insert("parquet-meta".to_string(), cli::parquet_meta);
+    callbacks.insert("convert".to_string(), cli::convert);
+    callbacks.insert("set".to_string(), cli::set);
+    callbacks.insert("show".to_string(), cli::show);
+    callbacks.insert("settings".to_string(), cli::settings);
+    callbacks.insert("timing".to_string(), cli::timing);
+    callbacks.insert("jobs".to_string(), cli::jobs);
+    callbacks.insert("wait".to_string(), cli::wait);
+    callbacks.insert("cancel".to_string(), cli::cancel);
+    callbacks.insert("result".to_string(), cli::result);
     callbacks
 }
 
 impl ReplContext {
     pub fn new() -> Self {
+        Self::with_backend(DataFusionBackend::new())
+    }
+
+    fn with_backend(backend: DataFusionBackend) -> Self {
         let (tx, rx) = mpsc::unbounded::<ReplMsg>();
         let rt = Runtime::new().expect("Failed to create runtime");
-        let mut backend = DataFusionBackend::new();
+        let backend = Arc::new(RwLock::new(backend));
+        let foreground: Arc<Mutex<Option<Arc<Notify>>>> = Default::default();
+
+        let current = foreground.clone();
+        if let Err(err) = ctrlc::set_handler(move || cancel_command(&current)) {
+            eprintln!("Failed to set Ctrl-C handler: {}", err);
+        }
+
         thread::Builder::new()
             .name("ReplBackend".to_string())
             .spawn(move || {
+                // 每个命令作为独立的任务运行，查询可以并发执行。锁按命令的发送顺序获取，
+                // 所以命令总能看到之前的修改
                 while let Ok(msg) = rx.recv() {
-                    if let Err(err) = rt.block_on(async {
-                        let ret = msg.cmd.execute(&mut backend).await?;
-                        msg.tx.send(ret)?;
-                        Ok::<_, anyhow::Error>(())
-                    }) {
-                        eprintln!("Failed to process command: {}", err);
-                    }
+                    let guard = if msg.cmd.is_mutation() {
+                        BackendGuard::Write(rt.block_on(backend.clone().write_owned()))
+                    } else {
+                        BackendGuard::Read(rt.block_on(backend.clone().read_owned()))
+                    };
+                    rt.spawn(run_command(msg, guard));
                 }
             })
             .unwrap();
 
-        Self { tx }
+        Self {
+            tx,
+            foreground,
+            printer: Default::default(),
+            pager: true,
+        }
+    }
+
+    /// Cancel the command the REPL is waiting for, if any. The background jobs keep
+    /// running, they are cancelled with `cancel <id>`.
+    pub fn cancel(&self) {
+        cancel_command(&self.foreground);
+    }
+
+    /// The printer of the outputs of the commands still running when the prompt
+    /// came back, to be set to the printer of the REPL. They are printed to stdout
+    /// until it is set.
+    pub fn printer(&self) -> Arc<OnceLock<ExternalPrinter<String>>> {
+        self.printer.clone()
     }
 
-    pub fn send(&self, msg: ReplMsg, rx: oneshot::Receiver<String>) -> Option<String> {
+    /// Send a command to the backend and wait for its output, Ctrl-C cancels it
+    /// meanwhile. Queries which should not block the prompt are run with
+    /// `sql --background`, and followed with `jobs`.
+    pub fn execute(
+        &self,
+        msg: ReplMsg,
+        rx: oneshot::Receiver<anyhow::Result<String>>,
+    ) -> anyhow::Result<String> {
+        self.wait(msg, rx, None)
+            .unwrap_or_else(|_| unreachable!("waited without a limit"))
+    }
+
+    /// Run a command and show its output, or its error, paged if it is too long.
+    /// If the command is still running after a few seconds, the prompt comes back
+    /// and the output is printed when it is done, so that other commands can run
+    /// meanwhile.
+    pub fn send(
+        &self,
+        msg: ReplMsg,
+        rx: oneshot::Receiver<anyhow::Result<String>>,
+    ) -> Option<String> {
+        let command = msg.cmd.to_string();
+        let output = match self.wait(msg, rx, Some(DETACH_AFTER)) {
+            Ok(output) => output,
+            Err(rx) => {
+                let printer = self.printer.clone();
+                let running = format!(
+                    "`{}` is still running, its output is printed when it is done",
+                    command
+                );
+                thread::spawn(move || {
+                    let output = rx
+                        .recv()
+                        .unwrap_or_else(|_| Err(anyhow!("The backend stopped before answering")));
+                    print_detached(&printer, &command, output);
+                });
+                return Some(running);
+            }
+        };
+        let output = output.unwrap_or_else(|err| format!("Failed to process command: {}", err));
+        if output.is_empty() || self.pager && cli::page(&output) {
+            return None;
+        }
+        Some(output)
+    }
+
+    /// Send a command and wait for its output up to `limit`, the receiver is given
+    /// back if the command is still running.
+    fn wait(
+        &self,
+        msg: ReplMsg,
+        rx: oneshot::Receiver<anyhow::Result<String>>,
+        limit: Option<Duration>,
+    ) -> Result<anyhow::Result<String>, oneshot::Receiver<anyhow::Result<String>>> {
+        *self.foreground.lock().unwrap() = Some(msg.cancel.clone());
         if let Err(err) = self.tx.send(msg) {
             eprintln!("Repl Send Error: {}", err);
             process::exit(1);
         }
-
-        // if the oneshot receiver is dropped, return None, because never had an error on the command
-        rx.recv().ok()
+        let stopped = || anyhow!("The backend stopped before answering");
+        let ret = match limit {
+            Some(limit) => match rx.recv_timeout(limit) {
+                Ok(output) => Ok(output),
+                Err(oneshot::RecvTimeoutError::Timeout) => Err(rx),
+                Err(oneshot::RecvTimeoutError::Disconnected) => Ok(Err(stopped())),
+            },
+            None => Ok(rx.recv().unwrap_or_else(|_| Err(stopped()))),
+        };
+        *self.foreground.lock().unwrap() = None;
+        ret
     }
 }
 
@@ -101,15 +487,202 @@ impl Default for ReplContext {
     }
 }
 
+/// The lock of the backend taken for a command, in the order the commands were sent.
+enum BackendGuard {
+    Read(OwnedRwLockReadGuard<DataFusionBackend>),
+    Write(OwnedRwLockWriteGuard<DataFusionBackend>),
+}
+
+/// Run a command of the REPL. Catalog mutations hold the write lock, so they are
+/// serialized, the other commands hold the read lock and run concurrently on a
+/// snapshot of the backend, which shares the catalog but keeps the settings of the
+/// time it was sent.
+async fn run_command(msg: ReplMsg, guard: BackendGuard) {
+    let ReplMsg { cmd, tx, cancel } = msg;
+    let ret = match guard {
+        BackendGuard::Write(mut backend) => execute_cancellable(cmd, &mut backend, &cancel).await,
+        BackendGuard::Read(backend) => {
+            let mut snapshot = backend.clone();
+            execute_cancellable(cmd, &mut snapshot, &cancel).await
+        }
+    };
+    // the receiver is gone if the REPL has exited
+    let _ = tx.send(ret);
+}
+
+async fn execute_cancellable(
+    cmd: ReplCommand,
+    backend: &mut DataFusionBackend,
+    cancel: &Notify,
+) -> anyhow::Result<String> {
+    let _job = (!matches!(cmd, ReplCommand::Jobs(_)))
+        .then(|| backend.job_registry().start(cmd.to_string()));
+    let timeout = backend.settings().timeout;
+    // dropping the command future aborts the query and all its tasks
+    tokio::select! {
+        ret = cmd.execute(backend) => ret,
+        _ = cancel.notified() => Err(anyhow!("Query cancelled")),
+        _ = sleep(timeout) => {
+            let timeout = humantime::format_duration(timeout.unwrap_or_default());
+            Err(anyhow!("Query timed out after {}", timeout))
+        }
+    }
+}
+
+/// Cancel the command of `foreground`. The permit is kept if the command has not
+/// started yet, e.g. it waits for the write lock.
+fn cancel_command(foreground: &Mutex<Option<Arc<Notify>>>) {
+    if let Some(cancel) = foreground.lock().unwrap().as_ref() {
+        cancel.notify_one();
+    }
+}
+
+/// Print the output of a command the prompt stopped waiting for, line by line as
+/// the printer of the REPL expects.
+fn print_detached(
+    printer: &OnceLock<ExternalPrinter<String>>,
+    command: &str,
+    output: anyhow::Result<String>,
+) {
+    let output = match output {
+        Ok(output) if output.is_empty() => format!("`{}` is done", command),
+        Ok(output) => format!("`{}` is done:\n{}", command, output),
+        Err(err) => format!("`{}` failed: {}", command, err),
+    };
+    for line in output.lines() {
+        match printer.get() {
+            Some(printer) => {
+                let _ = printer.print(line.to_string());
+            }
+            None => println!("{}", line),
+        }
+    }
+}
+
+/// Render the result of a query, with a footer of [`QueryStats`] if timing is on.
+/// `start` is the time when the query was sent to the backend, so that the logical planning is counted.
+async fn display_timed(
+    start: Instant,
+    data: impl ReplDisplay,
+    settings: &ReplSettings,
+) -> anyhow::Result<String> {
+    let planning = start.elapsed();
+    let (mut data, mut stats) = data.display_with_stats(settings).await?;
+    if settings.timing {
+        stats.planning += planning;
+        data.push_str(&format!("\n{}", stats));
+    }
+    Ok(data)
+}
+
+/// The rows of a result as a single batch, which outlives the query.
+async fn collect(data: impl ReplDisplay) -> Result<RecordBatch> {
+    let schema = data.arrow_schema();
+    let batches = data.batches().await.map_err(Error::backend)?;
+    concat_batches(&schema, &batches).map_err(Error::backend)
+}
+
+/// A column name as a SQL identifier, kept as it is: with its case, spaces or quotes.
+pub(crate) fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+async fn sleep(timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => time::sleep(timeout).await,
+        None => future::pending().await,
+    }
+}
+
+impl fmt::Display for QueryStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = if self.rows == 1 && !self.truncated {
+            "row"
+        } else {
+            "rows"
+        };
+        let more = if self.truncated { "+" } else { "" };
+        write!(
+            f,
+            "{}{} {} in set (planning: {:.3?}, execution: {:.3?}",
+            self.rows, more, rows, self.planning, self.execution
+        )?;
+        if let Some(bytes) = self.bytes_scanned {
+            write!(f, ", scanned: {}", ByteSize(bytes as u64))?;
+        }
+        write!(f, ")")
+    }
+}
+
 impl ReplMsg {
-    pub fn new(cmd: impl Into<ReplCommand>) -> (Self, oneshot::Receiver<String>) {
+    pub fn new(cmd: impl Into<ReplCommand>) -> (Self, oneshot::Receiver<anyhow::Result<String>>) {
         let (tx, rx) = oneshot::channel();
         (
             Self {
                 cmd: cmd.into(),
                 tx,
+                cancel: Arc::new(Notify::new()),
             },
             rx,
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use arrow::datatypes::{Field, Schema};
+    use clap::Parser;
+    use datafusion::{
+        datasource::streaming::StreamingTable,
+        execution::{SendableRecordBatchStream, TaskContext},
+        physical_plan::{stream::RecordBatchStreamAdapter, streaming::PartitionStream},
+    };
+
+    use super::*;
+
+    /// A table whose scan never ends.
+    struct Pending(SchemaRef);
+
+    impl PartitionStream for Pending {
+        fn schema(&self) -> &SchemaRef {
+            &self.0
+        }
+
+        fn execute(&self, _ctx: Arc<TaskContext>) -> SendableRecordBatchStream {
+            let stream = futures::stream::pending();
+            Box::pin(RecordBatchStreamAdapter::new(self.0.clone(), stream))
+        }
+    }
+
+    #[test]
+    fn reads_should_run_while_a_slow_command_is_in_flight() {
+        let backend = DataFusionBackend::new();
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, true)]));
+        let partition = Arc::new(Pending(schema.clone()));
+        let table = StreamingTable::try_new(schema, vec![partition]).unwrap();
+        backend.register_table("pending", Arc::new(table)).unwrap();
+        let ctx = ReplContext::with_backend(backend);
+
+        let sql = SqlOpts::try_parse_from(["sql", "SELECT count(*) FROM pending"]).unwrap();
+        let (slow, slow_rx) = ReplMsg::new(sql);
+        let cancel = slow.cancel.clone();
+        // the prompt comes back while the query runs
+        let output = ctx.send(slow, slow_rx).unwrap();
+        assert!(output.ends_with("is still running, its output is printed when it is done"));
+
+        let (msg, rx) = ReplMsg::new(ListOpts::try_parse_from(["list"]).unwrap());
+        assert!(ctx.execute(msg, rx).unwrap().contains("pending"));
+        let jobs = || {
+            let (msg, rx) = ReplMsg::new(JobsOpts::try_parse_from(["jobs"]).unwrap());
+            ctx.execute(msg, rx).unwrap()
+        };
+        assert!(jobs().contains("FROM pending"));
+
+        cancel.notify_one();
+        let start = Instant::now();
+        while jobs().contains("FROM pending") {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
@@ -1,19 +1,110 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
 use reedline_repl_rs::Repl;
-use taotie::{get_callbacks, ReplCommand, ReplContext};
+use taotie::{
+    get_callbacks, serve_flight, serve_http, serve_kernel, serve_pg, FlightOpts, FunctionAction,
+    FunctionOpts, KernelOpts, PgOpts, ReplCommand, ReplContext, ReplMsg, ServeOpts, SetOpts,
+};
+use tokio::runtime::Runtime;
 
 const HISTORY_SIZE: usize = 1024;
 
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Taotie, your dataset exploration REPL",
+    args_conflicts_with_subcommands = true
+)]
+struct Args {
+    #[command(subcommand)]
+    mode: Option<Mode>,
+
+    #[command(flatten)]
+    memory: MemoryArgs,
+
+    #[arg(
+        long,
+        help = "File of the SQL functions loaded at startup, where the created ones are saved, ~/.taotie_functions by default"
+    )]
+    functions: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+struct MemoryArgs {
+    #[arg(long, help = "Memory limit of the queries of the REPL, e.g. 2GB")]
+    memory_limit: Option<String>,
+
+    #[arg(
+        long,
+        help = "How the memory limit is shared by the queries: fair or greedy"
+    )]
+    memory_pool: Option<String>,
+
+    #[arg(
+        long,
+        help = "Directory of the data spilled by the queries, the temp dir by default"
+    )]
+    spill_dir: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Mode {
+    #[command(about = "Serve the backend as a JSON / Arrow IPC HTTP API")]
+    Serve(ServeOpts),
+
+    #[command(about = "Serve the registered datasets over Arrow Flight SQL")]
+    Flight(FlightOpts),
+
+    #[command(about = "Answer Postgres wire protocol queries with the backend")]
+    Pg(PgOpts),
+
+    #[command(about = "Run as a Jupyter kernel, cells are REPL commands")]
+    Kernel(KernelOpts),
+}
+
 fn main() -> Result<()> {
+    let args = Args::parse();
+    match args.mode {
+        Some(Mode::Serve(opts)) => Runtime::new()?.block_on(serve_http(opts)),
+        Some(Mode::Flight(opts)) => Runtime::new()?.block_on(serve_flight(opts)),
+        Some(Mode::Pg(opts)) => Runtime::new()?.block_on(serve_pg(opts)),
+        Some(Mode::Kernel(opts)) => Runtime::new()?.block_on(serve_kernel(opts)),
+        None => run_repl(args.memory, args.functions),
+    }
+}
+
+fn run_repl(memory: MemoryArgs, functions: Option<String>) -> Result<()> {
     let ctx = ReplContext::new();
+    let settings = [
+        ("memory_limit", memory.memory_limit),
+        ("memory_pool", memory.memory_pool),
+        ("spill_dir", memory.spill_dir),
+    ];
+    for (key, value) in settings {
+        if let Some(value) = value {
+            let (msg, rx) = ReplMsg::new(SetOpts::new(key, value));
+            ctx.execute(msg, rx)
+                .with_context(|| format!("Invalid --{}", key.replace('_', "-")))?;
+        }
+    }
+    let load = FunctionAction::Load { file: functions };
+    let (msg, rx) = ReplMsg::new(FunctionOpts::new(load));
+    match ctx.execute(msg, rx) {
+        Ok(output) if !output.is_empty() => println!("{}", output),
+        Ok(_) => {}
+        Err(err) => eprintln!("Failed to load functions: {}", err),
+    }
+
     let callbacks = get_callbacks();
     let history_file = dirs::home_dir()
         .expect("expect home dir")
         .join(".taotie_history");
+    let printer = ctx.printer();
     let mut repl = Repl::new(ctx)
         .with_banner("Welcome to Taotie, Your dataset exploration REPL!")
         .with_history(history_file, HISTORY_SIZE)
         .with_derived::<ReplCommand>(callbacks);
+    let _ = printer.set(repl.external_printer());
 
     repl.run()?;
     Ok(())
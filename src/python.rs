@@ -0,0 +1,138 @@
+use std::future::Future;
+
+use arrow::{array::RecordBatch, datatypes::SchemaRef, pyarrow::ToPyArrow};
+use pyo3::{exceptions::PyRuntimeError, prelude::*, types::PyList};
+use tokio::runtime::Runtime;
+
+use crate::{
+    backend::DataFusionBackend,
+    cli::{verify_conn_str, ConnectOpts, HeadOpts},
+    Backend, ReplDisplay,
+};
+
+/// A taotie session for Python, the results are returned as pyarrow Tables through
+/// the Arrow C data interface, so the data is not copied.
+#[pyclass(name = "Taotie")]
+struct PyTaotie {
+    backend: DataFusionBackend,
+    rt: Runtime,
+}
+
+#[pymethods]
+impl PyTaotie {
+    #[new]
+    fn new() -> PyResult<Self> {
+        Ok(Self {
+            backend: DataFusionBackend::new(),
+            rt: Runtime::new()?,
+        })
+    }
+
+    /// Connect to a dataset and register it to table `name`.
+    #[pyo3(signature = (conn, name, table = None))]
+    fn connect(
+        &mut self,
+        py: Python<'_>,
+        conn: &str,
+        name: &str,
+        table: Option<String>,
+    ) -> PyResult<()> {
+        let opts = ConnectOpts {
+            conn: verify_conn_str(conn).map_err(PyRuntimeError::new_err)?,
+            table,
+            name: name.to_string(),
+        };
+        let (rt, backend) = (&self.rt, &mut self.backend);
+        py.allow_threads(|| rt.block_on(backend.connect(&opts)))
+            .map_err(runtime_error)
+    }
+
+    fn list(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let data = self.run(
+            py,
+            |backend| async move { collect(backend.list().await?).await },
+        )?;
+        to_table(py, data)
+    }
+
+    fn schema(&self, py: Python<'_>, name: &str) -> PyResult<PyObject> {
+        let data = self.run(py, |backend| async move {
+            collect(backend.schema(name).await?).await
+        })?;
+        to_table(py, data)
+    }
+
+    fn describe(&self, py: Python<'_>, name: &str) -> PyResult<PyObject> {
+        let data = self.run(py, |backend| async move {
+            collect(backend.describe(name).await?).await
+        })?;
+        to_table(py, data)
+    }
+
+    #[pyo3(signature = (name, n = None))]
+    fn head(&self, py: Python<'_>, name: &str, n: Option<usize>) -> PyResult<PyObject> {
+        let opts = HeadOpts {
+            name: name.to_string(),
+            n,
+        };
+        let data = self.run(py, |backend| async move {
+            collect(backend.head(&opts.name, opts.size()).await?).await
+        })?;
+        to_table(py, data)
+    }
+
+    fn sql(&self, py: Python<'_>, query: &str) -> PyResult<PyObject> {
+        let data = self.run(py, |backend| async move {
+            collect(backend.sql(query).await?).await
+        })?;
+        to_table(py, data)
+    }
+}
+
+impl PyTaotie {
+    /// Run a command of the backend without holding the GIL, so that the other
+    /// Python threads go on during the query.
+    fn run<'a, F, Fut>(&'a self, py: Python<'_>, f: F) -> PyResult<Data>
+    where
+        F: FnOnce(&'a DataFusionBackend) -> Fut + Send,
+        Fut: Future<Output = anyhow::Result<Data>>,
+    {
+        py.allow_threads(|| self.rt.block_on(f(&self.backend)))
+            .map_err(runtime_error)
+    }
+}
+
+/// The schema and the record batches of a result.
+type Data = (SchemaRef, Vec<RecordBatch>);
+
+async fn collect(data: impl ReplDisplay) -> anyhow::Result<Data> {
+    let schema = data.arrow_schema();
+    let batches = data.batches().await?;
+    let schema = batches.first().map_or(schema, |batch| batch.schema());
+    Ok((schema, batches))
+}
+
+/// Build a `pyarrow.Table` from the record batches.
+fn to_table(py: Python<'_>, (schema, batches): Data) -> PyResult<PyObject> {
+    let batches = batches
+        .iter()
+        .map(|batch| batch.to_pyarrow(py))
+        .collect::<PyResult<Vec<_>>>()?;
+
+    let table = py.import_bound("pyarrow")?.getattr("Table")?;
+    let table = table.call_method1(
+        "from_batches",
+        (PyList::new_bound(py, batches), schema.to_pyarrow(py)?),
+    )?;
+    Ok(table.unbind())
+}
+
+fn runtime_error(err: impl std::fmt::Display) -> PyErr {
+    PyRuntimeError::new_err(err.to_string())
+}
+
+#[pymodule]
+fn taotie(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyTaotie>()?;
+    Ok(())
+}
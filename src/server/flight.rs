@@ -0,0 +1,516 @@
+// tonic::Status is large, but it is what the Flight SQL service returns everywhere
+#![allow(clippy::result_large_err)]
+
+use std::{
+    fmt,
+    net::SocketAddr,
+    ops::Deref,
+    pin::Pin,
+    sync::{Arc, OnceLock},
+};
+
+use arrow::{
+    array::{AsArray, RecordBatch, StringArray},
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    ipc::writer::IpcWriteOptions,
+};
+use arrow_flight::{
+    encode::FlightDataEncoderBuilder,
+    error::FlightError,
+    flight_service_server::FlightServiceServer,
+    sql::{
+        metadata::{SqlInfoData, SqlInfoDataBuilder},
+        server::FlightSqlService,
+        ActionClosePreparedStatementRequest, ActionCreatePreparedStatementRequest,
+        ActionCreatePreparedStatementResult, CommandGetCatalogs, CommandGetDbSchemas,
+        CommandGetSqlInfo, CommandGetTableTypes, CommandGetTables, CommandPreparedStatementQuery,
+        CommandStatementQuery, ProstMessageExt, SqlInfo, TicketStatementQuery,
+    },
+    Action, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest,
+    HandshakeResponse, IpcMessage, SchemaAsIpc, Ticket,
+};
+use clap::Args;
+use datafusion::{
+    execution::context::SQLOptions,
+    physical_plan::SendableRecordBatchStream,
+    prelude::{DataFrame, SessionContext},
+    sql::TableReference,
+};
+use futures::{stream, Stream, TryStreamExt};
+use prost::Message;
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+
+use crate::{backend::DataFusionBackend, cli::parse_dataset, cli::ConnectOpts, Backend};
+
+type FlightDataStream = Pin<Box<dyn Stream<Item = Result<FlightData, Status>> + Send>>;
+
+#[derive(Debug, Clone, Args)]
+pub struct FlightOpts {
+    #[arg(long, default_value = "127.0.0.1", help = "The address to listen on")]
+    pub host: String,
+
+    #[arg(short, long, default_value_t = 50051, help = "The port to listen on")]
+    pub port: u16,
+
+    #[arg(
+        short,
+        long = "dataset",
+        value_parser = parse_dataset,
+        help = "Dataset to register before serving, in the form of name=conn, e.g. users=assets/users.ndjson"
+    )]
+    pub datasets: Vec<ConnectOpts>,
+}
+
+/// Serve the `SessionContext` of the backend over Arrow Flight SQL. Statement and
+/// prepared statement handles are the SQL text itself, so the server keeps no state
+/// other than the registered datasets.
+#[derive(Clone)]
+pub struct FlightSqlServer {
+    ctx: SessionContext,
+}
+
+pub async fn serve_flight(opts: FlightOpts) -> anyhow::Result<()> {
+    let mut backend = DataFusionBackend::new();
+    for dataset in &opts.datasets {
+        backend.connect(dataset).await?;
+        println!("Connected to dataset: {}", dataset.name);
+    }
+
+    let addr: SocketAddr = format!("{}:{}", opts.host, opts.port).parse()?;
+    println!("Taotie Flight SQL server listening on grpc://{}", addr);
+    let service = FlightSqlServer {
+        ctx: backend.deref().clone(),
+    };
+    Server::builder()
+        .add_service(FlightServiceServer::new(service))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
+
+#[tonic::async_trait]
+impl FlightSqlService for FlightSqlServer {
+    type FlightService = FlightSqlServer;
+
+    // no authentication, the server is meant to listen on localhost
+    async fn do_handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<
+        Response<Pin<Box<dyn Stream<Item = Result<HandshakeResponse, Status>> + Send>>>,
+        Status,
+    > {
+        let resp = HandshakeResponse::default();
+        Ok(Response::new(Box::pin(stream::iter(vec![Ok(resp)]))))
+    }
+
+    async fn get_flight_info_statement(
+        &self,
+        query: CommandStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = self.plan_schema(&query.query).await?;
+        let ticket = TicketStatementQuery {
+            statement_handle: query.query.into_bytes().into(),
+        };
+        flight_info(&schema, ticket.as_any().encode_to_vec(), request)
+    }
+
+    async fn get_flight_info_prepared_statement(
+        &self,
+        query: CommandPreparedStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let sql = handle_to_sql(&query.prepared_statement_handle)?;
+        let schema = self.plan_schema(&sql).await?;
+        flight_info(&schema, query.as_any().encode_to_vec(), request)
+    }
+
+    async fn get_flight_info_catalogs(
+        &self,
+        query: CommandGetCatalogs,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder().schema();
+        flight_info(&schema, query.as_any().encode_to_vec(), request)
+    }
+
+    async fn get_flight_info_schemas(
+        &self,
+        query: CommandGetDbSchemas,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder().schema();
+        flight_info(&schema, query.as_any().encode_to_vec(), request)
+    }
+
+    async fn get_flight_info_tables(
+        &self,
+        query: CommandGetTables,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder().schema();
+        flight_info(&schema, query.as_any().encode_to_vec(), request)
+    }
+
+    async fn get_flight_info_table_types(
+        &self,
+        query: CommandGetTableTypes,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        flight_info(
+            &table_types_schema(),
+            query.as_any().encode_to_vec(),
+            request,
+        )
+    }
+
+    async fn get_flight_info_sql_info(
+        &self,
+        query: CommandGetSqlInfo,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder(sql_info()).schema();
+        flight_info(&schema, query.as_any().encode_to_vec(), request)
+    }
+
+    async fn do_get_statement(
+        &self,
+        ticket: TicketStatementQuery,
+        _request: Request<Ticket>,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        let sql = handle_to_sql(&ticket.statement_handle)?;
+        self.execute(&sql).await
+    }
+
+    async fn do_get_prepared_statement(
+        &self,
+        query: CommandPreparedStatementQuery,
+        _request: Request<Ticket>,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        let sql = handle_to_sql(&query.prepared_statement_handle)?;
+        self.execute(&sql).await
+    }
+
+    async fn do_get_catalogs(
+        &self,
+        query: CommandGetCatalogs,
+        _request: Request<Ticket>,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        let rows = self
+            .information_schema("SELECT DISTINCT table_catalog FROM information_schema.tables")
+            .await?;
+        let mut builder = query.into_builder();
+        for row in rows {
+            builder.append(&row[0]);
+        }
+        let schema = builder.schema();
+        batch_stream(schema, builder.build())
+    }
+
+    async fn do_get_schemas(
+        &self,
+        query: CommandGetDbSchemas,
+        _request: Request<Ticket>,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        let sql = "SELECT DISTINCT table_catalog, table_schema FROM information_schema.tables";
+        let rows = self.information_schema(sql).await?;
+        let mut builder = query.into_builder();
+        for row in rows {
+            builder.append(&row[0], &row[1]);
+        }
+        let schema = builder.schema();
+        batch_stream(schema, builder.build())
+    }
+
+    async fn do_get_tables(
+        &self,
+        query: CommandGetTables,
+        _request: Request<Ticket>,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        let sql = "SELECT table_catalog, table_schema, table_name, table_type FROM information_schema.tables";
+        let rows = self.information_schema(sql).await?;
+        let include_schema = query.include_schema;
+        let mut builder = query.into_builder();
+        for row in rows {
+            let schema = if include_schema {
+                let table = TableReference::full(row[0].as_str(), row[1].as_str(), row[2].as_str());
+                let provider = self.ctx.table_provider(table).await.map_err(status)?;
+                provider.schema()
+            } else {
+                Arc::new(Schema::empty())
+            };
+            builder.append(&row[0], &row[1], &row[2], &row[3], &schema)?;
+        }
+        let schema = builder.schema();
+        batch_stream(schema, builder.build())
+    }
+
+    async fn do_get_table_types(
+        &self,
+        _query: CommandGetTableTypes,
+        _request: Request<Ticket>,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        let rows = self
+            .information_schema("SELECT DISTINCT table_type FROM information_schema.tables")
+            .await?;
+        let types = StringArray::from_iter_values(rows.into_iter().map(|mut row| row.remove(0)));
+        let schema = table_types_schema();
+        let batch = RecordBatch::try_new(schema.clone(), vec![Arc::new(types)]);
+        batch_stream(schema, batch.map_err(FlightError::from))
+    }
+
+    async fn do_get_sql_info(
+        &self,
+        query: CommandGetSqlInfo,
+        _request: Request<Ticket>,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        let builder = query.into_builder(sql_info());
+        let schema = builder.schema();
+        batch_stream(schema, builder.build())
+    }
+
+    async fn do_action_create_prepared_statement(
+        &self,
+        query: ActionCreatePreparedStatementRequest,
+        _request: Request<Action>,
+    ) -> Result<ActionCreatePreparedStatementResult, Status> {
+        let schema = self.plan_schema(&query.query).await?;
+        let IpcMessage(dataset_schema) = SchemaAsIpc::new(&schema, &IpcWriteOptions::default())
+            .try_into()
+            .map_err(FlightError::from)?;
+        Ok(ActionCreatePreparedStatementResult {
+            prepared_statement_handle: query.query.into_bytes().into(),
+            dataset_schema,
+            parameter_schema: Default::default(),
+        })
+    }
+
+    async fn do_action_close_prepared_statement(
+        &self,
+        _query: ActionClosePreparedStatementRequest,
+        _request: Request<Action>,
+    ) -> Result<(), Status> {
+        Ok(())
+    }
+
+    async fn register_sql_info(&self, _id: i32, _result: &SqlInfo) {}
+}
+
+impl FlightSqlServer {
+    /// Plan a query of the clients, which are not allowed to change the session nor
+    /// to write files, as the server is advertised as read-only.
+    async fn plan(&self, sql: &str) -> Result<DataFrame, Status> {
+        let options = SQLOptions::new()
+            .with_allow_ddl(false)
+            .with_allow_dml(false)
+            .with_allow_statements(false);
+        self.ctx
+            .sql_with_options(sql, options)
+            .await
+            .map_err(status)
+    }
+
+    async fn plan_schema(&self, sql: &str) -> Result<Schema, Status> {
+        let df = self.plan(sql).await?;
+        Ok(df.schema().as_arrow().clone())
+    }
+
+    async fn execute(&self, sql: &str) -> Result<Response<FlightDataStream>, Status> {
+        let df = self.plan(sql).await?;
+        let schema = Arc::new(df.schema().as_arrow().clone());
+        let stream: SendableRecordBatchStream = df.execute_stream().await.map_err(status)?;
+        let stream = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(stream.map_err(|e| FlightError::ExternalError(Box::new(e))))
+            .map_err(Status::from);
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    /// Run a query against information_schema, the same as `list` does, and
+    /// return the rows as strings.
+    async fn information_schema(&self, sql: &str) -> Result<Vec<Vec<String>>, Status> {
+        let batches = self.plan(sql).await?.collect().await.map_err(status)?;
+        let mut rows = Vec::new();
+        for batch in batches {
+            for i in 0..batch.num_rows() {
+                let row = batch
+                    .columns()
+                    .iter()
+                    .map(|col| col.as_string::<i32>().value(i).to_string())
+                    .collect();
+                rows.push(row);
+            }
+        }
+        Ok(rows)
+    }
+}
+
+fn flight_info(
+    schema: &Schema,
+    ticket: Vec<u8>,
+    request: Request<FlightDescriptor>,
+) -> Result<Response<FlightInfo>, Status> {
+    let endpoint = FlightEndpoint::new().with_ticket(Ticket::new(ticket));
+    let info = FlightInfo::new()
+        .try_with_schema(schema)
+        .map_err(FlightError::from)?
+        .with_endpoint(endpoint)
+        .with_descriptor(request.into_inner());
+    Ok(Response::new(info))
+}
+
+fn batch_stream(
+    schema: SchemaRef,
+    batch: Result<RecordBatch, impl Into<FlightError>>,
+) -> Result<Response<FlightDataStream>, Status> {
+    let batch = batch.map_err(Into::into);
+    let stream = FlightDataEncoderBuilder::new()
+        .with_schema(schema)
+        .build(stream::once(async { batch }))
+        .map_err(Status::from);
+    Ok(Response::new(Box::pin(stream)))
+}
+
+fn handle_to_sql(handle: &[u8]) -> Result<String, Status> {
+    String::from_utf8(handle.to_vec())
+        .map_err(|e| Status::invalid_argument(format!("Invalid statement handle: {}", e)))
+}
+
+fn table_types_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![Field::new(
+        "table_type",
+        DataType::Utf8,
+        false,
+    )]))
+}
+
+fn sql_info() -> &'static SqlInfoData {
+    static SQL_INFO: OnceLock<SqlInfoData> = OnceLock::new();
+    SQL_INFO.get_or_init(|| {
+        let mut builder = SqlInfoDataBuilder::new();
+        builder.append(SqlInfo::FlightSqlServerName, "Taotie");
+        builder.append(SqlInfo::FlightSqlServerVersion, env!("CARGO_PKG_VERSION"));
+        builder.append(SqlInfo::FlightSqlServerArrowVersion, "1.3");
+        // only queries are served, there are no updates nor ingestion
+        builder.append(SqlInfo::FlightSqlServerReadOnly, true);
+        builder.build().expect("valid sql info")
+    })
+}
+
+fn status(err: impl fmt::Display) -> Status {
+    Status::invalid_argument(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{array::UnionArray, datatypes::Int64Type};
+    use arrow_flight::sql::{client::FlightSqlServiceClient, SqlInfo};
+    use tokio::net::TcpListener;
+    use tonic::transport::Endpoint;
+
+    use super::*;
+
+    async fn start() -> FlightSqlServiceClient<tonic::transport::Channel> {
+        let mut backend = DataFusionBackend::new();
+        let dataset = parse_dataset("users=assets/users.ndjson").unwrap();
+        backend.connect(&dataset).await.unwrap();
+        let service = FlightSqlServer {
+            ctx: backend.deref().clone(),
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = stream::unfold(listener, |listener| async {
+            let conn = listener.accept().await.map(|(stream, _)| stream);
+            Some((conn, listener))
+        });
+        tokio::spawn(
+            Server::builder()
+                .add_service(FlightServiceServer::new(service))
+                .serve_with_incoming(incoming),
+        );
+        let channel = Endpoint::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        FlightSqlServiceClient::new(channel)
+    }
+
+    #[tokio::test]
+    async fn flight_sql_client_should_execute_queries() {
+        let mut client = start().await;
+        let sql = "SELECT gender, count(*) AS n FROM users GROUP BY gender ORDER BY gender";
+        let info = client.execute(sql.to_string(), None).await.unwrap();
+        let schema = info.clone().try_decode_schema().unwrap();
+        assert_eq!(schema.field(0).name(), "gender");
+        assert_eq!(schema.field(1).name(), "n");
+
+        let mut batches = Vec::new();
+        for endpoint in info.endpoint {
+            let ticket = endpoint.ticket.unwrap();
+            let stream = client.do_get(ticket).await.unwrap();
+            batches.extend(stream.try_collect::<Vec<_>>().await.unwrap());
+        }
+        let batch = arrow::compute::concat_batches(&batches[0].schema(), &batches).unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        assert_eq!(batch.column(0).as_string::<i32>().value(0), "female");
+        assert_eq!(batch.column(1).as_primitive::<Int64Type>().value(0), 36);
+    }
+
+    #[tokio::test]
+    async fn flight_sql_client_should_see_a_read_only_server() {
+        let mut client = start().await;
+        let info = client
+            .get_sql_info(vec![SqlInfo::FlightSqlServerReadOnly])
+            .await
+            .unwrap();
+        let ticket = info.endpoint[0].ticket.clone().unwrap();
+        let batches: Vec<_> = client
+            .do_get(ticket)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(batches[0].num_rows(), 1);
+        let value = batches[0]
+            .column(1)
+            .as_any()
+            .downcast_ref::<UnionArray>()
+            .unwrap()
+            .value(0);
+        assert!(value.as_boolean().value(0));
+
+        let err = client.execute("SELECT * FROM nope".to_string(), None).await;
+        assert!(err.is_err());
+    }
+
+    #[tokio::test]
+    async fn flight_sql_client_should_not_change_the_session() {
+        let mut client = start().await;
+        let path = std::env::temp_dir().join("taotie_flight_copy.csv");
+        let statements = [
+            "CREATE VIEW females AS SELECT * FROM users WHERE gender = 'female'".to_string(),
+            "DROP TABLE users".to_string(),
+            format!("COPY users TO '{}' STORED AS CSV", path.display()),
+            "INSERT INTO users (email) VALUES ('a@example.com')".to_string(),
+            "SET datafusion.execution.batch_size = 10".to_string(),
+        ];
+        for sql in statements {
+            let err = client.execute(sql.clone(), None).await.unwrap_err();
+            assert!(
+                err.to_string().contains("not supported"),
+                "{}: {}",
+                sql,
+                err
+            );
+        }
+        assert!(!path.exists());
+        let info = client
+            .execute("SELECT count(*) FROM users".to_string(), None)
+            .await;
+        assert!(info.is_ok());
+    }
+}
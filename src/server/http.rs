@@ -0,0 +1,356 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use arrow::{datatypes::SchemaRef, ipc::writer::StreamWriter, json::ArrayWriter};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use clap::Args;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::{net::TcpListener, sync::RwLock};
+
+use crate::{
+    backend::DataFusionBackend,
+    cli::{verify_conn_str, ConnectOpts, HeadOpts},
+    Backend, ReplDisplay,
+};
+
+const ARROW_STREAM: &str = "application/vnd.apache.arrow.stream";
+
+#[derive(Debug, Clone, Args)]
+pub struct ServeOpts {
+    #[arg(long, default_value = "127.0.0.1", help = "The address to listen on")]
+    pub host: String,
+
+    #[arg(short, long, default_value_t = 8080, help = "The port to listen on")]
+    pub port: u16,
+}
+
+/// The backend is shared by all the requests, so datasets connected by one client
+/// are visible to the others.
+type AppState = Arc<RwLock<DataFusionBackend>>;
+
+#[derive(Debug, Deserialize)]
+struct ConnectReq {
+    conn: String,
+    name: String,
+    table: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SqlReq {
+    query: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HeadParams {
+    n: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FormatParams {
+    format: Option<String>,
+}
+
+struct AppError(StatusCode, anyhow::Error);
+
+pub async fn serve_http(opts: ServeOpts) -> anyhow::Result<()> {
+    let addr: SocketAddr = format!("{}:{}", opts.host, opts.port).parse()?;
+    let listener = TcpListener::bind(addr).await?;
+    println!("Taotie HTTP server listening on http://{}", addr);
+    axum::serve(listener, router(DataFusionBackend::new())).await?;
+    Ok(())
+}
+
+pub fn router(backend: DataFusionBackend) -> Router {
+    let state: AppState = Arc::new(RwLock::new(backend));
+    Router::new()
+        .route("/datasets", get(list).post(connect))
+        .route("/datasets/:name/schema", get(schema))
+        .route("/datasets/:name/head", get(head))
+        .route("/datasets/:name/describe", get(describe))
+        .route("/sql", post(sql))
+        .with_state(state)
+}
+
+async fn connect(
+    State(state): State<AppState>,
+    Json(req): Json<ConnectReq>,
+) -> Result<Response, AppError> {
+    let conn = verify_conn_str(&req.conn).map_err(|e| bad_request(anyhow::anyhow!(e)))?;
+    let opts = ConnectOpts {
+        conn,
+        table: req.table,
+        name: req.name,
+    };
+    state
+        .write()
+        .await
+        .connect(&opts)
+        .await
+        .map_err(bad_request)?;
+    let body = json!({ "message": format!("Connected to dataset: {}", opts.name) });
+    Ok(Json(body).into_response())
+}
+
+async fn list(
+    State(state): State<AppState>,
+    Query(params): Query<FormatParams>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let backend = state.read().await;
+    let data = backend.list().await.map_err(bad_request)?;
+    render(data, &params, &headers).await
+}
+
+async fn schema(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<FormatParams>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let backend = state.read().await;
+    let data = backend.schema(&name).await.map_err(bad_request)?;
+    render(data, &params, &headers).await
+}
+
+async fn head(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(head): Query<HeadParams>,
+    Query(params): Query<FormatParams>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let backend = state.read().await;
+    let opts = HeadOpts { name, n: head.n };
+    let data = backend
+        .head(&opts.name, opts.size())
+        .await
+        .map_err(bad_request)?;
+    render(data, &params, &headers).await
+}
+
+async fn describe(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<FormatParams>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let backend = state.read().await;
+    let data = backend.describe(&name).await.map_err(bad_request)?;
+    render(data, &params, &headers).await
+}
+
+async fn sql(
+    State(state): State<AppState>,
+    Query(params): Query<FormatParams>,
+    headers: HeaderMap,
+    Json(req): Json<SqlReq>,
+) -> Result<Response, AppError> {
+    let backend = state.read().await;
+    let data = backend.sql(&req.query).await.map_err(bad_request)?;
+    render(data, &params, &headers).await
+}
+
+/// Render the data as Arrow IPC stream if asked by `?format=arrow` or the
+/// `Accept` header, otherwise as a JSON array of rows.
+async fn render(
+    data: impl ReplDisplay,
+    params: &FormatParams,
+    headers: &HeaderMap,
+) -> Result<Response, AppError> {
+    let accept = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let arrow = match params.format.as_deref() {
+        Some("arrow") => true,
+        Some("json") | None => accept.contains(ARROW_STREAM),
+        Some(v) => {
+            let err = anyhow::anyhow!("Invalid format: {}, expect json or arrow", v);
+            return Err(bad_request(err));
+        }
+    };
+    // the query is planned, the errors from now on are failures of its execution
+    let schema = data.arrow_schema();
+    let batches = data.batches().await?;
+
+    if arrow {
+        // the schema of the query is kept for an empty result
+        let schema: SchemaRef = batches.first().map_or(schema, |batch| batch.schema());
+        let mut writer = StreamWriter::try_new(Vec::new(), &schema)?;
+        for batch in &batches {
+            writer.write(batch)?;
+        }
+        let data = writer.into_inner()?;
+        Ok(([(header::CONTENT_TYPE, ARROW_STREAM)], data).into_response())
+    } else {
+        let mut writer = ArrayWriter::new(Vec::new());
+        writer.write_batches(&batches.iter().collect::<Vec<_>>())?;
+        writer.finish()?;
+        let data = writer.into_inner();
+        // an empty result is written as nothing by the json writer
+        let data = if data.is_empty() {
+            b"[]".to_vec()
+        } else {
+            data
+        };
+        Ok(([(header::CONTENT_TYPE, "application/json")], data).into_response())
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let body = json!({ "error": self.1.to_string() });
+        (self.0, Json(body)).into_response()
+    }
+}
+
+/// The errors of the requests themselves, like an invalid query or an unknown dataset.
+fn bad_request(err: impl Into<anyhow::Error>) -> AppError {
+    AppError(StatusCode::BAD_REQUEST, err.into())
+}
+
+impl<E> From<E> for AppError
+where
+    E: Into<anyhow::Error>,
+{
+    fn from(err: E) -> Self {
+        Self(StatusCode::INTERNAL_SERVER_ERROR, err.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use arrow::ipc::reader::StreamReader;
+    use serde_json::Value;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+
+    struct Reply {
+        status: u16,
+        content_type: String,
+        body: Vec<u8>,
+    }
+
+    /// A bare HTTP/1.1 client, the server closes the connection after the response.
+    async fn request(addr: SocketAddr, method: &str, path: &str, body: Option<Value>) -> Reply {
+        let body = body.map(|b| b.to_string()).unwrap_or_default();
+        let req = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            addr,
+            body.len(),
+            body
+        );
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(req.as_bytes()).await.unwrap();
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).await.unwrap();
+
+        let split = raw.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let head = String::from_utf8(raw[..split].to_vec()).unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        let content_type = head
+            .lines()
+            .find_map(|line| line.strip_prefix("content-type: "))
+            .unwrap_or_default()
+            .to_string();
+        Reply {
+            status,
+            content_type,
+            body: raw[split + 4..].to_vec(),
+        }
+    }
+
+    fn json(reply: &Reply) -> Value {
+        serde_json::from_slice(&reply.body).unwrap()
+    }
+
+    async fn start() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router(DataFusionBackend::new()))
+                .await
+                .unwrap()
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn http_api_should_connect_and_query() {
+        let addr = start().await;
+        let conn = json!({ "conn": "assets/users.ndjson", "name": "users" });
+        let reply = request(addr, "POST", "/datasets", Some(conn)).await;
+        assert_eq!(reply.status, 200);
+        assert_eq!(json(&reply)["message"], "Connected to dataset: users");
+
+        let query = json!({ "query": "SELECT gender, count(*) AS n FROM users GROUP BY gender ORDER BY gender" });
+        let reply = request(addr, "POST", "/sql", Some(query.clone())).await;
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.content_type, "application/json");
+        let rows = json(&reply);
+        assert_eq!(rows[0], json!({ "gender": "female", "n": 36 }));
+        assert_eq!(rows.as_array().unwrap().len(), 3);
+
+        let reply = request(addr, "POST", "/sql?format=arrow", Some(query)).await;
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.content_type, ARROW_STREAM);
+        let reader = StreamReader::try_new(Cursor::new(reply.body), None).unwrap();
+        let batches: Vec<_> = reader.map(|batch| batch.unwrap()).collect();
+        let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+        assert_eq!(rows, 3);
+
+        let reply = request(addr, "GET", "/datasets/users/head?n=2", None).await;
+        assert_eq!(json(&reply).as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn http_api_should_keep_the_schema_of_empty_arrow_results() {
+        let addr = start().await;
+        let conn = json!({ "conn": "assets/users.ndjson", "name": "users" });
+        request(addr, "POST", "/datasets", Some(conn)).await;
+
+        let query = json!({ "query": "SELECT name, gender FROM users WHERE false" });
+        let reply = request(addr, "POST", "/sql?format=arrow", Some(query)).await;
+        assert_eq!(reply.status, 200);
+        let reader = StreamReader::try_new(Cursor::new(reply.body), None).unwrap();
+        let schema = reader.schema();
+        let names: Vec<_> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(names, ["name", "gender"]);
+        assert_eq!(reader.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn http_api_should_report_errors() {
+        let addr = start().await;
+        let conn = json!({ "conn": "assets/nope.xlsx", "name": "nope" });
+        let reply = request(addr, "POST", "/datasets", Some(conn)).await;
+        assert_eq!(reply.status, 400);
+
+        let reply = request(addr, "GET", "/datasets/nope/schema", None).await;
+        assert_eq!(reply.status, 400);
+        assert!(json(&reply)["error"].as_str().unwrap().contains("nope"));
+
+        let query = json!({ "query": "SELECT 1" });
+        let reply = request(addr, "POST", "/sql?format=xml", Some(query)).await;
+        assert_eq!(reply.status, 400);
+
+        // planned but failing when executed
+        let query = json!({ "query": "SELECT arrow_cast('x', 'Int64') + 1 FROM (VALUES (1)) AS t(a) WHERE a > 0" });
+        let reply = request(addr, "POST", "/sql", Some(query)).await;
+        assert_eq!(reply.status, 500);
+    }
+}
@@ -0,0 +1,812 @@
+use std::{fs, path::PathBuf, sync::Arc};
+
+use anyhow::{anyhow, bail, Context};
+use arrow::{
+    array::RecordBatch,
+    compute::concat_batches,
+    util::display::{ArrayFormatter, FormatOptions},
+};
+use bytes::Bytes;
+use clap::{Args, CommandFactory, Parser};
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::sync::{Mutex, Notify};
+use zeromq::{PubSocket, RepSocket, RouterSocket, Socket, SocketRecv, SocketSend, ZmqMessage};
+
+use crate::{
+    backend::DataFusionBackend,
+    cli::{ReplCommand, ReplSettings},
+    Backend, CmdExecutor, ReplDisplay, MORE_ROWS,
+};
+
+const PROTOCOL_VERSION: &str = "5.3";
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+
+#[derive(Debug, Clone, Args)]
+pub struct KernelOpts {
+    #[arg(
+        short = 'f',
+        long,
+        help = "The connection file given by Jupyter, with the ports and the signing key"
+    )]
+    pub connection_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Install the kernel spec so that Jupyter can start taotie"
+    )]
+    pub install: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConnectionInfo {
+    transport: String,
+    ip: String,
+    key: String,
+    signature_scheme: String,
+    shell_port: u16,
+    iopub_port: u16,
+    stdin_port: u16,
+    control_port: u16,
+    hb_port: u16,
+}
+
+/// A message of the Jupyter messaging protocol, see
+/// https://jupyter-client.readthedocs.io/en/stable/messaging.html
+#[derive(Debug, Clone)]
+struct Message {
+    identities: Vec<Bytes>,
+    header: Value,
+    parent_header: Value,
+    metadata: Value,
+    content: Value,
+}
+
+/// Signs and verifies the messages with the key of the connection file.
+struct Signer {
+    key: Option<Hmac<Sha256>>,
+}
+
+/// The kernel state shared by the sockets, the backend is only used by the shell
+/// socket but an execution can be interrupted from the control socket.
+struct Kernel {
+    signer: Signer,
+    session: String,
+    iopub: Mutex<PubSocket>,
+    interrupt: Notify,
+    shutdown: Notify,
+}
+
+struct Shell {
+    kernel: Arc<Kernel>,
+    backend: DataFusionBackend,
+    execution_count: u64,
+}
+
+/// The output of a REPL command, commands returning data are also shown as HTML tables.
+enum CellOutput {
+    Text(String),
+    Table { text: String, html: String },
+}
+
+pub async fn serve_kernel(opts: KernelOpts) -> anyhow::Result<()> {
+    if opts.install {
+        return install_kernel_spec();
+    }
+    let Some(path) = opts.connection_file else {
+        bail!("A connection file is required, use --install to register the kernel to Jupyter");
+    };
+    let info: ConnectionInfo = serde_json::from_str(
+        &fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read connection file {}", path.display()))?,
+    )?;
+    if !info.key.is_empty() && info.signature_scheme != "hmac-sha256" {
+        bail!("Unsupported signature scheme: {}", info.signature_scheme);
+    }
+    let endpoint = |port: u16| format!("{}://{}:{}", info.transport, info.ip, port);
+
+    let mut shell_socket = RouterSocket::new();
+    shell_socket.bind(&endpoint(info.shell_port)).await?;
+    let mut control_socket = RouterSocket::new();
+    control_socket.bind(&endpoint(info.control_port)).await?;
+    let mut iopub = PubSocket::new();
+    iopub.bind(&endpoint(info.iopub_port)).await?;
+    // input requests are not supported, the socket is only bound so that the port is taken
+    let mut stdin = RouterSocket::new();
+    stdin.bind(&endpoint(info.stdin_port)).await?;
+    let mut heartbeat = RepSocket::new();
+    heartbeat.bind(&endpoint(info.hb_port)).await?;
+
+    let kernel = Arc::new(Kernel {
+        signer: Signer::new(&info.key),
+        session: uuid::Uuid::new_v4().to_string(),
+        iopub: Mutex::new(iopub),
+        interrupt: Notify::new(),
+        shutdown: Notify::new(),
+    });
+    kernel.publish_status("starting", None).await?;
+
+    tokio::spawn(async move {
+        while let Ok(msg) = heartbeat.recv().await {
+            if heartbeat.send(msg).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let control = kernel.clone();
+    tokio::spawn(async move {
+        if let Err(err) = control.serve_control(control_socket).await {
+            eprintln!("Kernel control error: {}", err);
+        }
+    });
+
+    let mut shell = Shell {
+        kernel: kernel.clone(),
+        backend: DataFusionBackend::new(),
+        execution_count: 0,
+    };
+    tokio::select! {
+        ret = shell.serve(shell_socket) => ret,
+        _ = kernel.shutdown.notified() => Ok(()),
+    }
+}
+
+/// Write the kernel spec to the user data dir of Jupyter, e.g. ~/.local/share/jupyter/kernels/taotie.
+fn install_kernel_spec() -> anyhow::Result<()> {
+    let dir = dirs::data_dir()
+        .ok_or_else(|| anyhow!("Failed to find the user data dir"))?
+        .join("jupyter")
+        .join("kernels")
+        .join("taotie");
+    fs::create_dir_all(&dir)?;
+    let exe = std::env::current_exe()?;
+    let spec = json!({
+        "argv": [exe, "kernel", "-f", "{connection_file}"],
+        "display_name": "Taotie",
+        "language": "taotie",
+        "interrupt_mode": "message",
+    });
+    fs::write(
+        dir.join("kernel.json"),
+        serde_json::to_string_pretty(&spec)?,
+    )?;
+    println!("Installed Taotie kernel spec to {}", dir.display());
+    Ok(())
+}
+
+impl Kernel {
+    async fn serve_control(&self, mut socket: RouterSocket) -> anyhow::Result<()> {
+        loop {
+            let Some(msg) = self.recv(&mut socket).await? else {
+                continue;
+            };
+            match msg.msg_type() {
+                "shutdown_request" => {
+                    let content = json!({ "status": "ok", "restart": msg.content["restart"] });
+                    self.reply(&mut socket, &msg, "shutdown_reply", content)
+                        .await?;
+                    self.shutdown.notify_one();
+                    return Ok(());
+                }
+                "interrupt_request" => {
+                    self.interrupt.notify_waiters();
+                    self.reply(
+                        &mut socket,
+                        &msg,
+                        "interrupt_reply",
+                        json!({ "status": "ok" }),
+                    )
+                    .await?;
+                }
+                "kernel_info_request" => {
+                    self.reply(&mut socket, &msg, "kernel_info_reply", kernel_info())
+                        .await?;
+                }
+                msg_type => eprintln!("Unsupported control message: {}", msg_type),
+            }
+        }
+    }
+
+    async fn recv(&self, socket: &mut impl SocketRecv) -> anyhow::Result<Option<Message>> {
+        let frames = socket.recv().await?.into_vec();
+        match Message::parse(frames, &self.signer) {
+            Ok(msg) => Ok(Some(msg)),
+            Err(err) => {
+                eprintln!("Invalid message: {}", err);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn reply(
+        &self,
+        socket: &mut impl SocketSend,
+        parent: &Message,
+        msg_type: &str,
+        content: Value,
+    ) -> anyhow::Result<()> {
+        let msg = self.message(Some(parent), msg_type, content);
+        socket.send(msg.to_zmq(&self.signer)?).await?;
+        Ok(())
+    }
+
+    async fn publish(
+        &self,
+        parent: Option<&Message>,
+        msg_type: &str,
+        content: Value,
+    ) -> anyhow::Result<()> {
+        let mut msg = self.message(parent, msg_type, content);
+        msg.identities = vec![Bytes::from(msg_type.to_string())];
+        self.iopub
+            .lock()
+            .await
+            .send(msg.to_zmq(&self.signer)?)
+            .await?;
+        Ok(())
+    }
+
+    async fn publish_status(&self, state: &str, parent: Option<&Message>) -> anyhow::Result<()> {
+        self.publish(parent, "status", json!({ "execution_state": state }))
+            .await
+    }
+
+    fn message(&self, parent: Option<&Message>, msg_type: &str, content: Value) -> Message {
+        let header = json!({
+            "msg_id": uuid::Uuid::new_v4().to_string(),
+            "session": self.session,
+            "username": "taotie",
+            "date": chrono::Utc::now().to_rfc3339(),
+            "msg_type": msg_type,
+            "version": PROTOCOL_VERSION,
+        });
+        Message {
+            identities: parent.map(|p| p.identities.clone()).unwrap_or_default(),
+            header,
+            parent_header: parent.map(|p| p.header.clone()).unwrap_or(json!({})),
+            metadata: json!({}),
+            content,
+        }
+    }
+}
+
+impl Shell {
+    async fn serve(&mut self, mut socket: RouterSocket) -> anyhow::Result<()> {
+        let kernel = self.kernel.clone();
+        loop {
+            let Some(msg) = kernel.recv(&mut socket).await? else {
+                continue;
+            };
+            kernel.publish_status("busy", Some(&msg)).await?;
+            match msg.msg_type() {
+                "kernel_info_request" => {
+                    kernel
+                        .reply(&mut socket, &msg, "kernel_info_reply", kernel_info())
+                        .await?
+                }
+                "execute_request" => {
+                    let content = self.execute(&msg).await?;
+                    kernel
+                        .reply(&mut socket, &msg, "execute_reply", content)
+                        .await?;
+                }
+                "is_complete_request" => {
+                    kernel
+                        .reply(
+                            &mut socket,
+                            &msg,
+                            "is_complete_reply",
+                            json!({ "status": "complete" }),
+                        )
+                        .await?;
+                }
+                "complete_request" => {
+                    let content = complete(&msg.content);
+                    kernel
+                        .reply(&mut socket, &msg, "complete_reply", content)
+                        .await?;
+                }
+                "comm_info_request" => {
+                    kernel
+                        .reply(
+                            &mut socket,
+                            &msg,
+                            "comm_info_reply",
+                            json!({ "status": "ok", "comms": {} }),
+                        )
+                        .await?;
+                }
+                "shutdown_request" => {
+                    let content = json!({ "status": "ok", "restart": msg.content["restart"] });
+                    kernel
+                        .reply(&mut socket, &msg, "shutdown_reply", content)
+                        .await?;
+                    kernel.publish_status("idle", Some(&msg)).await?;
+                    return Ok(());
+                }
+                msg_type => eprintln!("Unsupported shell message: {}", msg_type),
+            }
+            kernel.publish_status("idle", Some(&msg)).await?;
+        }
+    }
+
+    /// Run the commands of a cell one by one, stop at the first error.
+    async fn execute(&mut self, msg: &Message) -> anyhow::Result<Value> {
+        let kernel = self.kernel.clone();
+        let code = msg.content["code"].as_str().unwrap_or_default();
+        let silent = msg.content["silent"].as_bool().unwrap_or(false);
+        if !silent {
+            self.execution_count += 1;
+        }
+        let count = self.execution_count;
+        if !silent {
+            kernel
+                .publish(
+                    Some(msg),
+                    "execute_input",
+                    json!({ "code": code, "execution_count": count }),
+                )
+                .await?;
+        }
+
+        for cmd in split_cell(code) {
+            let ret = tokio::select! {
+                ret = self.run(cmd) => ret,
+                _ = kernel.interrupt.notified() => Err(anyhow!("Query cancelled")),
+            };
+            let output = match ret {
+                Ok(output) => output,
+                Err(err) => {
+                    let error = json!({
+                        "ename": "Error",
+                        "evalue": err.to_string(),
+                        "traceback": [err.to_string()],
+                    });
+                    kernel.publish(Some(msg), "error", error.clone()).await?;
+                    let mut content = json!({ "status": "error", "execution_count": count });
+                    content
+                        .as_object_mut()
+                        .unwrap()
+                        .extend(error.as_object().unwrap().clone());
+                    return Ok(content);
+                }
+            };
+            if silent {
+                continue;
+            }
+            let data = match output {
+                CellOutput::Text(text) if text.is_empty() => continue,
+                CellOutput::Text(text) => json!({ "text/plain": text }),
+                CellOutput::Table { text, html } => {
+                    json!({ "text/plain": text, "text/html": html })
+                }
+            };
+            let content = json!({ "execution_count": count, "data": data, "metadata": {} });
+            kernel.publish(Some(msg), "execute_result", content).await?;
+        }
+
+        Ok(json!({ "status": "ok", "execution_count": count, "user_expressions": {} }))
+    }
+
+    async fn run(&mut self, args: Vec<String>) -> anyhow::Result<CellOutput> {
+        let cmd = parse_command(args)?;
+        let backend = &self.backend;
+        let settings = backend.settings();
+        match cmd {
+            ReplCommand::List(_) => table(backend.list().await?, settings).await,
+            ReplCommand::Schema(opts) => table(backend.schema(&opts.name).await?, settings).await,
+            ReplCommand::Describe(opts) if opts.fast => {
+                table(backend.describe_fast(&opts.name).await?, settings).await
+            }
+            ReplCommand::Describe(opts) => {
+                table(backend.describe(&opts.name).await?, settings).await
+            }
+            ReplCommand::Head(opts) => {
+                let data = backend.head(&opts.name, opts.size()).await?;
+                table(data, settings).await
+            }
+            ReplCommand::Tail(opts) => {
+                let data = backend.tail(&opts.name, opts.size(), opts.order_by.as_deref());
+                table(data.await?, settings).await
+            }
+            ReplCommand::Top(opts) => {
+                let data = backend.top(&opts.name, &opts.by, opts.size());
+                table(data.await?, settings).await
+            }
+            ReplCommand::Freq(opts) => table(backend.freq(&opts).await?, settings).await,
+            ReplCommand::Sql(opts) => table(backend.sql(&opts.query).await?, settings).await,
+            cmd => Ok(CellOutput::Text(cmd.execute(&mut self.backend).await?)),
+        }
+    }
+}
+
+async fn table(data: impl ReplDisplay, settings: &ReplSettings) -> anyhow::Result<CellOutput> {
+    let schema = data.arrow_schema();
+    // statements like CREATE TABLE have no output
+    if schema.fields().is_empty() {
+        return Ok(CellOutput::Text(String::new()));
+    }
+    // only the shown rows are read, a large result would not fit in the kernel
+    let (batches, truncated) = data.capped_batches(settings.max_rows).await?;
+    let schema = batches.first().map_or(schema, |batch| batch.schema());
+    let batch = concat_batches(&schema, &batches)?;
+    let html = html_table(&batch, truncated)?;
+    let mut text = batch.display(settings).await?;
+    if truncated {
+        text.push_str(&format!("\n{}", MORE_ROWS));
+    }
+    Ok(CellOutput::Table { text, html })
+}
+
+impl Message {
+    fn parse(frames: Vec<Bytes>, signer: &Signer) -> anyhow::Result<Self> {
+        let pos = frames
+            .iter()
+            .position(|f| f.as_ref() == DELIMITER)
+            .ok_or_else(|| anyhow!("missing delimiter"))?;
+        let identities = frames[..pos].to_vec();
+        let parts = &frames[pos + 1..];
+        if parts.len() < 5 {
+            bail!("expect signature, header, parent header, metadata and content");
+        }
+        let signature = String::from_utf8_lossy(&parts[0]);
+        if signer.sign(&parts[1..5]) != signature {
+            bail!("invalid signature");
+        }
+        Ok(Self {
+            identities,
+            header: serde_json::from_slice(&parts[1])?,
+            parent_header: serde_json::from_slice(&parts[2])?,
+            metadata: serde_json::from_slice(&parts[3])?,
+            content: serde_json::from_slice(&parts[4])?,
+        })
+    }
+
+    fn msg_type(&self) -> &str {
+        self.header["msg_type"].as_str().unwrap_or_default()
+    }
+
+    fn to_zmq(&self, signer: &Signer) -> anyhow::Result<ZmqMessage> {
+        let parts = [
+            &self.header,
+            &self.parent_header,
+            &self.metadata,
+            &self.content,
+        ]
+        .into_iter()
+        .map(|v| serde_json::to_vec(v).map(Bytes::from))
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let mut frames = self.identities.clone();
+        frames.push(Bytes::from_static(DELIMITER));
+        frames.push(Bytes::from(signer.sign(&parts)));
+        frames.extend(parts);
+        ZmqMessage::try_from(frames).map_err(|err| anyhow!("{}", err))
+    }
+}
+
+impl Signer {
+    fn new(key: &str) -> Self {
+        let key = (!key.is_empty())
+            .then(|| Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("hmac accepts any key"));
+        Self { key }
+    }
+
+    /// Signature of the header, parent header, metadata and content frames, empty if no key.
+    fn sign(&self, parts: &[Bytes]) -> String {
+        let Some(key) = &self.key else {
+            return String::new();
+        };
+        let mut mac = key.clone();
+        for part in parts {
+            mac.update(part);
+        }
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+fn kernel_info() -> Value {
+    json!({
+        "status": "ok",
+        "protocol_version": PROTOCOL_VERSION,
+        "implementation": "taotie",
+        "implementation_version": env!("CARGO_PKG_VERSION"),
+        "language_info": {
+            "name": "taotie",
+            "version": env!("CARGO_PKG_VERSION"),
+            "mimetype": "text/x-taotie",
+            "file_extension": ".taotie",
+        },
+        "banner": "Taotie, your dataset exploration REPL",
+        "help_links": [],
+    })
+}
+
+/// Complete the command names at the start of a line. The cursor positions are
+/// counted in code points by Jupyter.
+fn complete(content: &Value) -> Value {
+    let code = content["code"].as_str().unwrap_or_default();
+    let cursor = match content["cursor_pos"].as_u64() {
+        Some(pos) => code
+            .char_indices()
+            .nth(pos as usize)
+            .map_or(code.len(), |(i, _)| i),
+        None => code.len(),
+    };
+    let line_start = code[..cursor].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let prefix = &code[line_start..cursor];
+    let matches = if prefix.contains(char::is_whitespace) {
+        vec![]
+    } else {
+        command_names()
+            .into_iter()
+            .filter(|name| name.starts_with(prefix))
+            .collect()
+    };
+    json!({
+        "status": "ok",
+        "matches": matches,
+        "cursor_start": code[..line_start].chars().count(),
+        "cursor_end": code[..cursor].chars().count(),
+        "metadata": {},
+    })
+}
+
+fn command_names() -> Vec<String> {
+    ReplCommand::command()
+        .get_subcommands()
+        .map(|cmd| cmd.get_name().to_string())
+        .collect()
+}
+
+/// The commands taking a query, which is the rest of the command after the flags
+/// and doesn't need to be quoted.
+const QUERY_COMMANDS: [&str; 2] = ["sql", "explain"];
+
+/// Split a cell into commands, a command starts at a line beginning with a command
+/// name and spans the following lines, so that a long query can be written over
+/// several lines. Arguments are split the same way as the REPL does, except the
+/// query of `sql` and `explain` which is kept as written.
+fn split_cell(code: &str) -> Vec<Vec<String>> {
+    let names = command_names();
+    let mut commands: Vec<String> = vec![];
+    for line in code.lines() {
+        let first = line.split_whitespace().next().unwrap_or_default();
+        match commands.last_mut() {
+            Some(cmd) if !names.iter().any(|name| name == first) => {
+                cmd.push('\n');
+                cmd.push_str(line);
+            }
+            _ if first.is_empty() => {}
+            _ => commands.push(line.to_string()),
+        }
+    }
+
+    let re = Regex::new(r#"("[^"]+"|\S+)"#).unwrap();
+    commands
+        .iter()
+        .map(|cmd| match cmd.trim().split_once(char::is_whitespace) {
+            Some((name, rest)) if QUERY_COMMANDS.contains(&name) => query_args(name, rest),
+            _ => re
+                .find_iter(cmd)
+                .map(|arg| arg.as_str().replace('"', ""))
+                .collect(),
+        })
+        .collect()
+}
+
+/// The name, the leading flags and the query of a query command. A query quoted as a
+/// whole is unquoted, as the REPL does.
+fn query_args(name: &str, rest: &str) -> Vec<String> {
+    let mut args = vec![name.to_string()];
+    let mut query = rest.trim();
+    while query.starts_with('-') {
+        let (flag, tail) = query.split_once(char::is_whitespace).unwrap_or((query, ""));
+        args.push(flag.to_string());
+        query = tail.trim_start();
+    }
+    let query = match query.strip_prefix('"').and_then(|q| q.strip_suffix('"')) {
+        Some(unquoted) if !unquoted.contains('"') => unquoted,
+        _ => query,
+    };
+    args.push(query.to_string());
+    args
+}
+
+fn parse_command(args: Vec<String>) -> anyhow::Result<ReplCommand> {
+    ReplCommand::try_parse_from(std::iter::once(String::new()).chain(args))
+        .map_err(|err| anyhow!(err.render().to_string()))
+}
+
+fn html_table(batch: &RecordBatch, truncated: bool) -> anyhow::Result<String> {
+    let options = FormatOptions::default().with_null("NULL");
+    let formatters = batch
+        .columns()
+        .iter()
+        .map(|col| ArrayFormatter::try_new(col.as_ref(), &options))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut html = String::from("<table>\n<thead>\n<tr>");
+    for field in batch.schema().fields() {
+        html.push_str(&format!("<th>{}</th>", escape(field.name())));
+    }
+    html.push_str("</tr>\n</thead>\n<tbody>\n");
+    for row in 0..batch.num_rows() {
+        html.push_str("<tr>");
+        for formatter in &formatters {
+            let value = formatter.value(row).to_string();
+            html.push_str(&format!("<td>{}</td>", escape(&value)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>");
+    if truncated {
+        html.push_str("\n<p>... more rows, use <code>set max_rows</code> to show more</p>");
+    }
+    Ok(html)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Int32Array, StringArray};
+
+    use super::*;
+
+    fn batch() -> RecordBatch {
+        RecordBatch::try_from_iter([
+            (
+                "name",
+                Arc::new(StringArray::from(vec![Some("<b>薛柯勤</b>"), None])) as _,
+            ),
+            ("n", Arc::new(Int32Array::from(vec![1, 2])) as _),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn complete_should_count_the_cursor_in_code_points() {
+        let code = "sql SELECT * FROM users WHERE name = '薛柯勤'\nhe";
+        let cursor = code.chars().count();
+        let reply = complete(&json!({ "code": code, "cursor_pos": cursor }));
+        assert_eq!(reply["matches"], json!(["head"]));
+        assert_eq!(reply["cursor_start"], cursor - 2);
+        assert_eq!(reply["cursor_end"], cursor);
+
+        // past the end of the code
+        let reply = complete(&json!({ "code": code, "cursor_pos": 1000 }));
+        assert_eq!(reply["matches"], json!(["head"]));
+        assert_eq!(reply["cursor_end"], cursor);
+
+        // inside the multi-byte characters of the first line
+        let reply = complete(&json!({ "code": code, "cursor_pos": 40 }));
+        assert_eq!(reply["matches"], json!([]));
+    }
+
+    #[test]
+    fn split_cell_should_join_the_lines_of_a_command() {
+        let code = "connect assets/users.ndjson --name users\n\nsql SELECT name\n  FROM users\nhead \"my users\" -n 3";
+        let commands = split_cell(code);
+        assert_eq!(
+            commands,
+            [
+                vec!["connect", "assets/users.ndjson", "--name", "users"],
+                vec!["sql", "SELECT name\n  FROM users"],
+                vec!["head", "my users", "-n", "3"],
+            ]
+        );
+        assert!(split_cell("\n  \n").is_empty());
+
+        let mut commands = commands
+            .into_iter()
+            .map(|args| parse_command(args).unwrap());
+        assert!(matches!(commands.next(), Some(ReplCommand::Connect(_))));
+        match commands.next() {
+            Some(ReplCommand::Sql(opts)) => assert_eq!(opts.query, "SELECT name\n  FROM users"),
+            cmd => panic!("not a sql command: {:?}", cmd),
+        }
+        match commands.next() {
+            Some(ReplCommand::Head(opts)) => assert_eq!(opts.name, "my users"),
+            cmd => panic!("not a head command: {:?}", cmd),
+        }
+    }
+
+    #[test]
+    fn split_cell_should_keep_the_queries_as_written() {
+        let code = "sql --background SELECT \"kit number\"\n  FROM juve WHERE name = 'a  b'\n\
+                    explain --analyze --verbose SELECT 1\n\
+                    sql \"SELECT 2\"";
+        let mut commands = split_cell(code)
+            .into_iter()
+            .map(|args| parse_command(args).unwrap());
+        match commands.next() {
+            Some(ReplCommand::Sql(opts)) => {
+                assert!(opts.background);
+                assert_eq!(
+                    opts.query,
+                    "SELECT \"kit number\"\n  FROM juve WHERE name = 'a  b'"
+                );
+            }
+            cmd => panic!("not a sql command: {:?}", cmd),
+        }
+        match commands.next() {
+            Some(ReplCommand::Explain(opts)) => {
+                assert!(opts.analyze && opts.verbose);
+                assert_eq!(opts.query, "SELECT 1");
+            }
+            cmd => panic!("not an explain command: {:?}", cmd),
+        }
+        match commands.next() {
+            Some(ReplCommand::Sql(opts)) => assert_eq!(opts.query, "SELECT 2"),
+            cmd => panic!("not a sql command: {:?}", cmd),
+        }
+    }
+
+    #[test]
+    fn html_table_should_escape_the_values_and_note_the_truncation() {
+        let html = html_table(&batch(), false).unwrap();
+        assert!(html.contains("<th>name</th><th>n</th>"));
+        assert!(html.contains("<td>&lt;b&gt;薛柯勤&lt;/b&gt;</td><td>1</td>"));
+        assert!(html.contains("<td>NULL</td><td>2</td>"));
+        assert!(!html.contains("more rows"));
+
+        let html = html_table(&batch(), true).unwrap();
+        assert!(html.ends_with("<p>... more rows, use <code>set max_rows</code> to show more</p>"));
+    }
+
+    #[test]
+    fn signer_should_sign_with_hmac_sha256() {
+        let parts = [Bytes::from("{}"), Bytes::from("{\"a\":1}")];
+        assert_eq!(Signer::new("").sign(&parts), "");
+        let signature = Signer::new("secret").sign(&parts);
+        assert_eq!(signature.len(), 64);
+        assert_eq!(signature, Signer::new("secret").sign(&parts));
+        assert_ne!(signature, Signer::new("other").sign(&parts));
+    }
+
+    #[test]
+    fn message_should_parse_what_it_sends() {
+        let signer = Signer::new("secret");
+        let msg = Message {
+            identities: vec![Bytes::from("client")],
+            header: json!({ "msg_type": "execute_request" }),
+            parent_header: json!({}),
+            metadata: json!({}),
+            content: json!({ "code": "list" }),
+        };
+        let frames = msg.to_zmq(&signer).unwrap().into_vec();
+        let parsed = Message::parse(frames.clone(), &signer).unwrap();
+        assert_eq!(parsed.identities, msg.identities);
+        assert_eq!(parsed.msg_type(), "execute_request");
+        assert_eq!(parsed.content, msg.content);
+
+        assert!(Message::parse(frames.clone(), &Signer::new("other")).is_err());
+        let without_delimiter = frames.into_iter().filter(|f| f != DELIMITER).collect();
+        assert!(Message::parse(without_delimiter, &signer).is_err());
+    }
+
+    #[tokio::test]
+    async fn table_should_only_read_the_shown_rows() {
+        let ctx = datafusion::prelude::SessionContext::new();
+        let df = ctx.read_batch(batch()).unwrap();
+        let settings = ReplSettings {
+            max_rows: Some(1),
+            ..Default::default()
+        };
+        let CellOutput::Table { text, html } = table(df, &settings).await.unwrap() else {
+            panic!("expect a table");
+        };
+        assert!(text.ends_with(MORE_ROWS));
+        assert!(!html.contains("NULL"));
+    }
+}
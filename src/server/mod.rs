@@ -0,0 +1,9 @@
+mod flight;
+mod http;
+mod kernel;
+mod pg;
+
+pub use flight::{serve_flight, FlightOpts};
+pub use http::{serve_http, ServeOpts};
+pub use kernel::{serve_kernel, KernelOpts};
+pub use pg::{serve_pg, PgOpts};
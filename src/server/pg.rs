@@ -0,0 +1,881 @@
+use std::{error::Error, fmt, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, RecordBatch},
+    compute::cast,
+    datatypes::{
+        DataType, Date32Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, SchemaRef,
+        Time64MicrosecondType, TimeUnit, TimestampMicrosecondType,
+    },
+    util::display::{ArrayFormatter, FormatOptions},
+};
+use async_trait::async_trait;
+use bytes::{BufMut, BytesMut};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use clap::Args;
+use datafusion::{prelude::SessionContext, scalar::ScalarValue, sql::parser::DFParser};
+use futures::{stream, Sink, SinkExt};
+use pgwire::{
+    api::{
+        auth::noop::NoopStartupHandler,
+        portal::{Format, Portal},
+        query::{ExtendedQueryHandler, SimpleQueryHandler},
+        results::{
+            DataRowEncoder, DescribePortalResponse, DescribeStatementResponse, FieldInfo,
+            QueryResponse, Response, Tag,
+        },
+        stmt::{NoopQueryParser, StoredStatement},
+        store::PortalStore,
+        ClientInfo, ClientPortalStore, Type,
+    },
+    error::{ErrorInfo, PgWireError, PgWireResult},
+    messages::{data::DataRow, PgWireBackendMessage},
+    tokio::process_socket,
+    types::ToSqlText,
+};
+use postgres_types::{to_sql_checked, IsNull, Kind, ToSql};
+use tokio::net::TcpListener;
+
+use crate::{
+    backend::DataFusionBackend,
+    cli::{parse_dataset, ConnectOpts},
+    Backend, ReplDisplay,
+};
+
+const UTC: &str = "+00:00";
+
+#[derive(Debug, Clone, Args)]
+pub struct PgOpts {
+    #[arg(long, default_value = "127.0.0.1", help = "The address to listen on")]
+    pub host: String,
+
+    #[arg(short, long, default_value_t = 5432, help = "The port to listen on")]
+    pub port: u16,
+
+    #[arg(
+        short,
+        long = "dataset",
+        value_parser = parse_dataset,
+        help = "Dataset to register before serving, in the form of name=conn, e.g. users=assets/users.ndjson"
+    )]
+    pub datasets: Vec<ConnectOpts>,
+}
+
+/// Answers the Postgres simple and extended query flows with the backend, all
+/// the connections share the same session.
+pub struct PgHandler {
+    backend: Arc<DataFusionBackend>,
+    parser: Arc<NoopQueryParser>,
+}
+
+pub async fn serve_pg(opts: PgOpts) -> anyhow::Result<()> {
+    let mut backend = DataFusionBackend::new();
+    for dataset in &opts.datasets {
+        backend.connect(dataset).await?;
+        println!("Connected to dataset: {}", dataset.name);
+    }
+
+    let listener = TcpListener::bind((opts.host.as_str(), opts.port)).await?;
+    println!(
+        "Taotie Postgres server listening on postgres://{}:{}",
+        opts.host, opts.port
+    );
+    let handler = Arc::new(PgHandler {
+        backend: Arc::new(backend),
+        parser: Arc::new(NoopQueryParser::new()),
+    });
+    accept(listener, handler).await
+}
+
+async fn accept(listener: TcpListener, handler: Arc<PgHandler>) -> anyhow::Result<()> {
+    let startup = Arc::new(NoopStartupHandler);
+    loop {
+        let (socket, _) = listener.accept().await?;
+        let (startup, handler) = (startup.clone(), handler.clone());
+        tokio::spawn(async move {
+            if let Err(err) = process_socket(socket, None, startup, handler.clone(), handler).await
+            {
+                eprintln!("Postgres connection error: {}", err);
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl SimpleQueryHandler for PgHandler {
+    async fn do_query<'a, 'b: 'a, C>(
+        &'b self,
+        client: &mut C,
+        query: &'a str,
+    ) -> PgWireResult<Vec<Response<'a>>>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: fmt::Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let statements = DFParser::parse_sql(query).map_err(user_error)?;
+        let mut responses = Vec::with_capacity(statements.len());
+        for statement in statements {
+            let sql = statement.to_string();
+            if let Some((tag, warning)) = session_command(&sql) {
+                warn(client, warning).await?;
+                responses.push(Response::Execution(tag));
+                continue;
+            }
+            let data = self.backend.sql(&sql).await.map_err(user_error)?;
+            responses.push(into_response(data, &Format::UnifiedText).await?);
+        }
+        Ok(responses)
+    }
+}
+
+#[async_trait]
+impl ExtendedQueryHandler for PgHandler {
+    type Statement = String;
+    type QueryParser = NoopQueryParser;
+
+    fn query_parser(&self) -> Arc<Self::QueryParser> {
+        self.parser.clone()
+    }
+
+    async fn do_query<'a, 'b: 'a, C>(
+        &'b self,
+        client: &mut C,
+        portal: &'a Portal<Self::Statement>,
+        _max_rows: usize,
+    ) -> PgWireResult<Response<'a>>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = Self::Statement>,
+        C::Error: fmt::Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let sql = &portal.statement.statement;
+        if let Some((tag, warning)) = session_command(sql) {
+            warn(client, warning).await?;
+            return Ok(Response::Execution(tag));
+        }
+        if portal.parameter_len() == 0 {
+            let data = self.backend.sql(sql).await.map_err(user_error)?;
+            return into_response(data, &portal.result_column_format).await;
+        }
+
+        let params = self.parameter_types(&portal.statement).await?;
+        let values = (0..portal.parameter_len())
+            .map(|i| decode_parameter(portal, i, &params[i]))
+            .collect::<PgWireResult<Vec<_>>>()?;
+        let df = self
+            .ctx()
+            .sql(sql)
+            .await
+            .and_then(|df| df.with_param_values(values))
+            .map_err(user_error)?;
+        into_response(df, &portal.result_column_format).await
+    }
+
+    async fn do_describe_statement<C>(
+        &self,
+        _client: &mut C,
+        stmt: &StoredStatement<Self::Statement>,
+    ) -> PgWireResult<DescribeStatementResponse>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = Self::Statement>,
+        C::Error: fmt::Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        if session_command(&stmt.statement).is_some() {
+            return Ok(DescribeStatementResponse::new(vec![], vec![]));
+        }
+        let params = self.parameter_types(stmt).await?;
+        let df = self.ctx().sql(&stmt.statement).await.map_err(user_error)?;
+        let fields = field_infos(&df.arrow_schema(), &Format::UnifiedBinary);
+        Ok(DescribeStatementResponse::new(params, fields))
+    }
+
+    async fn do_describe_portal<C>(
+        &self,
+        _client: &mut C,
+        portal: &Portal<Self::Statement>,
+    ) -> PgWireResult<DescribePortalResponse>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = Self::Statement>,
+        C::Error: fmt::Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        if session_command(&portal.statement.statement).is_some() {
+            return Ok(DescribePortalResponse::new(vec![]));
+        }
+        let df = self
+            .ctx()
+            .sql(&portal.statement.statement)
+            .await
+            .map_err(user_error)?;
+        let fields = field_infos(&df.arrow_schema(), &portal.result_column_format);
+        Ok(DescribePortalResponse::new(fields))
+    }
+}
+
+impl PgHandler {
+    fn ctx(&self) -> &SessionContext {
+        &self.backend
+    }
+
+    /// Types of the parameters given by the client, or inferred by DataFusion from
+    /// the placeholders (`$1`, `$2`...) of the query if not given.
+    async fn parameter_types(&self, stmt: &StoredStatement<String>) -> PgWireResult<Vec<Type>> {
+        let df = self.ctx().sql(&stmt.statement).await.map_err(user_error)?;
+        let inferred = df
+            .logical_plan()
+            .get_parameter_types()
+            .map_err(user_error)?;
+        let len = inferred.len().max(stmt.parameter_types.len());
+        let types = (0..len)
+            .map(|i| match stmt.parameter_types.get(i) {
+                Some(ty) if *ty != Type::UNKNOWN => ty.clone(),
+                _ => inferred
+                    .get(&format!("${}", i + 1))
+                    .and_then(|dt| dt.as_ref())
+                    .map(|dt| match pg_type(dt) {
+                        // the parameters are decoded as floats, numeric is only encoded
+                        Type::NUMERIC => Type::FLOAT8,
+                        ty => ty,
+                    })
+                    .unwrap_or(Type::TEXT),
+            })
+            .collect();
+        Ok(types)
+    }
+}
+
+const IGNORED_SET: &str = "SET has no effect, only the datafusion.* settings can be changed";
+const NO_TRANSACTIONS: &str = "Transactions are not supported, each statement runs on its own";
+
+/// Statements that clients send to set up the session (e.g. `SET extra_float_digits = 3`
+/// by JDBC). They have no effect, so they are acknowledged with a warning.
+fn session_command(sql: &str) -> Option<(Tag, Option<&'static str>)> {
+    let sql = sql.trim_start().to_uppercase();
+    let command = sql.split_whitespace().next()?;
+    let ack = match command {
+        "SET" if !sql.contains("DATAFUSION.") => (Tag::new("SET"), Some(IGNORED_SET)),
+        "BEGIN" | "START" => (Tag::new("BEGIN"), Some(NO_TRANSACTIONS)),
+        "COMMIT" | "END" => (Tag::new("COMMIT"), Some(NO_TRANSACTIONS)),
+        "ROLLBACK" => (Tag::new("ROLLBACK"), Some(NO_TRANSACTIONS)),
+        // there is nothing to discard
+        "DISCARD" => (Tag::new("DISCARD ALL"), None),
+        _ => return None,
+    };
+    Some(ack)
+}
+
+/// Send a warning notice to the client, before the response of the statement.
+async fn warn<C>(client: &mut C, warning: Option<&str>) -> PgWireResult<()>
+where
+    C: Sink<PgWireBackendMessage> + Unpin,
+    PgWireError: From<C::Error>,
+{
+    if let Some(warning) = warning {
+        let info = ErrorInfo::new("WARNING".to_string(), "01000".to_string(), warning.into());
+        client
+            .send(PgWireBackendMessage::NoticeResponse(info.into()))
+            .await?;
+    }
+    Ok(())
+}
+
+async fn into_response<'a>(data: impl ReplDisplay, format: &Format) -> PgWireResult<Response<'a>> {
+    let schema = data.arrow_schema();
+    let batches = data.batches().await.map_err(user_error)?;
+    if schema.fields().is_empty() {
+        return Ok(Response::Execution(Tag::new("OK")));
+    }
+
+    let fields = Arc::new(field_infos(&schema, format));
+    let mut rows = Vec::new();
+    for batch in &batches {
+        encode_batch(batch, &fields, &mut rows)?;
+    }
+    Ok(Response::Query(QueryResponse::new(
+        fields,
+        stream::iter(rows),
+    )))
+}
+
+fn field_infos(schema: &SchemaRef, format: &Format) -> Vec<FieldInfo> {
+    schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let format = format.format_for(i);
+            let ty = pg_type(field.data_type());
+            FieldInfo::new(field.name().to_string(), None, None, ty, format)
+        })
+        .collect()
+}
+
+/// Postgres type of an arrow type. Types without a counterpart are sent as text,
+/// decimals and unsigned 64-bit integers, which don't fit in an int8, are numeric.
+fn pg_type(dt: &DataType) -> Type {
+    match dt {
+        DataType::Null => Type::UNKNOWN,
+        DataType::Boolean => Type::BOOL,
+        DataType::Int8 | DataType::UInt8 | DataType::Int16 => Type::INT2,
+        DataType::UInt16 | DataType::Int32 => Type::INT4,
+        DataType::UInt32 | DataType::Int64 => Type::INT8,
+        DataType::Float16 | DataType::Float32 => Type::FLOAT4,
+        DataType::Float64 => Type::FLOAT8,
+        DataType::UInt64 | DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => Type::NUMERIC,
+        DataType::Utf8 | DataType::LargeUtf8 => Type::VARCHAR,
+        DataType::Binary | DataType::LargeBinary | DataType::FixedSizeBinary(_) => Type::BYTEA,
+        DataType::Date32 | DataType::Date64 => Type::DATE,
+        DataType::Time32(_) | DataType::Time64(_) => Type::TIME,
+        DataType::Timestamp(_, None) => Type::TIMESTAMP,
+        DataType::Timestamp(_, Some(_)) => Type::TIMESTAMPTZ,
+        DataType::List(field) | DataType::LargeList(field) => match pg_type(field.data_type()) {
+            Type::BOOL => Type::BOOL_ARRAY,
+            Type::INT2 => Type::INT2_ARRAY,
+            Type::INT4 => Type::INT4_ARRAY,
+            Type::INT8 => Type::INT8_ARRAY,
+            Type::FLOAT4 => Type::FLOAT4_ARRAY,
+            Type::FLOAT8 => Type::FLOAT8_ARRAY,
+            Type::VARCHAR => Type::VARCHAR_ARRAY,
+            _ => Type::TEXT,
+        },
+        _ => Type::TEXT,
+    }
+}
+
+/// The arrow type the values of a column are cast to before being encoded.
+fn wire_type(ty: &Type) -> Option<DataType> {
+    let dt = match *ty {
+        Type::BOOL => DataType::Boolean,
+        Type::INT2 => DataType::Int16,
+        Type::INT4 => DataType::Int32,
+        Type::INT8 => DataType::Int64,
+        Type::FLOAT4 => DataType::Float32,
+        Type::FLOAT8 => DataType::Float64,
+        Type::VARCHAR => DataType::Utf8,
+        Type::BYTEA => DataType::Binary,
+        Type::DATE => DataType::Date32,
+        Type::TIME => DataType::Time64(TimeUnit::Microsecond),
+        Type::TIMESTAMP => DataType::Timestamp(TimeUnit::Microsecond, None),
+        // the instants are kept, a cast to a timestamp without zone would shift them
+        Type::TIMESTAMPTZ => DataType::Timestamp(TimeUnit::Microsecond, Some(UTC.into())),
+        _ => return None,
+    };
+    Some(dt)
+}
+
+fn encode_batch(
+    batch: &RecordBatch,
+    fields: &Arc<Vec<FieldInfo>>,
+    rows: &mut Vec<PgWireResult<DataRow>>,
+) -> PgWireResult<()> {
+    let columns = batch
+        .columns()
+        .iter()
+        .zip(fields.iter())
+        .map(|(col, field)| normalize(col, field.datatype()))
+        .collect::<PgWireResult<Vec<_>>>()?;
+
+    for row in 0..batch.num_rows() {
+        let mut encoder = DataRowEncoder::new(fields.clone());
+        for (col, field) in columns.iter().zip(fields.iter()) {
+            encode_value(&mut encoder, col, field.datatype(), row)?;
+        }
+        rows.push(encoder.finish());
+    }
+    Ok(())
+}
+
+/// Cast the column to the arrow type matching its postgres type, lists are cast
+/// element-wise.
+fn normalize(col: &ArrayRef, ty: &Type) -> PgWireResult<ArrayRef> {
+    let target = match ty.kind() {
+        Kind::Array(inner) => match (col.data_type(), wire_type(inner)) {
+            (DataType::List(field) | DataType::LargeList(field), Some(dt)) => {
+                DataType::List(Arc::new(field.as_ref().clone().with_data_type(dt)))
+            }
+            _ => return Ok(col.clone()),
+        },
+        _ => match wire_type(ty) {
+            Some(dt) => dt,
+            None => return Ok(col.clone()),
+        },
+    };
+    cast(col, &target).map_err(user_error)
+}
+
+fn encode_value(
+    encoder: &mut DataRowEncoder,
+    col: &ArrayRef,
+    ty: &Type,
+    row: usize,
+) -> PgWireResult<()> {
+    if col.is_null(row) {
+        return encoder.encode_field(&None::<i8>);
+    }
+    match *ty {
+        Type::BOOL => encoder.encode_field(&col.as_boolean().value(row)),
+        Type::INT2 => encoder.encode_field(&col.as_primitive::<Int16Type>().value(row)),
+        Type::INT4 => encoder.encode_field(&col.as_primitive::<Int32Type>().value(row)),
+        Type::INT8 => encoder.encode_field(&col.as_primitive::<Int64Type>().value(row)),
+        Type::FLOAT4 => encoder.encode_field(&col.as_primitive::<Float32Type>().value(row)),
+        Type::FLOAT8 => encoder.encode_field(&col.as_primitive::<Float64Type>().value(row)),
+        Type::VARCHAR => encoder.encode_field(&col.as_string::<i32>().value(row)),
+        Type::BYTEA => encoder.encode_field(&col.as_binary::<i32>().value(row)),
+        Type::DATE => encoder.encode_field(&col.as_primitive::<Date32Type>().value_as_date(row)),
+        Type::TIME => {
+            let time = col
+                .as_primitive::<Time64MicrosecondType>()
+                .value_as_time(row);
+            encoder.encode_field(&time)
+        }
+        Type::TIMESTAMP => {
+            let ts = col
+                .as_primitive::<TimestampMicrosecondType>()
+                .value_as_datetime(row);
+            encoder.encode_field(&ts)
+        }
+        Type::TIMESTAMPTZ => {
+            let ts = col
+                .as_primitive::<TimestampMicrosecondType>()
+                .value_as_datetime(row)
+                // the values of a timestamp with zone are UTC
+                .map(|ts| ts.and_utc());
+            encoder.encode_field(&ts)
+        }
+        Type::BOOL_ARRAY => encode_list(encoder, col, row, |a, i| a.as_boolean().value(i)),
+        Type::INT2_ARRAY => encode_list(encoder, col, row, |a, i| {
+            a.as_primitive::<Int16Type>().value(i)
+        }),
+        Type::INT4_ARRAY => encode_list(encoder, col, row, |a, i| {
+            a.as_primitive::<Int32Type>().value(i)
+        }),
+        Type::INT8_ARRAY => encode_list(encoder, col, row, |a, i| {
+            a.as_primitive::<Int64Type>().value(i)
+        }),
+        Type::FLOAT4_ARRAY => encode_list(encoder, col, row, |a, i| {
+            a.as_primitive::<Float32Type>().value(i)
+        }),
+        Type::FLOAT8_ARRAY => encode_list(encoder, col, row, |a, i| {
+            a.as_primitive::<Float64Type>().value(i)
+        }),
+        Type::VARCHAR_ARRAY => encode_list(encoder, col, row, |a, i| {
+            a.as_string::<i32>().value(i).to_string()
+        }),
+        Type::NUMERIC => {
+            let formatter = ArrayFormatter::try_new(col.as_ref(), &FormatOptions::default())
+                .map_err(user_error)?;
+            encoder.encode_field(&Numeric(formatter.value(row).to_string()))
+        }
+        _ => {
+            // the types without a postgres counterpart
+            let formatter = ArrayFormatter::try_new(col.as_ref(), &FormatOptions::default())
+                .map_err(user_error)?;
+            encoder.encode_field(&formatter.value(row).to_string())
+        }
+    }
+}
+
+fn encode_list<T, F>(
+    encoder: &mut DataRowEncoder,
+    col: &ArrayRef,
+    row: usize,
+    value: F,
+) -> PgWireResult<()>
+where
+    T: ToSql + ToSqlText,
+    F: Fn(&ArrayRef, usize) -> T,
+{
+    let values = col.as_list::<i32>().value(row);
+    let items = (0..values.len())
+        .map(|i| (!values.is_null(i)).then(|| value(&values, i)))
+        .collect::<Vec<_>>();
+    encoder.encode_field(&items)
+}
+
+/// A decimal number as written by arrow, e.g. -12.340, which is encoded as a
+/// postgres numeric in both formats so that no digit is lost.
+#[derive(Debug)]
+struct Numeric(String);
+
+impl ToSql for Numeric {
+    /// The binary numeric: the number of base 10000 digits, the weight of the first
+    /// one, the sign, the number of decimal digits, then the digits.
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        let (negative, number) = match self.0.strip_prefix('-') {
+            Some(number) => (true, number),
+            None => (false, self.0.as_str()),
+        };
+        let (int, frac) = number.split_once('.').unwrap_or((number, ""));
+        if int.is_empty() || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit()) {
+            return Err(format!("Invalid numeric {}", self.0).into());
+        }
+        let scale = frac.len() as u16;
+        // the digits are grouped by 4 on both sides of the point
+        let int = format!("{:0>width$}", int, width = int.len().div_ceil(4) * 4);
+        let frac = format!("{:0<width$}", frac, width = frac.len().div_ceil(4) * 4);
+        let mut digits = int
+            .as_bytes()
+            .chunks(4)
+            .chain(frac.as_bytes().chunks(4))
+            .map(|group| group.iter().fold(0i16, |n, b| n * 10 + (b - b'0') as i16))
+            .collect::<Vec<_>>();
+        let mut weight = (int.len() / 4) as i16 - 1;
+        let leading = digits.iter().take_while(|d| **d == 0).count();
+        digits.drain(..leading);
+        weight -= leading as i16;
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        if digits.is_empty() {
+            weight = 0;
+        }
+
+        out.put_i16(digits.len() as i16);
+        out.put_i16(weight);
+        out.put_u16(if negative && !digits.is_empty() {
+            0x4000
+        } else {
+            0
+        });
+        out.put_u16(scale);
+        for digit in digits {
+            out.put_i16(digit);
+        }
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::NUMERIC
+    }
+
+    to_sql_checked!();
+}
+
+impl ToSqlText for Numeric {
+    fn to_sql_text(
+        &self,
+        _ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        out.put_slice(self.0.as_bytes());
+        Ok(IsNull::No)
+    }
+}
+
+/// Decode a parameter of the extended query flow, text format is parsed here
+/// since pgwire only decodes the binary format.
+fn decode_parameter(portal: &Portal<String>, idx: usize, ty: &Type) -> PgWireResult<ScalarValue> {
+    if portal.parameter_format.is_text(idx) {
+        let text = portal.parameters[idx]
+            .as_ref()
+            .map(|v| String::from_utf8_lossy(v).to_string());
+        let value = match (text, wire_type(ty)) {
+            (None, Some(dt)) => ScalarValue::try_from(&dt).map_err(user_error)?,
+            (None, None) => ScalarValue::Utf8(None),
+            (Some(text), Some(dt)) if dt != DataType::Utf8 => {
+                ScalarValue::try_from_string(text, &dt).map_err(user_error)?
+            }
+            (Some(text), _) => ScalarValue::Utf8(Some(text)),
+        };
+        return Ok(value);
+    }
+
+    let value = match *ty {
+        Type::BOOL => ScalarValue::Boolean(portal.parameter::<bool>(idx, ty)?),
+        Type::INT2 => ScalarValue::Int16(portal.parameter::<i16>(idx, ty)?),
+        Type::INT4 => ScalarValue::Int32(portal.parameter::<i32>(idx, ty)?),
+        Type::INT8 => ScalarValue::Int64(portal.parameter::<i64>(idx, ty)?),
+        Type::FLOAT4 => ScalarValue::Float32(portal.parameter::<f32>(idx, ty)?),
+        Type::FLOAT8 => ScalarValue::Float64(portal.parameter::<f64>(idx, ty)?),
+        Type::DATE => {
+            let date = portal.parameter::<NaiveDate>(idx, ty)?;
+            ScalarValue::Date32(date.map(Date32Type::from_naive_date))
+        }
+        Type::TIMESTAMP => {
+            let ts = portal.parameter::<NaiveDateTime>(idx, ty)?;
+            let micros = ts.map(|ts| ts.and_utc().timestamp_micros());
+            ScalarValue::TimestampMicrosecond(micros, None)
+        }
+        Type::TIMESTAMPTZ => {
+            let ts = portal.parameter::<DateTime<Utc>>(idx, ty)?;
+            let micros = ts.map(|ts| ts.timestamp_micros());
+            ScalarValue::TimestampMicrosecond(micros, Some(UTC.into()))
+        }
+        _ => ScalarValue::Utf8(portal.parameter::<String>(idx, ty)?),
+    };
+    Ok(value)
+}
+
+fn user_error(err: impl fmt::Display) -> PgWireError {
+    PgWireError::UserError(Box::new(ErrorInfo::new(
+        "ERROR".to_string(),
+        "XX000".to_string(),
+        err.to_string(),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+
+    /// What the server answered to a simple query, read from the wire.
+    #[derive(Debug, Default)]
+    struct Reply {
+        types: Vec<u32>,
+        rows: Vec<Vec<Option<String>>>,
+        /// The values as sent, for the binary format.
+        raw: Vec<Vec<Option<Vec<u8>>>>,
+        notices: Vec<String>,
+        tags: Vec<String>,
+        errors: Vec<String>,
+    }
+
+    async fn connect() -> TcpStream {
+        let mut backend = DataFusionBackend::new();
+        let dataset = parse_dataset("users=assets/users.ndjson").unwrap();
+        backend.connect(&dataset).await.unwrap();
+        let handler = Arc::new(PgHandler {
+            backend: Arc::new(backend),
+            parser: Arc::new(NoopQueryParser::new()),
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(accept(listener, handler));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut body = 196608i32.to_be_bytes().to_vec();
+        body.extend_from_slice(b"user\0taotie\0\0");
+        stream
+            .write_all(&(body.len() as i32 + 4).to_be_bytes())
+            .await
+            .unwrap();
+        stream.write_all(&body).await.unwrap();
+        read_reply(&mut stream).await;
+        stream
+    }
+
+    async fn query(stream: &mut TcpStream, sql: &str) -> Reply {
+        let len = (sql.len() + 5) as i32;
+        let mut msg = vec![b'Q'];
+        msg.extend_from_slice(&len.to_be_bytes());
+        msg.extend_from_slice(sql.as_bytes());
+        msg.push(0);
+        stream.write_all(&msg).await.unwrap();
+        read_reply(stream).await
+    }
+
+    /// Run a query with the extended flow, the results in binary format.
+    async fn query_binary(stream: &mut TcpStream, sql: &str) -> Reply {
+        let mut msg = Vec::new();
+        let parse = [b"\0", sql.as_bytes(), b"\0", &0i16.to_be_bytes()].concat();
+        message(&mut msg, b'P', &parse);
+        // no parameters, one binary format for all the columns
+        let bind = [b"\0\0".as_slice(), &[0, 0, 0, 0, 0, 1, 0, 1]].concat();
+        message(&mut msg, b'B', &bind);
+        message(&mut msg, b'D', b"P\0");
+        message(&mut msg, b'E', b"\0\0\0\0\0");
+        message(&mut msg, b'S', b"");
+        stream.write_all(&msg).await.unwrap();
+        read_reply(stream).await
+    }
+
+    fn message(out: &mut Vec<u8>, kind: u8, body: &[u8]) {
+        out.push(kind);
+        out.extend_from_slice(&(body.len() as i32 + 4).to_be_bytes());
+        out.extend_from_slice(body);
+    }
+
+    /// A binary numeric: the header values then the base 10000 digits.
+    fn numeric(ndigits: i16, weight: i16, sign: u16, scale: u16, digits: &[i16]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&ndigits.to_be_bytes());
+        out.extend_from_slice(&weight.to_be_bytes());
+        out.extend_from_slice(&sign.to_be_bytes());
+        out.extend_from_slice(&scale.to_be_bytes());
+        for digit in digits {
+            out.extend_from_slice(&digit.to_be_bytes());
+        }
+        out
+    }
+
+    /// Read the messages up to ReadyForQuery.
+    async fn read_reply(stream: &mut TcpStream) -> Reply {
+        let mut reply = Reply::default();
+        loop {
+            let kind = stream.read_u8().await.unwrap();
+            let len = stream.read_i32().await.unwrap() as usize;
+            let mut body = vec![0; len - 4];
+            stream.read_exact(&mut body).await.unwrap();
+            let mut body = body.as_slice();
+            match kind {
+                b'Z' => return reply,
+                b'T' => {
+                    for _ in 0..take_i16(&mut body) {
+                        take_cstr(&mut body);
+                        body = &body[6..];
+                        reply
+                            .types
+                            .push(u32::from_be_bytes(body[..4].try_into().unwrap()));
+                        body = &body[12..];
+                    }
+                }
+                b'D' => {
+                    let row: Vec<_> = (0..take_i16(&mut body))
+                        .map(|_| {
+                            let len = i32::from_be_bytes(body[..4].try_into().unwrap());
+                            body = &body[4..];
+                            (len >= 0).then(|| {
+                                let (value, rest) = body.split_at(len as usize);
+                                body = rest;
+                                value.to_vec()
+                            })
+                        })
+                        .collect();
+                    let text = row
+                        .iter()
+                        .map(|v| v.as_ref().map(|v| String::from_utf8_lossy(v).to_string()))
+                        .collect();
+                    reply.rows.push(text);
+                    reply.raw.push(row);
+                }
+                b'C' => reply.tags.push(take_cstr(&mut body)),
+                b'N' | b'E' => {
+                    let mut message = String::new();
+                    while body[0] != 0 {
+                        let field = body[0];
+                        body = &body[1..];
+                        let value = take_cstr(&mut body);
+                        if field == b'M' {
+                            message = value;
+                        }
+                    }
+                    match kind {
+                        b'N' => reply.notices.push(message),
+                        _ => reply.errors.push(message),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn take_i16(body: &mut &[u8]) -> i16 {
+        let value = i16::from_be_bytes(body[..2].try_into().unwrap());
+        *body = &body[2..];
+        value
+    }
+
+    fn take_cstr(body: &mut &[u8]) -> String {
+        let end = body.iter().position(|b| *b == 0).unwrap();
+        let value = String::from_utf8(body[..end].to_vec()).unwrap();
+        *body = &body[end + 1..];
+        value
+    }
+
+    fn text(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    #[tokio::test]
+    async fn pg_should_answer_simple_queries() {
+        let mut stream = connect().await;
+        let sql = "SELECT gender, count(*) AS n FROM users GROUP BY gender ORDER BY gender";
+        let reply = query(&mut stream, sql).await;
+        assert_eq!(reply.types, [Type::VARCHAR.oid(), Type::INT8.oid()]);
+        assert_eq!(reply.rows[0], [text("female"), text("36")]);
+        assert_eq!(reply.rows.len(), 3);
+
+        let reply = query(&mut stream, "SELECT * FROM nope").await;
+        assert_eq!(reply.errors.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn pg_should_warn_about_ignored_session_commands() {
+        let mut stream = connect().await;
+        let reply = query(&mut stream, "SET extra_float_digits = 3").await;
+        assert_eq!(reply.tags, ["SET"]);
+        assert_eq!(reply.notices, [IGNORED_SET]);
+
+        let reply = query(&mut stream, "BEGIN").await;
+        assert_eq!(reply.tags, ["BEGIN"]);
+        assert_eq!(reply.notices, [NO_TRANSACTIONS]);
+
+        let reply = query(&mut stream, "SET datafusion.execution.batch_size = 1024").await;
+        assert!(reply.notices.is_empty());
+        assert!(reply.errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn pg_should_keep_the_instants_of_timestamps_with_zone() {
+        let mut stream = connect().await;
+        let sql = r#"SELECT arrow_cast('2024-01-01T10:00:00+02:00', 'Timestamp(Microsecond, Some("+02:00"))') AS ts"#;
+        let reply = query(&mut stream, sql).await;
+        assert_eq!(reply.types, [Type::TIMESTAMPTZ.oid()]);
+        let ts = reply.rows[0][0].clone().unwrap();
+        assert!(ts.starts_with("2024-01-01 08:00:00"), "{}", ts);
+    }
+
+    #[tokio::test]
+    async fn pg_should_send_large_unsigned_integers_as_numeric() {
+        let mut stream = connect().await;
+        let sql = "SELECT arrow_cast('18446744073709551615', 'UInt64') AS n";
+        let reply = query(&mut stream, sql).await;
+        assert_eq!(reply.types, [Type::NUMERIC.oid()]);
+        assert_eq!(reply.rows[0], [text("18446744073709551615")]);
+
+        let reply = query_binary(&mut stream, sql).await;
+        assert!(reply.errors.is_empty(), "{:?}", reply.errors);
+        assert_eq!(reply.types, [Type::NUMERIC.oid()]);
+        let value = numeric(5, 4, 0, 0, &[1844, 6744, 737, 955, 1615]);
+        assert_eq!(reply.raw[0], [Some(value)]);
+    }
+
+    #[tokio::test]
+    async fn pg_should_send_decimals_as_numeric_in_both_formats() {
+        let mut stream = connect().await;
+        let sql = "SELECT CAST('-12345678901234567.123456789012345678' AS DECIMAL(38, 18)) AS d";
+        let reply = query(&mut stream, sql).await;
+        assert_eq!(reply.types, [Type::NUMERIC.oid()]);
+        assert_eq!(
+            reply.rows[0],
+            [text("-12345678901234567.123456789012345678")]
+        );
+
+        let reply = query_binary(&mut stream, sql).await;
+        assert_eq!(reply.types, [Type::NUMERIC.oid()]);
+        let digits = [1, 2345, 6789, 123, 4567, 1234, 5678, 9012, 3456, 7800];
+        let value = numeric(10, 4, 0x4000, 18, &digits);
+        assert_eq!(reply.raw[0], [Some(value)]);
+    }
+
+    #[test]
+    fn numeric_should_drop_the_zero_digits() {
+        let encode = |value: &str| {
+            let mut out = BytesMut::new();
+            Numeric(value.to_string())
+                .to_sql(&Type::NUMERIC, &mut out)
+                .unwrap();
+            out.to_vec()
+        };
+        assert_eq!(encode("0.0012"), numeric(1, -1, 0, 4, &[12]));
+        assert_eq!(encode("10000"), numeric(1, 1, 0, 0, &[1]));
+        assert_eq!(encode("-0.00"), numeric(0, 0, 0, 2, &[]));
+        assert!(Numeric("1e10".to_string())
+            .to_sql(&Type::NUMERIC, &mut BytesMut::new())
+            .is_err());
+    }
+}
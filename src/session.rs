@@ -0,0 +1,191 @@
+use arrow::array::RecordBatch;
+
+use crate::{
+    backend::DataFusionBackend,
+    cli::{verify_conn_str, ConnectOpts, ReplSettings},
+    Backend, Error, ReplDisplay, Result,
+};
+
+/// A taotie session, to explore datasets from Rust code without the REPL.
+///
+/// The session runs on [`DataFusionBackend`] by default, any [`Backend`] can be
+/// given with [`Taotie::with_backend`].
+///
+/// ```
+/// # #[tokio::main]
+/// # async fn main() -> taotie::Result<()> {
+/// use taotie::Taotie;
+///
+/// let mut taotie = Taotie::new();
+/// taotie.connect("assets/users.ndjson", "users").await?;
+/// let batches = taotie.sql("SELECT count(*) FROM users").await?;
+/// assert_eq!(batches[0].num_rows(), 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Taotie<B = DataFusionBackend> {
+    backend: B,
+}
+
+impl Taotie {
+    pub fn new() -> Self {
+        Self::with_backend(DataFusionBackend::new())
+    }
+}
+
+impl Default for Taotie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: Backend> Taotie<B> {
+    pub fn with_backend(backend: B) -> Self {
+        Self { backend }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// Connect to a dataset and register it as `name`, `conn` is a connection string
+    /// as given to the `connect` command, e.g. `assets/users.ndjson`.
+    pub async fn connect(&mut self, conn: &str, name: &str) -> Result<()> {
+        let opts = ConnectOpts {
+            conn: verify_conn_str(conn).map_err(Error::InvalidConnection)?,
+            table: None,
+            name: name.to_string(),
+        };
+        self.connect_with(&opts).await
+    }
+
+    pub async fn connect_with(&mut self, opts: &ConnectOpts) -> Result<()> {
+        self.backend.connect(opts).await
+    }
+
+    /// The registered datasets, with their names and types.
+    pub async fn list(&self) -> Result<Vec<RecordBatch>> {
+        batches(self.backend.list().await?).await
+    }
+
+    /// The columns of a dataset, with their types and nullability.
+    pub async fn schema(&self, name: &str) -> Result<Vec<RecordBatch>> {
+        batches(self.backend.schema(name).await?).await
+    }
+
+    /// Summary statistics of the columns of a dataset.
+    pub async fn describe(&self, name: &str) -> Result<Vec<RecordBatch>> {
+        batches(self.backend.describe(name).await?).await
+    }
+
+    /// The first `n` rows of a dataset.
+    pub async fn head(&self, name: &str, n: usize) -> Result<Vec<RecordBatch>> {
+        batches(self.backend.head(name, n).await?).await
+    }
+
+    pub async fn sql(&self, query: &str) -> Result<Vec<RecordBatch>> {
+        batches(self.backend.sql(query).await?).await
+    }
+
+    /// The plans of a query, as shown by the `explain` command.
+    pub async fn explain(&self, query: &str, analyze: bool, verbose: bool) -> Result<String> {
+        self.backend.explain(query, analyze, verbose).await
+    }
+
+    /// Change a session setting, as the `set` command does.
+    pub async fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        self.backend.set(key, value).await
+    }
+
+    /// The value of a session setting, or of all of them for `all`.
+    pub async fn show(&self, key: &str) -> Result<Vec<RecordBatch>> {
+        batches(self.backend.show(key).await?).await
+    }
+
+    pub fn settings(&self) -> &ReplSettings {
+        self.backend.settings()
+    }
+}
+
+async fn batches(data: impl ReplDisplay) -> Result<Vec<RecordBatch>> {
+    data.batches().await.map_err(Error::backend)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::{array::RecordBatch, datatypes::Schema};
+
+    use crate::{
+        Backend, BackendError, ConnectOpts, Error, ReplDisplay, ReplSettings, Result, Taotie,
+    };
+
+    /// A backend with the required commands only, which knows no dataset.
+    struct Empty(ReplSettings);
+
+    impl Backend for Empty {
+        async fn connect(&mut self, _opts: &ConnectOpts) -> Result<()> {
+            Err(BackendError::new("read-only").into())
+        }
+
+        async fn list(&self) -> Result<impl ReplDisplay> {
+            Ok(RecordBatch::new_empty(Arc::new(Schema::empty())))
+        }
+
+        async fn schema(&self, name: &str) -> Result<impl ReplDisplay> {
+            Err::<RecordBatch, _>(unknown(name))
+        }
+
+        async fn describe(&self, name: &str) -> Result<impl ReplDisplay> {
+            Err::<RecordBatch, _>(unknown(name))
+        }
+
+        async fn head(&self, name: &str, _size: usize) -> Result<impl ReplDisplay> {
+            Err::<RecordBatch, _>(unknown(name))
+        }
+
+        async fn sql(&self, _sql: &str) -> Result<impl ReplDisplay> {
+            Ok(RecordBatch::new_empty(Arc::new(Schema::empty())))
+        }
+
+        fn settings(&self) -> &ReplSettings {
+            &self.0
+        }
+    }
+
+    fn unknown(name: &str) -> Error {
+        let cause = std::io::Error::new(std::io::ErrorKind::NotFound, name.to_string());
+        BackendError::new(cause).into()
+    }
+
+    #[tokio::test]
+    async fn a_backend_should_only_need_the_required_commands() {
+        let mut session = Taotie::with_backend(Empty(ReplSettings::default()));
+        assert!(session
+            .list()
+            .await
+            .unwrap()
+            .iter()
+            .all(|b| b.num_rows() == 0));
+        let err = session.explain("SELECT 1", false, false).await.unwrap_err();
+        assert!(matches!(err, Error::Unsupported("explain")));
+        let err = session.set("max_rows", "10").await.unwrap_err();
+        assert_eq!(err.to_string(), "set is not supported by this backend");
+
+        let err = session.schema("users").await.unwrap_err();
+        assert_eq!(err.to_string(), "users");
+        assert!(matches!(err, Error::Backend(_)));
+        let err = session.connect("users", "users").await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid connection string: users");
+        let err = session
+            .connect("assets/users.ndjson", "users")
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "read-only");
+    }
+}